@@ -0,0 +1,311 @@
+//! An on-disk cache for the metadata requested from the apis
+//!
+//! Every response body is stored as a json file keyed by the request. An entry
+//! younger than the ttl of its endpoint is used as is, an older one is
+//! revalidated with `If-None-Match` when the server gave an `ETag`.
+
+use std::{
+    fs,
+    future::Future,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use reqwest::{
+    header::{ETAG, IF_NONE_MATCH},
+    Response, StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use sha::{
+    sha1::Sha1,
+    utils::{Digest, DigestExt},
+};
+
+use crate::error::ApiError;
+
+static GLOBAL: OnceLock<HttpCache> = OnceLock::new();
+
+/// The cache directory and how to use it
+///
+/// The functions of `mojang`, `fabric`, `quilt` and `neoforge` use the
+/// [`HttpCache::global`] one, a [`crate::modrinth::ModrinthClient`] uses the
+/// one given to it with `with_cache`.
+#[derive(Debug, Clone)]
+pub struct HttpCache {
+    dir: PathBuf,
+    refresh: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CacheEntry {
+    key: String,
+    etag: Option<String>,
+    /// Unix timestamp in seconds
+    fetched_at: u64,
+    body: String,
+}
+
+/// The result of a (conditional) request
+pub(crate) enum Fetched {
+    NotModified,
+    Modified { body: String, etag: Option<String> },
+}
+
+impl Fetched {
+    pub(crate) async fn from_response(res: Response) -> Result<Self, ApiError> {
+        if res.status() == StatusCode::NOT_MODIFIED {
+            return Ok(Fetched::NotModified);
+        }
+        let etag = res
+            .headers()
+            .get(ETAG)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string());
+        let body = res.text().await?;
+        Ok(Fetched::Modified { body, etag })
+    }
+}
+
+impl HttpCache {
+    pub fn new(dir: impl AsRef<Path>) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+            refresh: false,
+        }
+    }
+
+    /// Always request the servers, the responses are still stored
+    pub fn with_refresh(mut self, refresh: bool) -> Self {
+        self.refresh = refresh;
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Make this the cache used by the free functions of the api modules
+    ///
+    /// Only the first installed cache is used.
+    pub fn install(self) {
+        let _ = GLOBAL.set(self);
+    }
+
+    pub fn global() -> Option<&'static HttpCache> {
+        GLOBAL.get()
+    }
+
+    /// Get the body of `key`, calling `fetch` with the `ETag` to revalidate
+    /// with if the entry is missing or older than `ttl`
+    pub(crate) async fn get<F, Fut>(
+        &self,
+        key: &str,
+        ttl: Duration,
+        fetch: F,
+    ) -> Result<String, ApiError>
+    where
+        F: FnOnce(Option<String>) -> Fut,
+        Fut: Future<Output = Result<Fetched, ApiError>>,
+    {
+        let entry = if self.refresh { None } else { self.load(key) };
+        let now = now();
+        if let Some(entry) = &entry {
+            if now.saturating_sub(entry.fetched_at) < ttl.as_secs() {
+                return Ok(entry.body.clone());
+            }
+        }
+
+        let etag = entry.as_ref().and_then(|entry| entry.etag.clone());
+        let entry = match (fetch(etag).await?, entry) {
+            (Fetched::NotModified, Some(entry)) => CacheEntry {
+                fetched_at: now,
+                ..entry
+            },
+            (Fetched::NotModified, None) => {
+                return Err(ApiError::decode("unexpected 304 without a cached response"))
+            }
+            (Fetched::Modified { body, etag }, _) => CacheEntry {
+                key: key.to_string(),
+                etag,
+                fetched_at: now,
+                body,
+            },
+        };
+        self.store(&entry);
+        Ok(entry.body)
+    }
+
+    /// Forget `key`, e.g. when its body turns out to be unusable
+    pub(crate) fn remove(&self, key: &str) {
+        let _ = fs::remove_file(self.path(key));
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        let hash = Sha1::default().digest(key.as_bytes()).to_hex();
+        self.dir.join(format!("{hash}.json"))
+    }
+
+    fn load(&self, key: &str) -> Option<CacheEntry> {
+        let content = fs::read_to_string(self.path(key)).ok()?;
+        let entry = serde_json::from_str::<CacheEntry>(&content).ok()?;
+        (entry.key == key).then_some(entry)
+    }
+
+    /// The cache is best effort, failing to write it is not an error
+    fn store(&self, entry: &CacheEntry) {
+        let path = self.path(&entry.key);
+        let tmp = path.with_extension("json.tmp");
+        let Ok(content) = serde_json::to_string(entry) else {
+            return;
+        };
+        if fs::create_dir_all(&self.dir).is_ok() && fs::write(&tmp, content).is_ok() {
+            let _ = fs::rename(&tmp, &path);
+        }
+    }
+}
+
+/// The cache key of a request
+pub(crate) fn key(method: &str, url: &str, body: &[u8]) -> String {
+    if body.is_empty() {
+        format!("{method} {url}")
+    } else {
+        format!("{method} {url} {}", String::from_utf8_lossy(body))
+    }
+}
+
+/// Get the body of `key` through `cache`, or just `fetch` it without a cache
+pub(crate) async fn cached<F, Fut>(
+    cache: Option<&HttpCache>,
+    key: &str,
+    ttl: Duration,
+    fetch: F,
+) -> Result<String, ApiError>
+where
+    F: FnOnce(Option<String>) -> Fut,
+    Fut: Future<Output = Result<Fetched, ApiError>>,
+{
+    match cache {
+        Some(cache) => cache.get(key, ttl, fetch).await,
+        None => match fetch(None).await? {
+            Fetched::Modified { body, .. } => Ok(body),
+            Fetched::NotModified => {
+                Err(ApiError::decode("unexpected 304 without a cached response"))
+            }
+        },
+    }
+}
+
+/// `GET` the body of `url` through the global cache
+pub(crate) async fn get_text(url: &str, ttl: Duration) -> Result<String, ApiError> {
+    cached(
+        HttpCache::global(),
+        &key("GET", url, &[]),
+        ttl,
+        |etag| async move {
+            let mut request = reqwest::Client::new().get(url);
+            if let Some(etag) = etag {
+                request = request.header(IF_NONE_MATCH, etag);
+            }
+            Fetched::from_response(request.send().await?.error_for_status()?).await
+        },
+    )
+    .await
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::mock::{MockResponse, MockServer};
+
+    fn cache_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("ice-cache-test-{}-{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    async fn get(cache: &HttpCache, url: &str, ttl: Duration) -> Result<String, ApiError> {
+        cache
+            .get(&key("GET", url, &[]), ttl, |etag| async move {
+                let mut request = reqwest::Client::new().get(url);
+                if let Some(etag) = etag {
+                    request = request.header(IF_NONE_MATCH, etag);
+                }
+                Fetched::from_response(request.send().await?).await
+            })
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_fresh_entry_is_used() {
+        let server = MockServer::start(vec![MockResponse::json(r#"{"a":1}"#)]);
+        let cache = HttpCache::new(cache_dir("fresh"));
+        let url = format!("{}/foo", server.url);
+
+        let ttl = Duration::from_secs(3600);
+        assert_eq!(get(&cache, &url, ttl).await.unwrap(), r#"{"a":1}"#);
+        assert_eq!(get(&cache, &url, ttl).await.unwrap(), r#"{"a":1}"#);
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_stale_entry_is_revalidated() {
+        let server = MockServer::start(vec![
+            MockResponse::json(r#"{"a":1}"#).with_header("etag", "\"v1\""),
+            MockResponse::new(304, ""),
+            MockResponse::json(r#"{"a":2}"#).with_header("etag", "\"v2\""),
+        ]);
+        let cache = HttpCache::new(cache_dir("stale"));
+        let url = format!("{}/foo", server.url);
+
+        assert_eq!(
+            get(&cache, &url, Duration::ZERO).await.unwrap(),
+            r#"{"a":1}"#
+        );
+        assert_eq!(
+            get(&cache, &url, Duration::ZERO).await.unwrap(),
+            r#"{"a":1}"#
+        );
+        assert_eq!(
+            get(&cache, &url, Duration::ZERO).await.unwrap(),
+            r#"{"a":2}"#
+        );
+
+        let requests = server.requests();
+        assert_eq!(requests[0].header("if-none-match"), None);
+        assert_eq!(requests[1].header("if-none-match"), Some("\"v1\""));
+        assert_eq!(requests[2].header("if-none-match"), Some("\"v1\""));
+    }
+
+    #[tokio::test]
+    async fn test_refresh_bypasses_cache() {
+        let server = MockServer::start(vec![
+            MockResponse::json(r#"{"a":1}"#).with_header("etag", "\"v1\""),
+            MockResponse::json(r#"{"a":2}"#),
+        ]);
+        let dir = cache_dir("refresh");
+        let url = format!("{}/foo", server.url);
+
+        let ttl = Duration::from_secs(3600);
+        let cache = HttpCache::new(&dir);
+        assert_eq!(get(&cache, &url, ttl).await.unwrap(), r#"{"a":1}"#);
+        let cache = HttpCache::new(&dir).with_refresh(true);
+        assert_eq!(get(&cache, &url, ttl).await.unwrap(), r#"{"a":2}"#);
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[1].header("if-none-match"), None);
+
+        // the refreshed response is stored
+        let cache = HttpCache::new(&dir);
+        assert_eq!(get(&cache, &url, ttl).await.unwrap(), r#"{"a":2}"#);
+    }
+}
@@ -0,0 +1,627 @@
+//! A client for the CurseForge api, see <https://docs.curseforge.com/rest-api/>
+//!
+//! Every request needs an api key, which is taken from `CURSEFORGE_API_KEY`
+//! unless given with [`CurseForgeClient::with_api_key`].
+
+use std::{collections::HashMap, time::Duration};
+
+use reqwest::{header::IF_NONE_MATCH, RequestBuilder, Response, StatusCode};
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::json;
+use types::{File, ModLoaderType, Project};
+
+use crate::{
+    cache::{self, Fetched, HttpCache},
+    error::ApiError,
+};
+
+pub const DEFAULT_BASE_URL: &str = "https://api.curseforge.com/v1";
+pub const API_KEY_ENV: &str = "CURSEFORGE_API_KEY";
+/// The id of Minecraft among the games on CurseForge
+pub const MINECRAFT_GAME_ID: u32 = 432;
+
+/// How long the responses of the endpoints are used without asking CurseForge
+mod ttl {
+    use std::time::Duration;
+
+    /// Files hardly change once published
+    pub const FILE: Duration = Duration::from_secs(24 * 60 * 60);
+    pub const PROJECT: Duration = Duration::from_secs(60 * 60);
+    /// Anything that answers "what is the latest"
+    pub const LATEST: Duration = Duration::from_secs(10 * 60);
+}
+
+/// The responses of CurseForge wrap everything in `data`
+#[derive(Deserialize)]
+struct Data<T> {
+    data: T,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FingerprintMatches {
+    exact_matches: Vec<FingerprintMatch>,
+}
+
+#[derive(Deserialize)]
+struct FingerprintMatch {
+    file: File,
+}
+
+/// A client for the CurseForge api
+///
+/// With [`CurseForgeClient::with_cache`], the responses are cached on disk,
+/// see [`crate::cache`].
+#[derive(Debug, Clone)]
+pub struct CurseForgeClient {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: Option<String>,
+    cache: Option<HttpCache>,
+}
+
+impl Default for CurseForgeClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CurseForgeClient {
+    pub fn new() -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            base_url: DEFAULT_BASE_URL.to_string(),
+            api_key: std::env::var(API_KEY_ENV)
+                .ok()
+                .filter(|key| !key.is_empty()),
+            cache: None,
+        }
+    }
+
+    /// Use another API base url, e.g. a local stand-in
+    pub fn with_base_url(mut self, base_url: impl AsRef<str>) -> Self {
+        self.base_url = base_url.as_ref().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Set the `x-api-key` sent with every request
+    pub fn with_api_key(mut self, api_key: impl AsRef<str>) -> Self {
+        self.api_key = Some(api_key.as_ref().to_string());
+        self
+    }
+
+    /// Cache the responses in `cache`
+    pub fn with_cache(mut self, cache: HttpCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    fn url(&self, path: impl AsRef<str>) -> String {
+        format!("{}{}", self.base_url, path.as_ref())
+    }
+
+    async fn send(&self, request: RequestBuilder) -> Result<Response, ApiError> {
+        let Some(api_key) = &self.api_key else {
+            return Err(ApiError::InvalidInput(format!(
+                "no CurseForge api key, set {API_KEY_ENV}"
+            )));
+        };
+        let res = request.header("x-api-key", api_key).send().await?;
+        match res.status() {
+            StatusCode::NOT_FOUND => Err(ApiError::NotFound(res.url().to_string())),
+            StatusCode::TOO_MANY_REQUESTS => Err(ApiError::RateLimited { retry_after: None }),
+            status if status.is_client_error() || status.is_server_error() => {
+                Err(ApiError::Server {
+                    status,
+                    url: res.url().to_string(),
+                })
+            }
+            _ => Ok(res),
+        }
+    }
+
+    async fn get_data<T: DeserializeOwned>(
+        &self,
+        url: reqwest::Url,
+        ttl: Duration,
+    ) -> Result<T, ApiError> {
+        self.fetch_data(self.client.get(url), ttl).await
+    }
+
+    async fn post_data<T: DeserializeOwned>(
+        &self,
+        url: impl reqwest::IntoUrl,
+        body: serde_json::Value,
+        ttl: Duration,
+    ) -> Result<T, ApiError> {
+        self.fetch_data(self.client.post(url).json(&body), ttl)
+            .await
+    }
+
+    /// Send a request through the cache and decode the `data` of the response
+    async fn fetch_data<T: DeserializeOwned>(
+        &self,
+        request: RequestBuilder,
+        ttl: Duration,
+    ) -> Result<T, ApiError> {
+        let built = request
+            .try_clone()
+            .expect("requests with json bodies can be cloned")
+            .build()?;
+        let key = cache::key(
+            built.method().as_str(),
+            built.url().as_str(),
+            built.body().and_then(|b| b.as_bytes()).unwrap_or_default(),
+        );
+
+        let body = cache::cached(self.cache.as_ref(), &key, ttl, |etag| async move {
+            let request = match etag {
+                Some(etag) => request.header(IF_NONE_MATCH, etag),
+                None => request,
+            };
+            Fetched::from_response(self.send(request).await?).await
+        })
+        .await?;
+        serde_json::from_str::<Data<T>>(&body)
+            .map(|data| data.data)
+            .map_err(|err| {
+                if let Some(cache) = &self.cache {
+                    cache.remove(&key);
+                }
+                err.into()
+            })
+    }
+
+    /// Get a project from its id
+    pub async fn get_project(&self, id: u32) -> Result<Project, ApiError> {
+        let url = parse_url(&self.url(format!("/mods/{id}")))?;
+        self.get_data(url, ttl::PROJECT).await
+    }
+
+    /// Get a Minecraft project from its slug
+    ///
+    /// Slugs are only unique inside a class, see [`types::class_id`]
+    pub async fn get_project_from_slug(
+        &self,
+        slug: impl AsRef<str>,
+        class_id: u32,
+    ) -> Result<Project, ApiError> {
+        let slug = slug.as_ref();
+        let url = parse_url_with_params(
+            &self.url("/mods/search"),
+            [
+                ("gameId", MINECRAFT_GAME_ID.to_string()),
+                ("classId", class_id.to_string()),
+                ("slug", slug.to_string()),
+            ],
+        )?;
+        self.get_data::<Vec<Project>>(url, ttl::PROJECT)
+            .await?
+            .into_iter()
+            .find(|p| p.slug == slug)
+            .ok_or(ApiError::NotFound(format!("project {slug}")))
+    }
+
+    /// Get projects from their ids in one request
+    pub async fn get_projects(
+        &self,
+        ids: impl IntoIterator<Item = u32>,
+    ) -> Result<Vec<Project>, ApiError> {
+        let ids = ids.into_iter().collect::<Vec<_>>();
+        if ids.is_empty() {
+            return Ok(vec![]);
+        }
+        self.post_data(self.url("/mods"), json!({ "modIds": ids }), ttl::PROJECT)
+            .await
+    }
+
+    /// Get files from their ids in one request
+    pub async fn get_files(
+        &self,
+        ids: impl IntoIterator<Item = u32>,
+    ) -> Result<Vec<File>, ApiError> {
+        let ids = ids.into_iter().collect::<Vec<_>>();
+        if ids.is_empty() {
+            return Ok(vec![]);
+        }
+        self.post_data(
+            self.url("/mods/files"),
+            json!({ "fileIds": ids }),
+            ttl::FILE,
+        )
+        .await
+    }
+
+    /// Get the newest files of a project, optionally filtered by game version
+    /// and loader
+    ///
+    /// Only the first page (the newest 50 files) is returned.
+    pub async fn get_project_files(
+        &self,
+        project_id: u32,
+        game_version: Option<&str>,
+        loader: Option<ModLoaderType>,
+    ) -> Result<Vec<File>, ApiError> {
+        let mut params = vec![("pageSize", "50".to_string())];
+        if let Some(game_version) = game_version {
+            params.push(("gameVersion", game_version.to_string()));
+        }
+        if let Some(loader) = loader {
+            params.push(("modLoaderType", (loader as u8).to_string()));
+        }
+        let url = parse_url_with_params(&self.url(format!("/mods/{project_id}/files")), params)?;
+        self.get_data(url, ttl::LATEST).await
+    }
+
+    /// Get the files matching the fingerprints, see [`fingerprint`]
+    ///
+    /// Fingerprints unknown to CurseForge are missing from the result.
+    pub async fn get_files_from_fingerprints(
+        &self,
+        fingerprints: impl IntoIterator<Item = u32>,
+    ) -> Result<HashMap<u32, File>, ApiError> {
+        let fingerprints = fingerprints.into_iter().collect::<Vec<_>>();
+        if fingerprints.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let matches = self
+            .post_data::<FingerprintMatches>(
+                self.url(format!("/fingerprints/{MINECRAFT_GAME_ID}")),
+                json!({ "fingerprints": fingerprints }),
+                ttl::FILE,
+            )
+            .await?;
+        Ok(matches
+            .exact_matches
+            .into_iter()
+            .map(|m| (m.file.file_fingerprint, m.file))
+            .collect())
+    }
+}
+
+/// The fingerprint CurseForge identifies a file with
+///
+/// It is the 32-bit MurmurHash2 (seed `1`) of the file with the whitespace
+/// bytes (`\t`, `\n`, `\r` and space) removed.
+pub fn fingerprint(bytes: &[u8]) -> u32 {
+    const M: u32 = 0x5bd1e995;
+    const R: u32 = 24;
+
+    let data = bytes
+        .iter()
+        .copied()
+        .filter(|b| !matches!(b, b'\t' | b'\n' | b'\r' | b' '))
+        .collect::<Vec<_>>();
+
+    let mut h = 1 ^ data.len() as u32;
+    let mut chunks = data.chunks_exact(4);
+    for chunk in &mut chunks {
+        let mut k = u32::from_le_bytes(chunk.try_into().unwrap());
+        k = k.wrapping_mul(M);
+        k ^= k >> R;
+        k = k.wrapping_mul(M);
+        h = h.wrapping_mul(M);
+        h ^= k;
+    }
+    let rest = chunks.remainder();
+    if !rest.is_empty() {
+        for (i, b) in rest.iter().enumerate() {
+            h ^= (*b as u32) << (8 * i);
+        }
+        h = h.wrapping_mul(M);
+    }
+    h ^= h >> 13;
+    h = h.wrapping_mul(M);
+    h ^= h >> 15;
+    h
+}
+
+fn parse_url(url: &str) -> Result<reqwest::Url, ApiError> {
+    reqwest::Url::parse(url).map_err(|err| ApiError::InvalidInput(format!("{url}: {err}")))
+}
+
+fn parse_url_with_params<I, K, V>(url: &str, params: I) -> Result<reqwest::Url, ApiError>
+where
+    I: IntoIterator,
+    I::Item: std::borrow::Borrow<(K, V)>,
+    K: AsRef<str>,
+    V: AsRef<str>,
+{
+    reqwest::Url::parse_with_params(url, params)
+        .map_err(|err| ApiError::InvalidInput(format!("{url}: {err}")))
+}
+
+pub mod types {
+    use chrono::{DateTime, Utc};
+    use serde::Deserialize;
+
+    use crate::modrinth::types::VersionType;
+
+    /// The classes of Minecraft projects
+    pub mod class_id {
+        pub const MODS: u32 = 6;
+        pub const RESOURCE_PACKS: u32 = 12;
+        pub const SHADERS: u32 = 6552;
+        pub const DATAPACKS: u32 = 6945;
+    }
+
+    /// A project, called a "mod" by the api whatever its class is
+    #[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+    #[serde(rename_all = "camelCase")]
+    pub struct Project {
+        pub id: u32,
+        pub name: String,
+        pub slug: String,
+        pub summary: String,
+        pub class_id: Option<u32>,
+        pub links: Links,
+    }
+
+    #[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+    #[serde(rename_all = "camelCase")]
+    pub struct Links {
+        pub website_url: Option<String>,
+    }
+
+    #[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+    #[serde(rename_all = "camelCase")]
+    pub struct File {
+        pub id: u32,
+        /// The id of the project of the file
+        pub mod_id: u32,
+        pub display_name: String,
+        pub file_name: String,
+        pub release_type: ReleaseType,
+        pub hashes: Vec<FileHash>,
+        pub file_date: DateTime<Utc>,
+        pub file_length: u64,
+        /// `None` if the author does not allow third-party downloads
+        pub download_url: Option<String>,
+        /// The game versions, and the loaders like `Fabric`
+        pub game_versions: Vec<String>,
+        pub dependencies: Vec<FileDependency>,
+        pub file_fingerprint: u32,
+    }
+
+    impl File {
+        pub fn sha1(&self) -> Option<&str> {
+            self.hashes
+                .iter()
+                .find(|h| h.algo == HashAlgo::Sha1)
+                .map(|h| h.value.as_str())
+        }
+
+        /// What the downloaded file is checked against
+        pub fn checksum(&self) -> ice_util::Checksum {
+            ice_util::Checksum {
+                size: Some(self.file_length),
+                sha1: self.sha1().map(|s| s.to_string()),
+                sha512: None,
+            }
+        }
+
+        /// Whether this file runs on `game_version` with one of `loaders`
+        /// and is on `channel`
+        ///
+        /// Empty `loaders` matches any file, as files of resource packs and
+        /// the like have no loader.
+        pub fn is_compatible(
+            &self,
+            loaders: &[ModLoaderType],
+            game_version: &str,
+            channel: VersionType,
+        ) -> bool {
+            self.game_versions.iter().any(|v| v == game_version)
+                && (loaders.is_empty()
+                    || loaders.iter().any(|l| {
+                        self.game_versions
+                            .iter()
+                            .any(|v| v.eq_ignore_ascii_case(l.name()))
+                    }))
+                && VersionType::from(self.release_type).is_on_channel(channel)
+        }
+    }
+
+    #[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+    pub struct FileHash {
+        pub value: String,
+        pub algo: HashAlgo,
+    }
+
+    #[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+    #[serde(rename_all = "camelCase")]
+    pub struct FileDependency {
+        pub mod_id: u32,
+        pub relation_type: RelationType,
+    }
+
+    #[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    #[serde(from = "u8")]
+    pub enum ReleaseType {
+        Release,
+        Beta,
+        Alpha,
+    }
+
+    impl From<u8> for ReleaseType {
+        fn from(value: u8) -> Self {
+            match value {
+                1 => Self::Release,
+                2 => Self::Beta,
+                _ => Self::Alpha,
+            }
+        }
+    }
+
+    impl From<ReleaseType> for VersionType {
+        fn from(value: ReleaseType) -> Self {
+            match value {
+                ReleaseType::Release => VersionType::Release,
+                ReleaseType::Beta => VersionType::Beta,
+                ReleaseType::Alpha => VersionType::Alpha,
+            }
+        }
+    }
+
+    #[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    #[serde(from = "u8")]
+    pub enum HashAlgo {
+        Sha1,
+        Md5,
+        Unknown(u8),
+    }
+
+    impl From<u8> for HashAlgo {
+        fn from(value: u8) -> Self {
+            match value {
+                1 => Self::Sha1,
+                2 => Self::Md5,
+                value => Self::Unknown(value),
+            }
+        }
+    }
+
+    #[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    #[serde(from = "u8")]
+    pub enum RelationType {
+        EmbeddedLibrary,
+        OptionalDependency,
+        RequiredDependency,
+        Tool,
+        Incompatible,
+        Include,
+        Unknown(u8),
+    }
+
+    impl From<u8> for RelationType {
+        fn from(value: u8) -> Self {
+            match value {
+                1 => Self::EmbeddedLibrary,
+                2 => Self::OptionalDependency,
+                3 => Self::RequiredDependency,
+                4 => Self::Tool,
+                5 => Self::Incompatible,
+                6 => Self::Include,
+                value => Self::Unknown(value),
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    #[repr(u8)]
+    pub enum ModLoaderType {
+        Forge = 1,
+        Fabric = 4,
+        Quilt = 5,
+        NeoForge = 6,
+    }
+
+    impl ModLoaderType {
+        /// The name of the loader in the `game_versions` of a file
+        pub fn name(&self) -> &'static str {
+            match self {
+                Self::Forge => "Forge",
+                Self::Fabric => "Fabric",
+                Self::Quilt => "Quilt",
+                Self::NeoForge => "NeoForge",
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        mock::{MockResponse, MockServer},
+        modrinth::types::VersionType,
+    };
+
+    const FILE: &str = r#"{
+        "id": 5101366,
+        "modId": 238222,
+        "displayName": "jei-1.21-fabric-19.0.0.1",
+        "fileName": "jei-1.21-fabric-19.0.0.1.jar",
+        "releaseType": 1,
+        "hashes": [{ "value": "abc", "algo": 1 }, { "value": "def", "algo": 2 }],
+        "fileDate": "2024-07-01T00:00:00Z",
+        "fileLength": 1024,
+        "downloadUrl": "https://edge.forgecdn.net/files/5101/366/jei-1.21-fabric-19.0.0.1.jar",
+        "gameVersions": ["1.21", "Fabric", "Client", "Server"],
+        "dependencies": [{ "modId": 306612, "relationType": 3 }],
+        "fileFingerprint": 2824650221
+    }"#;
+
+    #[test]
+    fn test_fingerprint() {
+        assert_eq!(fingerprint(b""), 1540447798);
+        assert_eq!(fingerprint(b"hello world"), 2824650221);
+        // whitespace is ignored
+        assert_eq!(fingerprint(b"hello\r\n\tworld"), 2824650221);
+        assert_eq!(
+            fingerprint(b"The quick brown fox jumps over the lazy dog"),
+            3751777527
+        );
+    }
+
+    #[test]
+    fn test_file_is_compatible() {
+        let file = serde_json::from_str::<File>(FILE).unwrap();
+        assert_eq!(file.sha1(), Some("abc"));
+        assert_eq!(
+            file.dependencies[0].relation_type,
+            types::RelationType::RequiredDependency
+        );
+
+        let fabric = [ModLoaderType::Fabric];
+        assert!(file.is_compatible(&fabric, "1.21", VersionType::Release));
+        assert!(!file.is_compatible(&fabric, "1.21.1", VersionType::Release));
+        assert!(!file.is_compatible(&[ModLoaderType::NeoForge], "1.21", VersionType::Release));
+        assert!(file.is_compatible(&[], "1.21", VersionType::Release));
+    }
+
+    #[tokio::test]
+    async fn test_client() {
+        let server = MockServer::start(vec![
+            MockResponse::json(format!(
+                r#"{{ "data": {{ "exactMatches": [{{ "id": 238222, "file": {FILE} }}] }} }}"#
+            )),
+            MockResponse::new(404, ""),
+        ]);
+        let client = CurseForgeClient::new()
+            .with_base_url(&server.url)
+            .with_api_key("test-key");
+
+        let files = client
+            .get_files_from_fingerprints([2824650221, 1])
+            .await
+            .unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[&2824650221].id, 5101366);
+
+        let err = client.get_project(1).await.unwrap_err();
+        assert!(err.is_not_found());
+
+        let requests = server.requests();
+        assert_eq!(requests[0].method, "POST");
+        assert_eq!(requests[0].path, "/fingerprints/432");
+        assert_eq!(requests[0].header("x-api-key"), Some("test-key"));
+        assert_eq!(requests[0].body, r#"{"fingerprints":[2824650221,1]}"#);
+    }
+
+    #[tokio::test]
+    async fn test_client_without_api_key() {
+        let client = CurseForgeClient {
+            api_key: None,
+            ..CurseForgeClient::new().with_base_url("http://127.0.0.1:1")
+        };
+        assert!(matches!(
+            client.get_project(1).await,
+            Err(ApiError::InvalidInput(_))
+        ));
+    }
+}
@@ -0,0 +1,85 @@
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+    time::Duration,
+};
+
+use reqwest::StatusCode;
+
+/// The error of the api functions
+#[derive(Debug)]
+pub enum ApiError {
+    /// The requested resource does not exist
+    NotFound(String),
+    /// Still rate limited after all the retries
+    RateLimited { retry_after: Option<Duration> },
+    /// The request could not be sent, or the response could not be received
+    Network(reqwest::Error),
+    /// The response could not be decoded
+    Decode(Box<dyn Error + Send + Sync>),
+    /// The server responded with an error status
+    Server { status: StatusCode, url: String },
+    /// The request could not be built from the given input
+    InvalidInput(String),
+}
+
+impl ApiError {
+    /// Whether the resource is confirmed to not exist
+    ///
+    /// Only this means "not there", the other errors say nothing about it.
+    pub fn is_not_found(&self) -> bool {
+        matches!(self, ApiError::NotFound(_))
+    }
+
+    pub(crate) fn decode(err: impl Into<Box<dyn Error + Send + Sync>>) -> Self {
+        ApiError::Decode(err.into())
+    }
+}
+
+impl Display for ApiError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotFound(what) => write!(f, "not found: {what}"),
+            Self::RateLimited {
+                retry_after: Some(retry_after),
+            } => write!(f, "rate limited, retry after {}s", retry_after.as_secs()),
+            Self::RateLimited { retry_after: None } => f.write_str("rate limited"),
+            Self::Network(err) => write!(f, "network error: {err}"),
+            Self::Decode(err) => write!(f, "failed to decode response: {err}"),
+            Self::Server { status, url } => write!(f, "server responded {status} for {url}"),
+            Self::InvalidInput(msg) => write!(f, "invalid input: {msg}"),
+        }
+    }
+}
+
+impl Error for ApiError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Network(err) => Some(err),
+            Self::Decode(err) => Some(err.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for ApiError {
+    fn from(err: reqwest::Error) -> Self {
+        if err.is_decode() {
+            return ApiError::Decode(err.into());
+        }
+        match err.status() {
+            Some(StatusCode::NOT_FOUND) => ApiError::NotFound(
+                err.url()
+                    .map(|url| url.to_string())
+                    .unwrap_or_else(|| err.to_string()),
+            ),
+            _ => ApiError::Network(err),
+        }
+    }
+}
+
+impl From<serde_json::Error> for ApiError {
+    fn from(err: serde_json::Error) -> Self {
+        ApiError::Decode(err.into())
+    }
+}
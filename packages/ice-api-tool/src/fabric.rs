@@ -1,13 +1,39 @@
-use types::InstallerMeta;
+use std::time::Duration;
 
-pub async fn get_latest_installer_url() -> Result<String, anyhow::Error> {
-    let res = reqwest::get("https://meta.fabricmc.net/v2/versions/installer")
-        .await?
-        .json::<Vec<InstallerMeta>>()
-        .await?;
+use types::{InstallerMeta, LoaderMeta};
+
+use crate::{cache, error::ApiError};
+
+const INSTALLER_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+const LOADER_TTL: Duration = Duration::from_secs(60 * 60);
+
+pub async fn get_latest_installer_url() -> Result<String, ApiError> {
+    let res = cache::get_text(
+        "https://meta.fabricmc.net/v2/versions/installer",
+        INSTALLER_TTL,
+    )
+    .await?;
+    let res = serde_json::from_str::<Vec<InstallerMeta>>(&res)?;
     res.first()
         .map(|meta| meta.url.clone())
-        .ok_or(anyhow::anyhow!("failed to get latest fabric installer url"))
+        .ok_or(ApiError::NotFound("latest fabric installer".to_string()))
+}
+
+/// Get the latest stable fabric loader version for `game_version`
+pub async fn get_latest_loader_version(game_version: &str) -> Result<String, ApiError> {
+    let res = cache::get_text(
+        &format!("https://meta.fabricmc.net/v2/versions/loader/{game_version}"),
+        LOADER_TTL,
+    )
+    .await?;
+    let res = serde_json::from_str::<Vec<LoaderMeta>>(&res)?;
+    res.iter()
+        .find(|meta| meta.loader.stable)
+        .or(res.first())
+        .map(|meta| meta.loader.version.clone())
+        .ok_or(ApiError::NotFound(format!(
+            "fabric loader for {game_version}"
+        )))
 }
 
 pub mod types {
@@ -20,12 +46,23 @@ pub mod types {
         pub version: String,
         pub stable: bool,
     }
+
+    #[derive(Debug, Deserialize)]
+    pub struct LoaderMeta {
+        pub loader: LoaderVersion,
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct LoaderVersion {
+        pub version: String,
+        pub stable: bool,
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use smol_macros::test;
     use macro_rules_attribute::apply;
+    use smol_macros::test;
 
     use super::*;
 
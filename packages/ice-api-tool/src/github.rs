@@ -0,0 +1,309 @@
+//! A client for the releases of GitHub repositories, see
+//! <https://docs.github.com/en/rest/releases/releases>
+//!
+//! Requests are anonymous unless a token is given with `GITHUB_TOKEN` or
+//! [`GitHubClient::with_token`], which raises the rate limit.
+
+use std::time::Duration;
+
+use reqwest::{
+    header::{ACCEPT, AUTHORIZATION, IF_NONE_MATCH, USER_AGENT},
+    RequestBuilder, Response, StatusCode,
+};
+use serde::de::DeserializeOwned;
+use types::Release;
+
+use crate::{
+    cache::{self, Fetched, HttpCache},
+    error::ApiError,
+};
+
+pub const DEFAULT_BASE_URL: &str = "https://api.github.com";
+pub const TOKEN_ENV: &str = "GITHUB_TOKEN";
+
+/// How long the responses of the endpoints are used without asking GitHub
+mod ttl {
+    use std::time::Duration;
+
+    /// A tagged release hardly changes once published
+    pub const RELEASE: Duration = Duration::from_secs(24 * 60 * 60);
+    /// Anything that answers "what is the latest"
+    pub const LATEST: Duration = Duration::from_secs(10 * 60);
+}
+
+/// A client for the GitHub releases api
+///
+/// With [`GitHubClient::with_cache`], the responses are cached on disk,
+/// see [`crate::cache`].
+#[derive(Debug, Clone)]
+pub struct GitHubClient {
+    client: reqwest::Client,
+    base_url: String,
+    token: Option<String>,
+    cache: Option<HttpCache>,
+}
+
+impl Default for GitHubClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GitHubClient {
+    pub fn new() -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            base_url: DEFAULT_BASE_URL.to_string(),
+            token: std::env::var(TOKEN_ENV)
+                .ok()
+                .filter(|token| !token.is_empty()),
+            cache: None,
+        }
+    }
+
+    /// Use another API base url, e.g. a local stand-in
+    pub fn with_base_url(mut self, base_url: impl AsRef<str>) -> Self {
+        self.base_url = base_url.as_ref().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Set the token sent with every request
+    pub fn with_token(mut self, token: impl AsRef<str>) -> Self {
+        self.token = Some(token.as_ref().to_string());
+        self
+    }
+
+    /// Cache the responses in `cache`
+    pub fn with_cache(mut self, cache: HttpCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    fn url(&self, path: impl AsRef<str>) -> String {
+        format!("{}{}", self.base_url, path.as_ref())
+    }
+
+    async fn send(&self, request: RequestBuilder) -> Result<Response, ApiError> {
+        let request = request
+            .header(ACCEPT, "application/vnd.github+json")
+            .header(USER_AGENT, "AzurIce/ice");
+        let request = match &self.token {
+            Some(token) => request.header(AUTHORIZATION, format!("Bearer {token}")),
+            None => request,
+        };
+        let res = request.send().await?;
+        let rate_limited = res
+            .headers()
+            .get("x-ratelimit-remaining")
+            .is_some_and(|remaining| remaining == "0");
+        match res.status() {
+            StatusCode::NOT_FOUND => Err(ApiError::NotFound(res.url().to_string())),
+            StatusCode::TOO_MANY_REQUESTS => Err(ApiError::RateLimited { retry_after: None }),
+            StatusCode::FORBIDDEN if rate_limited => {
+                Err(ApiError::RateLimited { retry_after: None })
+            }
+            status if status.is_client_error() || status.is_server_error() => {
+                Err(ApiError::Server {
+                    status,
+                    url: res.url().to_string(),
+                })
+            }
+            _ => Ok(res),
+        }
+    }
+
+    /// `GET` `path` through the cache and decode the response
+    async fn get_json<T: DeserializeOwned>(
+        &self,
+        path: impl AsRef<str>,
+        ttl: Duration,
+    ) -> Result<T, ApiError> {
+        let url = reqwest::Url::parse(&self.url(path))
+            .map_err(|err| ApiError::InvalidInput(err.to_string()))?;
+        let key = cache::key("GET", url.as_str(), &[]);
+
+        let body = cache::cached(self.cache.as_ref(), &key, ttl, |etag| async move {
+            let request = self.client.get(url);
+            let request = match etag {
+                Some(etag) => request.header(IF_NONE_MATCH, etag),
+                None => request,
+            };
+            Fetched::from_response(self.send(request).await?).await
+        })
+        .await?;
+        serde_json::from_str(&body).map_err(|err| {
+            if let Some(cache) = &self.cache {
+                cache.remove(&key);
+            }
+            err.into()
+        })
+    }
+
+    /// Get the latest release of `repo` (`owner/name`)
+    ///
+    /// Drafts and pre-releases are never the latest one.
+    pub async fn get_latest_release(&self, repo: &str) -> Result<Release, ApiError> {
+        self.get_json(format!("/repos/{repo}/releases/latest"), ttl::LATEST)
+            .await
+    }
+
+    /// Get the published releases of `repo` (`owner/name`), newest first
+    ///
+    /// Drafts and pre-releases are left out, like in
+    /// [`GitHubClient::get_latest_release`].
+    pub async fn get_releases(&self, repo: &str) -> Result<Vec<Release>, ApiError> {
+        let releases: Vec<Release> = self
+            .get_json(format!("/repos/{repo}/releases?per_page=100"), ttl::LATEST)
+            .await?;
+        Ok(releases
+            .into_iter()
+            .filter(|release| !release.draft && !release.prerelease)
+            .collect())
+    }
+
+    /// Get the release of `repo` (`owner/name`) tagged `tag`
+    pub async fn get_release_by_tag(&self, repo: &str, tag: &str) -> Result<Release, ApiError> {
+        self.get_json(format!("/repos/{repo}/releases/tags/{tag}"), ttl::RELEASE)
+            .await
+    }
+}
+
+/// Whether `name` matches the glob `pattern`, where `*` matches any run of
+/// characters and `?` any single one
+pub fn matches_pattern(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let name = name.chars().collect::<Vec<_>>();
+
+    // The position after the last `*`, and the position in `name` it matched up to
+    let mut star = None;
+    let (mut p, mut n) = (0, 0);
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p + 1, n));
+                p += 1;
+            }
+            Some(c) if *c == '?' || *c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((star_p, star_n)) => {
+                    p = star_p;
+                    n = star_n + 1;
+                    star = Some((star_p, star_n + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+pub mod types {
+    use chrono::{DateTime, Utc};
+    use serde::Deserialize;
+
+    #[derive(Debug, Clone, Deserialize)]
+    pub struct Release {
+        pub id: u64,
+        pub tag_name: String,
+        pub name: Option<String>,
+        pub draft: bool,
+        pub prerelease: bool,
+        pub published_at: Option<DateTime<Utc>>,
+        pub assets: Vec<Asset>,
+    }
+
+    #[derive(Debug, Clone, Deserialize)]
+    pub struct Asset {
+        pub id: u64,
+        pub name: String,
+        pub size: u64,
+        pub browser_download_url: String,
+    }
+
+    impl Release {
+        /// The first asset whose name matches `pattern`, see
+        /// [`super::matches_pattern`]
+        pub fn find_asset(&self, pattern: &str) -> Option<&Asset> {
+            self.assets
+                .iter()
+                .find(|asset| super::matches_pattern(pattern, &asset.name))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::mock::{MockResponse, MockServer};
+
+    const RELEASE: &str = r#"{
+        "id": 1,
+        "tag_name": "v1.2.0",
+        "name": "1.2.0",
+        "draft": false,
+        "prerelease": false,
+        "published_at": "2024-07-01T00:00:00Z",
+        "assets": [
+            { "id": 10, "name": "foo-1.2.0-sources.jar", "size": 10, "browser_download_url": "https://example.com/foo-1.2.0-sources.jar" },
+            { "id": 11, "name": "foo-1.2.0.jar", "size": 20, "browser_download_url": "https://example.com/foo-1.2.0.jar" }
+        ]
+    }"#;
+
+    #[test]
+    fn test_matches_pattern() {
+        assert!(matches_pattern("*.jar", "foo-1.2.0.jar"));
+        assert!(matches_pattern("foo-?.?.?.jar", "foo-1.2.0.jar"));
+        assert!(matches_pattern("foo-*-fabric.jar", "foo-1.2-fabric.jar"));
+        assert!(matches_pattern("*", ""));
+        assert!(!matches_pattern("*.jar", "foo.jar.asc"));
+        assert!(!matches_pattern("foo-*-fabric.jar", "foo-1.2-forge.jar"));
+        assert!(!matches_pattern("foo", "foobar"));
+    }
+
+    #[tokio::test]
+    async fn test_client() {
+        let server = MockServer::start(vec![MockResponse::json(RELEASE)]);
+        let client = GitHubClient::new()
+            .with_base_url(&server.url)
+            .with_token("test-token");
+
+        let release = client.get_latest_release("owner/foo").await.unwrap();
+        assert_eq!(release.tag_name, "v1.2.0");
+        let asset = release.find_asset("foo-?.?.?.jar").unwrap();
+        assert_eq!(asset.id, 11);
+
+        let requests = server.requests();
+        assert_eq!(requests[0].path, "/repos/owner/foo/releases/latest");
+        assert_eq!(
+            requests[0].header("authorization"),
+            Some("Bearer test-token")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_releases() {
+        let prerelease = RELEASE
+            .replace(r#""id": 1,"#, r#""id": 2,"#)
+            .replace("v1.2.0", "v1.3.0-beta")
+            .replace(r#""prerelease": false"#, r#""prerelease": true"#);
+        let server = MockServer::start(vec![MockResponse::json(format!(
+            "[{prerelease}, {RELEASE}]"
+        ))]);
+        let client = GitHubClient::new().with_base_url(&server.url);
+
+        let releases = client.get_releases("owner/foo").await.unwrap();
+        assert_eq!(releases.len(), 1);
+        assert_eq!(releases[0].tag_name, "v1.2.0");
+        assert_eq!(
+            server.requests()[0].path,
+            "/repos/owner/foo/releases?per_page=100"
+        );
+    }
+}
@@ -1,5 +1,13 @@
+pub mod cache;
+pub mod curseforge;
+pub mod error;
+pub mod fabric;
+pub mod github;
+pub mod maven;
 pub mod modrinth;
 pub mod mojang;
-pub mod fabric;
-pub mod quilt;
 pub mod neoforge;
+pub mod quilt;
+
+#[cfg(any(test, feature = "mock"))]
+pub mod mock;
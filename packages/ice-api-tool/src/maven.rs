@@ -0,0 +1,213 @@
+//! Artifacts in Maven repositories
+//!
+//! A repository is only a base url, so anything serving the standard layout
+//! (including a local stand-in) can be used.
+
+use std::{
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+    time::Duration,
+};
+
+use serde::Deserialize;
+
+use crate::{cache, error::ApiError};
+
+/// How long `maven-metadata.xml` is used without asking the repository
+const METADATA_TTL: Duration = Duration::from_secs(10 * 60);
+/// Published artifacts never change
+const CHECKSUM_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// The coordinates of a jar artifact, `group:artifact:version[:classifier]`
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct Coordinates {
+    pub group: String,
+    pub artifact: String,
+    pub version: String,
+    pub classifier: Option<String>,
+}
+
+impl Coordinates {
+    /// The same artifact with another version
+    pub fn with_version(&self, version: impl Into<String>) -> Self {
+        Self {
+            version: version.into(),
+            ..self.clone()
+        }
+    }
+
+    /// The file name of the jar, `artifact-version[-classifier].jar`
+    pub fn filename(&self) -> String {
+        match &self.classifier {
+            Some(classifier) => format!("{}-{}-{classifier}.jar", self.artifact, self.version),
+            None => format!("{}-{}.jar", self.artifact, self.version),
+        }
+    }
+
+    /// The path of the directory holding every version of the artifact
+    fn artifact_path(&self) -> String {
+        format!("{}/{}", self.group.replace('.', "/"), self.artifact)
+    }
+}
+
+impl FromStr for Coordinates {
+    type Err = ApiError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts = s.split(':').collect::<Vec<_>>();
+        if parts.iter().any(|part| part.is_empty()) {
+            return Err(ApiError::InvalidInput(format!(
+                "{s}: empty part in maven coordinates"
+            )));
+        }
+        match parts[..] {
+            [group, artifact, version] | [group, artifact, version, _] => Ok(Self {
+                group: group.to_string(),
+                artifact: artifact.to_string(),
+                version: version.to_string(),
+                classifier: parts.get(3).map(|s| s.to_string()),
+            }),
+            _ => Err(ApiError::InvalidInput(format!(
+                "{s}: expected group:artifact:version[:classifier]"
+            ))),
+        }
+    }
+}
+
+impl Display for Coordinates {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.group, self.artifact, self.version)?;
+        if let Some(classifier) = &self.classifier {
+            write!(f, ":{classifier}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The `maven-metadata.xml` of an artifact
+#[derive(Debug, Clone, Deserialize)]
+pub struct Metadata {
+    pub versioning: Versioning,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Versioning {
+    pub latest: Option<String>,
+    pub release: Option<String>,
+    #[serde(default)]
+    pub versions: Versions,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Versions {
+    /// Oldest first
+    #[serde(default)]
+    pub version: Vec<String>,
+}
+
+impl Metadata {
+    /// The newest release version, the last listed one if `release` is missing
+    pub fn release(&self) -> Option<&str> {
+        self.versioning.release.as_deref().or(self
+            .versioning
+            .versions
+            .version
+            .last()
+            .map(|s| s.as_str()))
+    }
+}
+
+/// The url of the jar of `coordinates` in `repository`
+pub fn artifact_url(repository: &str, coordinates: &Coordinates) -> String {
+    format!(
+        "{}/{}/{}/{}",
+        repository.trim_end_matches('/'),
+        coordinates.artifact_path(),
+        coordinates.version,
+        coordinates.filename()
+    )
+}
+
+/// Get the `maven-metadata.xml` of the artifact of `coordinates`
+pub async fn get_metadata(
+    repository: &str,
+    coordinates: &Coordinates,
+) -> Result<Metadata, ApiError> {
+    let url = format!(
+        "{}/{}/maven-metadata.xml",
+        repository.trim_end_matches('/'),
+        coordinates.artifact_path()
+    );
+    let body = cache::get_text(&url, METADATA_TTL).await?;
+    quick_xml::de::from_str(&body).map_err(ApiError::decode)
+}
+
+/// Get the sha1 the repository publishes next to the jar of `coordinates`
+pub async fn get_sha1(repository: &str, coordinates: &Coordinates) -> Result<String, ApiError> {
+    let url = format!("{}.sha1", artifact_url(repository, coordinates));
+    let body = cache::get_text(&url, CHECKSUM_TTL).await?;
+    // Some repositories append the file name after the hash
+    body.split_whitespace()
+        .next()
+        .map(|hash| hash.to_lowercase())
+        .ok_or(ApiError::decode(format!("empty checksum at {url}")))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_coordinates() {
+        let coordinates = "com.example.mods:foo:1.2.0".parse::<Coordinates>().unwrap();
+        assert_eq!(coordinates.group, "com.example.mods");
+        assert_eq!(coordinates.filename(), "foo-1.2.0.jar");
+        assert_eq!(
+            artifact_url("https://maven.example.com/releases/", &coordinates),
+            "https://maven.example.com/releases/com/example/mods/foo/1.2.0/foo-1.2.0.jar"
+        );
+
+        let classified = "com.example:foo:1.2.0:fabric"
+            .parse::<Coordinates>()
+            .unwrap();
+        assert_eq!(classified.filename(), "foo-1.2.0-fabric.jar");
+        assert_eq!(classified.to_string(), "com.example:foo:1.2.0:fabric");
+        assert_eq!(
+            classified.with_version("1.3.0").to_string(),
+            "com.example:foo:1.3.0:fabric"
+        );
+
+        assert!("com.example:foo".parse::<Coordinates>().is_err());
+        assert!("com.example::1.0".parse::<Coordinates>().is_err());
+    }
+
+    #[test]
+    fn test_metadata() {
+        let metadata = quick_xml::de::from_str::<Metadata>(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<metadata>
+  <groupId>com.example</groupId>
+  <artifactId>foo</artifactId>
+  <versioning>
+    <latest>1.3.0-SNAPSHOT</latest>
+    <release>1.2.0</release>
+    <versions>
+      <version>1.1.0</version>
+      <version>1.2.0</version>
+      <version>1.3.0-SNAPSHOT</version>
+    </versions>
+    <lastUpdated>20240701000000</lastUpdated>
+  </versioning>
+</metadata>"#,
+        )
+        .unwrap();
+        assert_eq!(metadata.release(), Some("1.2.0"));
+        assert_eq!(metadata.versioning.versions.version.len(), 3);
+
+        let without_release = quick_xml::de::from_str::<Metadata>(
+            "<metadata><versioning><versions><version>1.0</version><version>1.1</version></versions></versioning></metadata>",
+        )
+        .unwrap();
+        assert_eq!(without_release.release(), Some("1.1"));
+    }
+}
@@ -0,0 +1,175 @@
+//! A tiny HTTP server serving canned responses, used to test the api clients
+//! without hitting the real services.
+
+use std::{
+    collections::HashMap,
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::mpsc::{self, Receiver},
+    thread,
+};
+
+/// A request received by the [`MockServer`]
+#[derive(Debug, Clone)]
+pub struct MockRequest {
+    pub method: String,
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl MockRequest {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// A canned response of the [`MockServer`]
+#[derive(Debug, Clone)]
+pub struct MockResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl MockResponse {
+    pub fn new(status: u16, body: impl Into<String>) -> Self {
+        Self {
+            status,
+            headers: vec![],
+            body: body.into(),
+        }
+    }
+
+    pub fn json(body: impl Into<String>) -> Self {
+        Self::new(200, body).with_header("content-type", "application/json")
+    }
+
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+}
+
+/// Serves canned responses, in order or by route
+pub struct MockServer {
+    pub url: String,
+    requests: Receiver<MockRequest>,
+}
+
+impl MockServer {
+    /// Serve the given responses in order, one per connection
+    pub fn start(responses: Vec<MockResponse>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let (tx, rx) = mpsc::channel();
+
+        thread::spawn(move || {
+            for response in responses {
+                let Ok((mut stream, _)) = listener.accept() else {
+                    return;
+                };
+                let request = read_request(&stream);
+                let _ = tx.send(request);
+                write_response(&mut stream, &response);
+            }
+        });
+
+        Self { url, requests: rx }
+    }
+
+    /// Serve the response of the route matching each request until the
+    /// server is dropped, `404` if none does
+    ///
+    /// A route is a method and a path without the query, e.g.
+    /// `GET /v2/project/sodium/version`. Use it when the order of the
+    /// requests is not known, e.g. when they are sent concurrently.
+    pub fn route(routes: Vec<(&str, MockResponse)>) -> Self {
+        let routes = routes
+            .into_iter()
+            .map(|(route, response)| (route.to_string(), response))
+            .collect::<HashMap<_, _>>();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let (tx, rx) = mpsc::channel();
+
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else {
+                    return;
+                };
+                let request = read_request(&stream);
+                let path = request.path.split('?').next().unwrap_or_default();
+                let not_found = MockResponse::new(404, "");
+                let response = routes
+                    .get(&format!("{} {}", request.method, path))
+                    .unwrap_or(&not_found);
+                write_response(&mut stream, response);
+                if tx.send(request).is_err() {
+                    return;
+                }
+            }
+        });
+
+        Self { url, requests: rx }
+    }
+
+    /// All the requests received so far
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.requests.try_iter().collect()
+    }
+}
+
+fn read_request(stream: &TcpStream) -> MockRequest {
+    let mut reader = BufReader::new(stream);
+
+    let mut line = String::new();
+    reader.read_line(&mut line).unwrap();
+    let mut parts = line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let path = parts.next().unwrap_or_default().to_string();
+
+    let mut headers = vec![];
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((k, v)) = line.split_once(':') {
+            headers.push((k.trim().to_string(), v.trim().to_string()));
+        }
+    }
+    let len = headers
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, v)| v.parse::<usize>().ok())
+        .unwrap_or(0);
+    let mut body = vec![0; len];
+    reader.read_exact(&mut body).unwrap();
+
+    MockRequest {
+        method,
+        path,
+        headers,
+        body: String::from_utf8_lossy(&body).to_string(),
+    }
+}
+
+fn write_response(stream: &mut TcpStream, response: &MockResponse) {
+    let mut raw = format!(
+        "HTTP/1.1 {} Mock\r\ncontent-length: {}\r\nconnection: close\r\n",
+        response.status,
+        response.body.len()
+    );
+    for (k, v) in &response.headers {
+        raw.push_str(&format!("{k}: {v}\r\n"));
+    }
+    raw.push_str("\r\n");
+    raw.push_str(&response.body);
+    let _ = stream.write_all(raw.as_bytes());
+}
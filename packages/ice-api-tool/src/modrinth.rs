@@ -1,130 +1,544 @@
-use std::{fmt::Display, vec};
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
 
-use anyhow::Context;
-use serde::Serialize;
+use crate::{
+    cache::{self, Fetched, HttpCache},
+    error::ApiError,
+};
+use reqwest::{
+    header::{HeaderMap, IF_NONE_MATCH},
+    RequestBuilder, Response, StatusCode,
+};
+use serde::{de::DeserializeOwned, Serialize};
 use serde_json::json;
-use types::{Loader, Project, Version};
+use types::{Loader, Project, ProjectType, SearchResults, SideSupport, Version, VersionType};
 
-const HOST: &str = "https://api.modrinth.com/v2";
+pub const DEFAULT_BASE_URL: &str = "https://api.modrinth.com/v2";
+pub const DEFAULT_USER_AGENT: &str = concat!(
+    "AzurIce/ice-api-tool/",
+    env!("CARGO_PKG_VERSION"),
+    " (github.com/AzurIce/ice)"
+);
 
-pub async fn get_project<S: AsRef<str>>(id_or_slug: S) -> Result<Project, anyhow::Error> {
-    let id_or_slug = id_or_slug.as_ref();
+/// How long the responses of the endpoints are used without asking Modrinth
+mod ttl {
+    use std::time::Duration;
 
-    let res = reqwest::get(format!("{HOST}/project/{id_or_slug}")).await?;
-    let project = res.json::<Project>().await?;
-    Ok(project)
+    /// Versions and the files of them hardly change once published
+    pub const VERSION: Duration = Duration::from_secs(24 * 60 * 60);
+    pub const PROJECT: Duration = Duration::from_secs(60 * 60);
+    /// Anything that answers "what is the latest"
+    pub const LATEST: Duration = Duration::from_secs(10 * 60);
+    pub const SEARCH: Duration = Duration::from_secs(10 * 60);
 }
 
-pub async fn get_project_versions<S: AsRef<str>>(
-    id_or_slug: S,
-    loaders: Option<&Vec<Loader>>,
-    game_version: Option<String>,
-) -> Result<Vec<Version>, anyhow::Error> {
-    let slug = id_or_slug.as_ref();
-    let url = format!("{HOST}/project/{slug}/version");
-    let mut params = vec![];
-    if let Some(loaders) = loaders {
-        params.push(("loaders", serde_json::to_string(loaders).unwrap()));
-    }
-    if let Some(game_version) = game_version {
-        params.push((
-            "game_versions",
-            format!(
-                "[{}]",
-                vec![game_version]
-                    .into_iter()
-                    .map(|s| format!(r#""{s}""#))
-                    .collect::<Vec<String>>()
-                    .join(", ")
-            ),
-        ))
-    }
-    let url = reqwest::Url::parse_with_params(&url, params)?;
-
-    let res = reqwest::get(url).await?;
-    let versions = res.json::<Vec<Version>>().await?;
-    Ok(versions)
+/// A client for the Modrinth API
+///
+/// It holds a shared [`reqwest::Client`], so cloning it is cheap and clones
+/// share the connection pool and the rate-limit state.
+///
+/// Requests that hit a `429 Too Many Requests` or a `5xx` response are
+/// retried with backoff, and when `X-Ratelimit-Remaining` drops to `0`,
+/// further requests wait until `X-Ratelimit-Reset` has passed.
+///
+/// With [`ModrinthClient::with_cache`], the responses are cached on disk, see
+/// [`crate::cache`].
+#[derive(Debug, Clone)]
+pub struct ModrinthClient {
+    client: reqwest::Client,
+    base_url: String,
+    user_agent: String,
+    max_retries: u32,
+    ratelimit_reset_at: Arc<Mutex<Option<Instant>>>,
+    cache: Option<HttpCache>,
 }
 
-/// Get `Version` from id
-pub async fn get_version<S: AsRef<str>>(id: S) -> Result<Version, anyhow::Error> {
-    let id = id.as_ref();
-    let url = format!("{HOST}/version/{id}");
-
-    let url = reqwest::Url::parse(&url)?;
-    let res = reqwest::get(url).await?;
-    let version = res.json::<Version>().await?;
-    Ok(version)
+impl Default for ModrinthClient {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-// Get `Version` from the hash of the file
-pub async fn get_version_from_hash<H: AsRef<str>>(
-    hash: H,
-    hash_method: HashMethod,
-) -> Result<Version, anyhow::Error> {
-    let hash = hash.as_ref();
-    let url = format!("{HOST}/version_file/{hash}");
-
-    let params = [("algorithm", hash_method.to_string())];
-    let url = reqwest::Url::parse_with_params(&url, params)?;
-    let res = reqwest::get(url).await?;
-    let version = res.json::<Version>().await?;
-    Ok(version)
-}
+impl ModrinthClient {
+    pub fn new() -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            base_url: DEFAULT_BASE_URL.to_string(),
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+            max_retries: 5,
+            ratelimit_reset_at: Arc::new(Mutex::new(None)),
+            cache: None,
+        }
+    }
 
-/// Get the latest version of a mod from a hash
-///
-/// if the version supports on of the loaders, it will consider valid,
-/// same as game_version(current only on version is provided)
-pub async fn get_latest_version_from_hash<H: AsRef<str>, V: AsRef<str>>(
-    hash: H,
-    hash_method: HashMethod,
-    loaders: &Vec<Loader>,
-    game_version: V,
-) -> Result<Version, anyhow::Error> {
-    let hash = hash.as_ref();
-    let game_version = game_version.as_ref();
-
-    let url = format!("{HOST}/version_file/{hash}/update");
-    let params = [("algorithm", hash_method.to_string())];
-    let url = reqwest::Url::parse_with_params(&url, params)?;
-
-    let client = reqwest::Client::new();
-    let res = client
-        .post(url)
-        .json(&json!({
+    /// Use another API base url, e.g. `https://staging-api.modrinth.com/v2`
+    pub fn with_base_url(mut self, base_url: impl AsRef<str>) -> Self {
+        self.base_url = base_url.as_ref().trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Set the `User-Agent` sent with every request
+    ///
+    /// Modrinth asks for a uniquely-identifying one, like
+    /// `github_username/project_name/1.56.0 (contact@example.com)`
+    pub fn with_user_agent(mut self, user_agent: impl AsRef<str>) -> Self {
+        self.user_agent = user_agent.as_ref().to_string();
+        self
+    }
+
+    /// Set how many times a request is retried on `429` and `5xx` responses
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Cache the responses in `cache`
+    pub fn with_cache(mut self, cache: HttpCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    fn url(&self, path: impl AsRef<str>) -> String {
+        format!("{}{}", self.base_url, path.as_ref())
+    }
+
+    /// Send a request, waiting for the rate limit and retrying on `429` and `5xx`
+    async fn send(&self, request: RequestBuilder) -> Result<Response, ApiError> {
+        let mut retries = 0;
+        loop {
+            self.wait_for_ratelimit().await;
+
+            let res = request
+                .try_clone()
+                .expect("requests with json bodies can be cloned")
+                .header(reqwest::header::USER_AGENT, &self.user_agent)
+                .send()
+                .await;
+            let res = match res {
+                Ok(res) => res,
+                Err(err)
+                    if (err.is_connect() || err.is_timeout()) && retries < self.max_retries =>
+                {
+                    smol::Timer::after(backoff(retries)).await;
+                    retries += 1;
+                    continue;
+                }
+                Err(err) => return Err(err.into()),
+            };
+            self.update_ratelimit(res.headers());
+
+            let status = res.status();
+            if (status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error())
+                && retries < self.max_retries
+            {
+                let delay = if status == StatusCode::TOO_MANY_REQUESTS {
+                    ratelimit_reset(res.headers()).unwrap_or(backoff(retries))
+                } else {
+                    backoff(retries)
+                };
+                smol::Timer::after(delay).await;
+                retries += 1;
+                continue;
+            }
+            return match status {
+                StatusCode::NOT_FOUND => Err(ApiError::NotFound(res.url().to_string())),
+                StatusCode::TOO_MANY_REQUESTS => Err(ApiError::RateLimited {
+                    retry_after: ratelimit_reset(res.headers()),
+                }),
+                status if status.is_client_error() || status.is_server_error() => {
+                    Err(ApiError::Server {
+                        status,
+                        url: res.url().to_string(),
+                    })
+                }
+                _ => Ok(res),
+            };
+        }
+    }
+
+    async fn get_json<T: DeserializeOwned>(
+        &self,
+        url: reqwest::Url,
+        ttl: Duration,
+    ) -> Result<T, ApiError> {
+        self.fetch_json(self.client.get(url), ttl).await
+    }
+
+    async fn post_json<T: DeserializeOwned>(
+        &self,
+        url: impl reqwest::IntoUrl,
+        body: serde_json::Value,
+        ttl: Duration,
+    ) -> Result<T, ApiError> {
+        self.fetch_json(self.client.post(url).json(&body), ttl)
+            .await
+    }
+
+    /// Send a request through the cache and decode the json response
+    async fn fetch_json<T: DeserializeOwned>(
+        &self,
+        request: RequestBuilder,
+        ttl: Duration,
+    ) -> Result<T, ApiError> {
+        let built = request
+            .try_clone()
+            .expect("requests with json bodies can be cloned")
+            .build()?;
+        let key = cache::key(
+            built.method().as_str(),
+            built.url().as_str(),
+            built.body().and_then(|b| b.as_bytes()).unwrap_or_default(),
+        );
+
+        let body = cache::cached(self.cache.as_ref(), &key, ttl, |etag| async move {
+            let request = match etag {
+                Some(etag) => request.header(IF_NONE_MATCH, etag),
+                None => request,
+            };
+            Fetched::from_response(self.send(request).await?).await
+        })
+        .await?;
+        serde_json::from_str(&body).map_err(|err| {
+            if let Some(cache) = &self.cache {
+                cache.remove(&key);
+            }
+            err.into()
+        })
+    }
+
+    async fn wait_for_ratelimit(&self) {
+        let reset_at = *self.ratelimit_reset_at.lock().unwrap();
+        if let Some(reset_at) = reset_at {
+            let now = Instant::now();
+            if reset_at > now {
+                smol::Timer::at(reset_at).await;
+            }
+        }
+    }
+
+    fn update_ratelimit(&self, headers: &HeaderMap) {
+        let remaining = headers
+            .get("x-ratelimit-remaining")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<u64>().ok());
+        let mut reset_at = self.ratelimit_reset_at.lock().unwrap();
+        *reset_at = match (remaining, ratelimit_reset(headers)) {
+            (Some(0), Some(reset)) => Some(Instant::now() + reset),
+            _ => None,
+        };
+    }
+
+    pub async fn get_project<S: AsRef<str>>(&self, id_or_slug: S) -> Result<Project, ApiError> {
+        let id_or_slug = id_or_slug.as_ref();
+
+        let url = parse_url(&self.url(format!("/project/{id_or_slug}")))?;
+        self.get_json(url, ttl::PROJECT).await
+    }
+
+    pub async fn get_project_versions<S: AsRef<str>>(
+        &self,
+        id_or_slug: S,
+        loaders: Option<&Vec<Loader>>,
+        game_version: Option<String>,
+    ) -> Result<Vec<Version>, ApiError> {
+        let slug = id_or_slug.as_ref();
+        let url = self.url(format!("/project/{slug}/version"));
+        let mut params = vec![];
+        if let Some(loaders) = loaders {
+            params.push(("loaders", serde_json::to_string(loaders).unwrap()));
+        }
+        if let Some(game_version) = game_version {
+            params.push((
+                "game_versions",
+                serde_json::to_string(&[game_version]).unwrap(),
+            ))
+        }
+        let url = parse_url_with_params(&url, params)?;
+        self.get_json(url, ttl::LATEST).await
+    }
+
+    /// Get `Version` from id
+    pub async fn get_version<S: AsRef<str>>(&self, id: S) -> Result<Version, ApiError> {
+        let id = id.as_ref();
+
+        let url = parse_url(&self.url(format!("/version/{id}")))?;
+        self.get_json(url, ttl::VERSION).await
+    }
+
+    // Get `Version` from the hash of the file
+    pub async fn get_version_from_hash<H: AsRef<str>>(
+        &self,
+        hash: H,
+        hash_method: HashMethod,
+    ) -> Result<Version, ApiError> {
+        let hash = hash.as_ref();
+        let url = self.url(format!("/version_file/{hash}"));
+
+        let params = [("algorithm", hash_method.to_string())];
+        let url = parse_url_with_params(&url, params)?;
+        self.get_json(url, ttl::VERSION).await
+    }
+
+    /// Get the latest version of a mod from a hash
+    ///
+    /// if the version supports on of the loaders, it will consider valid,
+    /// same as game_version(current only on version is provided)
+    pub async fn get_latest_version_from_hash<H: AsRef<str>, V: AsRef<str>>(
+        &self,
+        hash: H,
+        hash_method: HashMethod,
+        loaders: &Vec<Loader>,
+        game_version: V,
+    ) -> Result<Version, ApiError> {
+        let hash = hash.as_ref();
+        let game_version = game_version.as_ref();
+
+        let url = self.url(format!("/version_file/{hash}/update"));
+        let params = [("algorithm", hash_method.to_string())];
+        let url = parse_url_with_params(&url, params)?;
+
+        let body = json!({
             "loaders": loaders,
             "game_versions": [game_version],
-        }))
-        .send()
-        .await
-        .context("error getting response")?;
-    let version = res.json::<Version>().await?;
-    Ok(version)
+        });
+        self.post_json(url, body, ttl::LATEST).await
+    }
+
+    /// Get the `Project`s of the given ids or slugs in one request
+    pub async fn get_projects<S: AsRef<str>>(
+        &self,
+        ids_or_slugs: impl IntoIterator<Item = S>,
+    ) -> Result<Vec<Project>, ApiError> {
+        let ids = to_strings(ids_or_slugs);
+        if ids.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let params = [("ids", serde_json::to_string(&ids).unwrap())];
+        let url = parse_url_with_params(&self.url("/projects"), params)?;
+        self.get_json(url, ttl::PROJECT).await
+    }
+
+    /// Get the `Version`s of the given ids in one request
+    pub async fn get_versions<S: AsRef<str>>(
+        &self,
+        ids: impl IntoIterator<Item = S>,
+    ) -> Result<Vec<Version>, ApiError> {
+        let ids = to_strings(ids);
+        if ids.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let params = [("ids", serde_json::to_string(&ids).unwrap())];
+        let url = parse_url_with_params(&self.url("/versions"), params)?;
+        self.get_json(url, ttl::VERSION).await
+    }
+
+    /// Get the `Version`s of many files from their hashes in one request
+    ///
+    /// The result is keyed by hash, hashes unknown to Modrinth are absent
+    pub async fn get_versions_from_hashes<H: AsRef<str>>(
+        &self,
+        hashes: impl IntoIterator<Item = H>,
+        hash_method: HashMethod,
+    ) -> Result<HashMap<String, Version>, ApiError> {
+        let hashes = to_strings(hashes);
+        if hashes.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let body = json!({
+            "hashes": hashes,
+            "algorithm": hash_method,
+        });
+        self.post_json(self.url("/version_files"), body, ttl::VERSION)
+            .await
+    }
+
+    /// Get the latest `Version`s of many files from their hashes in one request
+    ///
+    /// The result is keyed by hash, hashes unknown to Modrinth or without a
+    /// version satisfying `loaders` and `game_versions` are absent
+    pub async fn get_latest_versions_from_hashes<H: AsRef<str>, V: AsRef<str>>(
+        &self,
+        hashes: impl IntoIterator<Item = H>,
+        hash_method: HashMethod,
+        loaders: &Vec<Loader>,
+        game_versions: impl IntoIterator<Item = V>,
+    ) -> Result<HashMap<String, Version>, ApiError> {
+        let hashes = to_strings(hashes);
+        if hashes.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let body = json!({
+            "hashes": hashes,
+            "algorithm": hash_method,
+            "loaders": loaders,
+            "game_versions": to_strings(game_versions),
+        });
+        self.post_json(self.url("/version_files/update"), body, ttl::LATEST)
+            .await
+    }
+
+    /// Search projects, see [`SearchQuery`]
+    pub async fn search(&self, query: &SearchQuery) -> Result<SearchResults, ApiError> {
+        let mut params = vec![
+            ("query", query.query.clone()),
+            ("offset", query.offset.to_string()),
+            ("limit", query.limit.to_string()),
+        ];
+        let facets = query.facets();
+        if !facets.is_empty() {
+            params.push(("facets", serde_json::to_string(&facets).unwrap()));
+        }
+        let url = parse_url_with_params(&self.url("/search"), params)?;
+        self.get_json(url, ttl::SEARCH).await
+    }
+
+    /// get the latest version of `slug` on `channel`
+    ///
+    /// if the version supports on of the loaders, it will consider valid,
+    /// same as game_version(current only on version is provided)
+    ///
+    /// the latest one is the newest by `date_published`, see
+    /// [`VersionType::is_on_channel`] for the channel
+    pub async fn get_latest_version_from_slug<S: AsRef<str>, V: AsRef<str>>(
+        &self,
+        slug: S,
+        loaders: Vec<Loader>,
+        game_version: V,
+        channel: VersionType,
+    ) -> Result<Version, ApiError> {
+        let slug = slug.as_ref();
+        let game_version = game_version.as_ref();
+
+        let versions = self.get_project_versions(slug, None, None).await?;
+        versions
+            .into_iter()
+            .filter(|v| v.is_compatible(&loaders, game_version, channel))
+            .max_by_key(|v| v.date_published)
+            .ok_or(ApiError::NotFound(format!(
+                "a {channel} version of {slug} satisfied {loaders:?} and {game_version}"
+            )))
+    }
 }
 
-/// get the latest version of `slug`
-///
-/// if the version supports on of the loaders, it will consider valid,
-/// same as game_version(current only on version is provided)
-pub async fn get_latest_version_from_slug<S: AsRef<str>, V: AsRef<str>>(
-    slug: S,
-    loaders: Vec<Loader>,
-    game_version: V,
-) -> Result<Version, anyhow::Error> {
-    let slug = slug.as_ref();
-    let game_version = game_version.as_ref();
-
-    let versions = get_project_versions(slug, None, None).await?;
-    versions
-        .into_iter()
-        .find(|v| {
-            v.game_versions.contains(&game_version.to_string())
-                && (loaders.iter().any(|l| v.loaders.contains(l)))
-        })
-        .ok_or(anyhow::anyhow!(
-            "cannot find a version of {slug} satisfied {loaders:?} and {game_version}"
-        ))
+fn parse_url(url: &str) -> Result<reqwest::Url, ApiError> {
+    reqwest::Url::parse(url).map_err(|err| ApiError::InvalidInput(format!("{url}: {err}")))
+}
+
+fn parse_url_with_params<I, K, V>(url: &str, params: I) -> Result<reqwest::Url, ApiError>
+where
+    I: IntoIterator,
+    I::Item: std::borrow::Borrow<(K, V)>,
+    K: AsRef<str>,
+    V: AsRef<str>,
+{
+    reqwest::Url::parse_with_params(url, params)
+        .map_err(|err| ApiError::InvalidInput(format!("{url}: {err}")))
+}
+
+fn to_strings<S: AsRef<str>>(iter: impl IntoIterator<Item = S>) -> Vec<String> {
+    iter.into_iter().map(|s| s.as_ref().to_string()).collect()
+}
+
+/// The wait time given by `X-Ratelimit-Reset` (or `Retry-After`), in seconds
+fn ratelimit_reset(headers: &HeaderMap) -> Option<Duration> {
+    headers
+        .get("x-ratelimit-reset")
+        .or(headers.get(reqwest::header::RETRY_AFTER))
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok())
+        .map(Duration::from_secs)
+}
+
+/// Exponential backoff starting from 500ms
+fn backoff(retries: u32) -> Duration {
+    Duration::from_millis(500 * 2u64.pow(retries.min(6)))
+}
+
+/// A search query, the non-empty filters are combined with AND,
+/// and the values inside one filter with OR
+#[derive(Debug, Clone)]
+pub struct SearchQuery {
+    pub query: String,
+    pub project_type: Option<ProjectType>,
+    pub categories: Vec<String>,
+    pub versions: Vec<String>,
+    pub loaders: Vec<Loader>,
+    pub server_side: Vec<SideSupport>,
+    pub offset: u32,
+    pub limit: u32,
+}
+
+impl Default for SearchQuery {
+    fn default() -> Self {
+        Self {
+            query: String::new(),
+            project_type: None,
+            categories: vec![],
+            versions: vec![],
+            loaders: vec![],
+            server_side: vec![],
+            offset: 0,
+            limit: 10,
+        }
+    }
+}
+
+impl SearchQuery {
+    pub fn new(query: impl AsRef<str>) -> Self {
+        Self {
+            query: query.as_ref().to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// The `facets` of the search request
+    fn facets(&self) -> Vec<Vec<String>> {
+        let mut facets = vec![];
+        if let Some(project_type) = &self.project_type {
+            facets.push(vec![format!("project_type:{project_type}")]);
+        }
+        // Loaders are categories on Modrinth, so they are OR-ed apart from the others
+        for category in &self.categories {
+            facets.push(vec![format!("categories:{category}")]);
+        }
+        if !self.loaders.is_empty() {
+            facets.push(
+                self.loaders
+                    .iter()
+                    .map(|l| format!("categories:{l}"))
+                    .collect(),
+            );
+        }
+        if !self.versions.is_empty() {
+            facets.push(
+                self.versions
+                    .iter()
+                    .map(|v| format!("versions:{v}"))
+                    .collect(),
+            );
+        }
+        if !self.server_side.is_empty() {
+            facets.push(
+                self.server_side
+                    .iter()
+                    .map(|s| format!("server_side:{s}"))
+                    .collect(),
+            );
+        }
+        facets
+    }
 }
 
 #[derive(Debug, Serialize)]
@@ -143,25 +557,79 @@ impl Display for HashMethod {
     }
 }
 
+/// The `.mrpack` modpack format
+///
+/// See <https://support.modrinth.com/en/articles/8802351-modrinth-modpack-format-mrpack>
+pub mod mrpack {
+    use std::collections::BTreeMap;
+
+    use serde::{Deserialize, Serialize};
+
+    use super::types::{SideSupport, VersionFileHashes};
+
+    /// The path of the [`Index`] inside the zip
+    pub const INDEX_PATH: &str = "modrinth.index.json";
+    /// The folders whose content is extracted into the instance, in order
+    pub const OVERRIDES: &str = "overrides";
+    pub const CLIENT_OVERRIDES: &str = "client-overrides";
+    pub const SERVER_OVERRIDES: &str = "server-overrides";
+
+    /// The `modrinth.index.json` of a modpack
+    #[derive(Serialize, Deserialize, Debug, Clone)]
+    #[serde(rename_all = "camelCase")]
+    pub struct Index {
+        pub format_version: u32,
+        pub game: String,
+        pub version_id: String,
+        pub name: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub summary: Option<String>,
+        pub files: Vec<IndexFile>,
+        /// `minecraft` and the loader (`fabric-loader`, `quilt-loader`,
+        /// `neoforge` or `forge`) to their versions
+        pub dependencies: BTreeMap<String, String>,
+    }
+
+    /// A file to download into the instance
+    #[derive(Serialize, Deserialize, Debug, Clone)]
+    #[serde(rename_all = "camelCase")]
+    pub struct IndexFile {
+        /// Relative to the instance, like `mods/sodium.jar`
+        pub path: String,
+        pub hashes: VersionFileHashes,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub env: Option<Env>,
+        pub downloads: Vec<String>,
+        pub file_size: u64,
+    }
+
+    /// Whether a file is needed on the client or server side
+    ///
+    /// Only `required`, `optional` and `unsupported` are valid here
+    #[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Env {
+        pub client: SideSupport,
+        pub server: SideSupport,
+    }
+}
+
 pub mod utils {
     use std::path::Path;
 
     use anyhow::Context;
-    use ice_util::download_from_url;
+    use ice_util::download_verified;
 
     use super::types::VersionFile;
 
+    /// Download `version_file` into `dir`, verified by its size and hashes
+    ///
+    /// An existing file is kept if it matches, and replaced otherwise.
     pub async fn download_version_file<P: AsRef<Path>>(
         version_file: &VersionFile,
         dir: P,
     ) -> Result<(), anyhow::Error> {
-        let dir = dir.as_ref();
-        let path = dir.join(&version_file.filename);
-        if path.exists() {
-            return Ok(());
-            // return Err("already exists".into());
-        }
-        download_from_url(&version_file.url, path, |_| {})
+        let path = dir.as_ref().join(&version_file.filename);
+        download_verified(&version_file.url, path, &version_file.checksum(), |_| {})
             .await
             .context("failed to download from url")
     }
@@ -170,9 +638,10 @@ pub mod utils {
 pub mod types {
     use std::fmt::{self, Display, Formatter};
 
+    use chrono::{DateTime, Utc};
     use serde::{Deserialize, Serialize};
 
-    #[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+    #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
     #[serde(rename_all = "lowercase")]
     pub enum Loader {
         Quilt,
@@ -206,8 +675,9 @@ pub mod types {
         Vanilla,
         Velocity,
         Waterfall,
-        // #[serde(untagged)]
-        // Unknown(String)
+        /// A loader tag this crate does not know about yet
+        #[serde(untagged)]
+        Unknown(String),
     }
 
     impl Display for Loader {
@@ -241,28 +711,184 @@ pub mod types {
                 Self::Vanilla => "vanilla",
                 Self::Velocity => "velocity",
                 Self::Waterfall => "waterfall",
-                // Self::Unknown(tag) => tag,
+                Self::Unknown(tag) => tag,
             })
         }
     }
 
-    #[derive(Deserialize, Debug, PartialEq, Eq)]
+    #[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
     pub struct Project {
+        pub id: String,
         pub slug: String,
+        pub title: String,
+        pub description: String,
+        pub project_type: ProjectType,
+        pub client_side: SideSupport,
+        pub server_side: SideSupport,
+        pub license: License,
+        pub categories: Vec<String>,
+        pub game_versions: Vec<String>,
+        pub loaders: Vec<Loader>,
+        pub versions: Vec<String>,
+        pub downloads: u64,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
+    #[serde(rename_all = "lowercase")]
+    pub enum ProjectType {
+        Mod,
+        Modpack,
+        ResourcePack,
+        Shader,
+        Plugin,
+        Datapack,
+        /// A project type this crate does not know about yet
+        #[serde(untagged)]
+        Unknown(String),
+    }
+
+    impl Display for ProjectType {
+        fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+            f.write_str(match self {
+                Self::Mod => "mod",
+                Self::Modpack => "modpack",
+                Self::ResourcePack => "resourcepack",
+                Self::Shader => "shader",
+                Self::Plugin => "plugin",
+                Self::Datapack => "datapack",
+                Self::Unknown(project_type) => project_type,
+            })
+        }
+    }
+
+    /// Whether a project is needed on the client or server side
+    #[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq, Eq, Hash)]
+    #[serde(rename_all = "lowercase")]
+    pub enum SideSupport {
+        Required,
+        Optional,
+        Unsupported,
+        Unknown,
+    }
+
+    impl Display for SideSupport {
+        fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+            f.write_str(match self {
+                Self::Required => "required",
+                Self::Optional => "optional",
+                Self::Unsupported => "unsupported",
+                Self::Unknown => "unknown",
+            })
+        }
+    }
+
+    #[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+    pub struct SearchResults {
+        pub hits: Vec<SearchHit>,
+        pub offset: u32,
+        pub limit: u32,
+        pub total_hits: u32,
     }
 
-    #[derive(Deserialize, Debug, PartialEq, Eq)]
+    #[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+    pub struct SearchHit {
+        pub project_id: String,
+        pub slug: String,
+        pub title: String,
+        pub description: String,
+        pub author: String,
+        pub project_type: ProjectType,
+        pub categories: Vec<String>,
+        pub client_side: SideSupport,
+        pub server_side: SideSupport,
+        pub downloads: u64,
+        pub versions: Vec<String>,
+        pub latest_version: Option<String>,
+    }
+
+    #[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+    pub struct License {
+        pub id: String,
+        pub name: String,
+        pub url: Option<String>,
+    }
+
+    #[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
     pub struct Version {
         pub name: String,
         pub version_number: String,
+        pub changelog: Option<String>,
+        pub dependencies: Vec<Dependency>,
         pub game_versions: Vec<String>,
+        pub version_type: VersionType,
         pub loaders: Vec<Loader>,
+        pub featured: bool,
         pub id: String,
         pub project_id: String,
+        pub date_published: DateTime<Utc>,
+        pub downloads: u64,
         pub files: Vec<VersionFile>,
     }
 
+    #[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+    #[serde(rename_all = "lowercase")]
+    pub enum VersionType {
+        Release,
+        Beta,
+        Alpha,
+    }
+
+    impl VersionType {
+        /// Whether a version of this type is allowed on `channel`
+        ///
+        /// A channel allows its own versions and the more stable ones,
+        /// e.g. `beta` allows `release` and `beta` versions
+        pub fn is_on_channel(&self, channel: VersionType) -> bool {
+            *self <= channel
+        }
+    }
+
+    impl Display for VersionType {
+        fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+            f.write_str(match self {
+                Self::Release => "release",
+                Self::Beta => "beta",
+                Self::Alpha => "alpha",
+            })
+        }
+    }
+
+    #[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+    pub struct Dependency {
+        pub version_id: Option<String>,
+        pub project_id: Option<String>,
+        pub file_name: Option<String>,
+        pub dependency_type: DependencyType,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq, Eq, Hash)]
+    #[serde(rename_all = "lowercase")]
+    pub enum DependencyType {
+        Required,
+        Optional,
+        Incompatible,
+        Embedded,
+    }
+
     impl Version {
+        /// Whether this version runs on `game_version` with one of `loaders`
+        /// and is on `channel`
+        pub fn is_compatible(
+            &self,
+            loaders: &[Loader],
+            game_version: &str,
+            channel: VersionType,
+        ) -> bool {
+            self.game_versions.iter().any(|v| v == game_version)
+                && loaders.iter().any(|l| self.loaders.contains(l))
+                && self.version_type.is_on_channel(channel)
+        }
+
         pub fn get_primary_file(&self) -> &VersionFile {
             self.files
                 .iter()
@@ -271,7 +897,7 @@ pub mod types {
         }
     }
 
-    #[derive(Deserialize, Debug, PartialEq, Eq)]
+    #[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
     pub struct VersionFile {
         pub hashes: VersionFileHashes,
         pub url: String,
@@ -280,11 +906,22 @@ pub mod types {
         pub size: i32,
     }
 
-    #[derive(Deserialize, Debug, PartialEq, Eq)]
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
     pub struct VersionFileHashes {
         pub sha1: String,
         pub sha512: String,
     }
+
+    impl VersionFile {
+        /// What the downloaded file is checked against
+        pub fn checksum(&self) -> ice_util::Checksum {
+            ice_util::Checksum {
+                size: u64::try_from(self.size).ok(),
+                sha1: Some(self.hashes.sha1.clone()),
+                sha512: Some(self.hashes.sha512.clone()),
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -295,15 +932,43 @@ mod test {
 
     use super::types::*;
     use super::*;
+    use crate::mock::{MockResponse, MockServer};
+
+    const IRIS_PROJECT: &str = r#"{
+        "id": "YL57xq9U",
+        "slug": "iris",
+        "title": "Iris Shaders",
+        "description": "A modern shader pack loader for Minecraft intended to be compatible with existing OptiFine shader packs",
+        "project_type": "mod",
+        "client_side": "required",
+        "server_side": "unsupported",
+        "license": { "id": "LGPL-3.0-only", "name": "GNU Lesser General Public License v3.0 only", "url": null },
+        "categories": ["decoration", "optimization"],
+        "game_versions": ["1.21"],
+        "loaders": ["fabric", "quilt", "neoforge"],
+        "versions": ["kuOV4Ece"],
+        "downloads": 1000
+    }"#;
 
     fn iris_version() -> Version {
         Version {
             name: "Iris 1.7.3 for Minecraft 1.21".to_string(),
             version_number: "1.7.3+1.21".to_string(),
+            changelog: None,
+            dependencies: vec![Dependency {
+                version_id: None,
+                project_id: Some("AANobbMI".to_string()),
+                file_name: None,
+                dependency_type: DependencyType::Optional,
+            }],
             game_versions: vec!["1.21".to_string()],
+            version_type: VersionType::Release,
             loaders: vec![Loader::Fabric, Loader::Quilt],
+            featured: false,
             id: "kuOV4Ece".to_string(),
             project_id: "YL57xq9U".to_string(),
+            date_published: "2024-06-24T14:46:14.000000Z".parse().unwrap(),
+            downloads: 1000,
             files: vec![
                 VersionFile {
                     hashes: VersionFileHashes {
@@ -330,45 +995,80 @@ mod test {
             name: Loader,
             supported_project_types: Vec<String>,
         }
-        let _loader_tags = res.json::<Vec<LoaderTag>>().await.unwrap();
-        // let loaders = _loader_tags.iter().map(|tag| tag.name.clone());
-        // let unknown_loaders = loaders.filter_map(|loader| if let Loader::Unknown(loader) = loader {
-        //     Some(loader)
-        // } else {
-        //     None
-        // }).collect::<Vec<_>>();
-        // if !unknown_loaders.is_empty() {
-        //     panic!("has {} unknown_loaders: {:?}", unknown_loaders.len(), unknown_loaders)
-        // }
+        let loader_tags = res.json::<Vec<LoaderTag>>().await.unwrap();
+        let loaders = loader_tags.into_iter().map(|tag| tag.name);
+        let unknown_loaders = loaders
+            .filter_map(|loader| {
+                if let Loader::Unknown(loader) = loader {
+                    Some(loader)
+                } else {
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+        if !unknown_loaders.is_empty() {
+            panic!(
+                "has {} unknown_loaders: {:?}",
+                unknown_loaders.len(),
+                unknown_loaders
+            )
+        }
+    }
+
+    #[test]
+    fn test_unknown_loader() {
+        let loaders =
+            serde_json::from_str::<Vec<Loader>>(r#"["fabric", "bta-babric", "mcp"]"#).unwrap();
+        assert_eq!(
+            loaders,
+            vec![
+                Loader::Fabric,
+                Loader::BtaBabric,
+                Loader::Unknown("mcp".to_string())
+            ]
+        );
+        assert_eq!(Loader::Unknown("mcp".to_string()).to_string(), "mcp");
+        assert_eq!(serde_json::to_string(&loaders[2]).unwrap(), r#""mcp""#);
     }
 
     #[apply(test!)]
     async fn test_get_project_versions() {
-        let versions = get_project_versions(
-            "terralith",
-            Some(&vec![Loader::Quilt, Loader::Fabric]),
-            Some("1.20.1".to_string()),
-        )
-        .await;
+        let client = ModrinthClient::new();
+        let versions = client
+            .get_project_versions(
+                "terralith",
+                Some(&vec![Loader::Quilt, Loader::Fabric]),
+                Some("1.20.1".to_string()),
+            )
+            .await;
         println!("{versions:?}")
     }
 
     #[apply(test!)]
     async fn test_get_version_from_hash() {
-        let version_sha1 =
-            get_version_from_hash(&iris_version().files[0].hashes.sha1, HashMethod::Sha1)
-                .await
-                .unwrap();
-        let version_sha512 =
-            get_version_from_hash(&iris_version().files[0].hashes.sha512, HashMethod::Sha512)
-                .await
-                .unwrap();
+        let client = ModrinthClient::new();
+        let version_sha1 = client
+            .get_version_from_hash(&iris_version().files[0].hashes.sha1, HashMethod::Sha1)
+            .await
+            .unwrap();
+        let version_sha512 = client
+            .get_version_from_hash(&iris_version().files[0].hashes.sha512, HashMethod::Sha512)
+            .await
+            .unwrap();
         assert_eq!(version_sha1, version_sha512);
     }
 
     #[apply(test!)]
     async fn test_get_latest_version_from_slug() {
-        let version = get_latest_version_from_slug("minihud", vec![Loader::Fabric], "1.21.6").await;
+        let client = ModrinthClient::new();
+        let version = client
+            .get_latest_version_from_slug(
+                "minihud",
+                vec![Loader::Fabric],
+                "1.21.6",
+                VersionType::Release,
+            )
+            .await;
         println!("{version:?}");
     }
 
@@ -377,16 +1077,289 @@ mod test {
     async fn test_get_latest_version_from_hash() {
         // Compat::new(async {
         // let hashes = &iris_version().files[0].hashes;
-        let version = get_latest_version_from_hash(
-            // &hashes.sha1,
-            "5cb96cdb2ad2cda38d4fb268e5eae95d00e853c2",
-            HashMethod::Sha1,
-            &vec![Loader::Fabric],
-            "1.21.6",
-        )
-        .await;
+        let client = ModrinthClient::new();
+        let version = client
+            .get_latest_version_from_hash(
+                // &hashes.sha1,
+                "5cb96cdb2ad2cda38d4fb268e5eae95d00e853c2",
+                HashMethod::Sha1,
+                &vec![Loader::Fabric],
+                "1.21.6",
+            )
+            .await;
         println!("{version:?}");
         // })
         // .await
     }
+
+    #[test]
+    fn test_mrpack_index() {
+        let index = r#"{
+            "formatVersion": 1,
+            "game": "minecraft",
+            "versionId": "1.0.0",
+            "name": "pack",
+            "files": [{
+                "path": "mods/sodium.jar",
+                "hashes": { "sha1": "s1", "sha512": "s512" },
+                "env": { "client": "required", "server": "unsupported" },
+                "downloads": ["https://cdn.modrinth.com/sodium.jar"],
+                "fileSize": 42
+            }],
+            "dependencies": { "minecraft": "1.21", "fabric-loader": "0.16.0" }
+        }"#;
+        let index = serde_json::from_str::<mrpack::Index>(index).unwrap();
+        assert_eq!(index.files[0].file_size, 42);
+        assert_eq!(index.files[0].env.unwrap().server, SideSupport::Unsupported);
+
+        let json = serde_json::to_value(&index).unwrap();
+        assert_eq!(json["versionId"], "1.0.0");
+        assert_eq!(json["files"][0]["fileSize"], 42);
+        assert_eq!(json["dependencies"]["fabric-loader"], "0.16.0");
+        assert!(json.get("summary").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_client_retries_on_ratelimit() {
+        let server = MockServer::start(vec![
+            MockResponse::new(429, "").with_header("x-ratelimit-reset", "0"),
+            MockResponse::new(502, ""),
+            MockResponse::json(IRIS_PROJECT),
+        ]);
+        let client = ModrinthClient::new()
+            .with_base_url(&server.url)
+            .with_user_agent("ice-test");
+
+        let project = client.get_project("iris").await.unwrap();
+        assert_eq!(project.slug, "iris");
+        assert_eq!(project.project_type, ProjectType::Mod);
+        assert_eq!(project.server_side, SideSupport::Unsupported);
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 3);
+        assert!(requests
+            .iter()
+            .all(|r| r.method == "GET" && r.body.is_empty()));
+        assert!(requests.iter().all(|r| r.path == "/project/iris"));
+        assert!(requests
+            .iter()
+            .all(|r| r.header("user-agent") == Some("ice-test")));
+    }
+
+    #[tokio::test]
+    async fn test_client_gives_up_after_max_retries() {
+        let server =
+            MockServer::start(vec![MockResponse::new(500, ""), MockResponse::new(500, "")]);
+        let client = ModrinthClient::new()
+            .with_base_url(&server.url)
+            .with_max_retries(1);
+
+        let err = client.get_project("iris").await.unwrap_err();
+        assert!(matches!(err, ApiError::Server { status, .. } if status == 500));
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_client_caches_responses() {
+        let server = MockServer::start(vec![
+            MockResponse::json(IRIS_PROJECT),
+            MockResponse::json("{}"),
+        ]);
+        let dir = std::env::temp_dir().join(format!("ice-modrinth-cache-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let client = ModrinthClient::new()
+            .with_base_url(&server.url)
+            .with_cache(HttpCache::new(&dir));
+
+        assert_eq!(client.get_project("iris").await.unwrap().slug, "iris");
+        assert_eq!(client.get_project("iris").await.unwrap().slug, "iris");
+        // post bodies are part of the key
+        let versions = client
+            .get_versions_from_hashes(["2"], HashMethod::Sha1)
+            .await
+            .unwrap();
+        assert!(versions.is_empty());
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[1].method, "POST");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_client_errors() {
+        let server = MockServer::start(vec![
+            MockResponse::new(404, r#"{"error":"not_found"}"#),
+            MockResponse::json(r#"{"slug":"#),
+            MockResponse::new(429, "").with_header("x-ratelimit-reset", "60"),
+        ]);
+        let client = ModrinthClient::new()
+            .with_base_url(&server.url)
+            .with_max_retries(0);
+
+        let err = client.get_project("not-a-mod").await.unwrap_err();
+        assert!(err.is_not_found());
+        let err = client.get_project("iris").await.unwrap_err();
+        assert!(matches!(err, ApiError::Decode(_)));
+        let err = client.get_project("iris").await.unwrap_err();
+        assert!(matches!(
+            err,
+            ApiError::RateLimited {
+                retry_after: Some(retry_after)
+            } if retry_after.as_secs() == 60
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_get_versions_from_hashes() {
+        let server = MockServer::start(vec![MockResponse::json(
+            r#"{
+            "1b4e44a74c850d407518bc5cbe33eec9577ad9e9": {
+                "name": "Iris 1.7.3 for Minecraft 1.21",
+                "version_number": "1.7.3+1.21",
+                "changelog": null,
+                "dependencies": [{
+                    "version_id": null,
+                    "project_id": "AANobbMI",
+                    "file_name": null,
+                    "dependency_type": "optional"
+                }],
+                "game_versions": ["1.21"],
+                "version_type": "release",
+                "loaders": ["fabric", "quilt"],
+                "featured": false,
+                "id": "kuOV4Ece",
+                "project_id": "YL57xq9U",
+                "date_published": "2024-06-24T14:46:14.000000Z",
+                "downloads": 1000,
+                "files": [{
+                    "hashes": {
+                        "sha1": "1b4e44a74c850d407518bc5cbe33eec9577ad9e9",
+                        "sha512": "bcd9b4f5cef1521c00b59cc9409725556922a077c60f62ba834dca680e29f334660a7c68150ff827779fb888585042b1ba687dfce6fd4d650507d9c59f023703"
+                    },
+                    "url": "https://cdn.modrinth.com/data/YL57xq9U/versions/kuOV4Ece/iris-1.7.3%2Bmc1.21.jar",
+                    "filename": "iris-1.7.3+mc1.21.jar",
+                    "primary": true,
+                    "size": 2707610
+                }]
+            }
+        }"#,
+        )]);
+        let client = ModrinthClient::new().with_base_url(&server.url);
+
+        let hashes = [
+            "1b4e44a74c850d407518bc5cbe33eec9577ad9e9",
+            "0000000000000000000000000000000000000000",
+        ];
+        let versions = client
+            .get_versions_from_hashes(hashes, HashMethod::Sha1)
+            .await
+            .unwrap();
+        assert_eq!(versions.len(), 1);
+        assert_eq!(versions[hashes[0]], iris_version());
+
+        let request = &server.requests()[0];
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/version_files");
+        let body = serde_json::from_str::<serde_json::Value>(&request.body).unwrap();
+        assert_eq!(body["algorithm"], "sha1");
+        assert_eq!(body["hashes"].as_array().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_search() {
+        let server = MockServer::start(vec![MockResponse::json(
+            r#"{
+                "hits": [{
+                    "project_id": "AANobbMI",
+                    "slug": "sodium",
+                    "title": "Sodium",
+                    "description": "The fastest rendering optimization mod for Minecraft.",
+                    "author": "jellysquid3",
+                    "project_type": "mod",
+                    "categories": ["optimization", "fabric"],
+                    "client_side": "required",
+                    "server_side": "unsupported",
+                    "downloads": 1000,
+                    "versions": ["1.21"],
+                    "latest_version": "1.21"
+                }],
+                "offset": 0,
+                "limit": 10,
+                "total_hits": 1
+            }"#,
+        )]);
+        let client = ModrinthClient::new().with_base_url(&server.url);
+
+        let query = SearchQuery {
+            project_type: Some(ProjectType::Mod),
+            versions: vec!["1.21".to_string()],
+            loaders: vec![Loader::Fabric, Loader::Quilt],
+            ..SearchQuery::new("sodium")
+        };
+        let results = client.search(&query).await.unwrap();
+        assert_eq!(results.hits[0].slug, "sodium");
+        assert_eq!(results.hits[0].server_side, SideSupport::Unsupported);
+
+        let request = &server.requests()[0];
+        let url = reqwest::Url::parse(&format!("{}{}", server.url, request.path)).unwrap();
+        assert_eq!(url.path(), "/search");
+        let facets = url
+            .query_pairs()
+            .find(|(k, _)| k == "facets")
+            .map(|(_, v)| v.to_string())
+            .unwrap();
+        assert_eq!(
+            facets,
+            r#"[["project_type:mod"],["categories:fabric","categories:quilt"],["versions:1.21"]]"#
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_latest_version_on_channel() {
+        let version = |id: &str, version_type: &str, date_published: &str| {
+            format!(
+                r#"{{
+                    "name": "{id}",
+                    "version_number": "{id}",
+                    "changelog": null,
+                    "dependencies": [],
+                    "game_versions": ["1.21"],
+                    "version_type": "{version_type}",
+                    "loaders": ["fabric"],
+                    "featured": false,
+                    "id": "{id}",
+                    "project_id": "AANobbMI",
+                    "date_published": "{date_published}",
+                    "downloads": 0,
+                    "files": []
+                }}"#
+            )
+        };
+        // Not sorted by date on purpose
+        let versions = format!(
+            "[{}, {}, {}]",
+            version("beta", "beta", "2024-07-01T00:00:00Z"),
+            version("alpha", "alpha", "2024-08-01T00:00:00Z"),
+            version("release", "release", "2024-06-01T00:00:00Z"),
+        );
+        let server = MockServer::start(vec![
+            MockResponse::json(versions.clone()),
+            MockResponse::json(versions.clone()),
+            MockResponse::json(versions),
+        ]);
+        let client = ModrinthClient::new().with_base_url(&server.url);
+
+        for (channel, expected) in [
+            (VersionType::Release, "release"),
+            (VersionType::Beta, "beta"),
+            (VersionType::Alpha, "alpha"),
+        ] {
+            let version = client
+                .get_latest_version_from_slug("sodium", vec![Loader::Fabric], "1.21", channel)
+                .await
+                .unwrap();
+            assert_eq!(version.id, expected);
+        }
+    }
 }
@@ -1,31 +1,79 @@
-use std::error::Error;
+use std::time::Duration;
 
-use serde_json::Value;
+use serde::Deserialize;
+
+use crate::{cache, error::ApiError};
+
+const VERSION_MANIFEST_URL: &str =
+    "https://launchermeta.mojang.com/mc/game/version_manifest_v2.json";
+const VERSION_MANIFEST_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// The game versions known to the launcher, newest first
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct VersionManifest {
+    pub latest: LatestVersions,
+    pub versions: Vec<ManifestVersion>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct LatestVersions {
+    pub release: String,
+    pub snapshot: String,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ManifestVersion {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub version_type: GameVersionType,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum GameVersionType {
+    Release,
+    Snapshot,
+    OldBeta,
+    OldAlpha,
+}
+
+impl VersionManifest {
+    /// The releases newer than `version`, oldest first
+    ///
+    /// `None` if `version` is not in the manifest
+    pub fn releases_after(&self, version: &str) -> Option<Vec<String>> {
+        let index = self.versions.iter().position(|v| v.id == version)?;
+        Some(
+            self.versions[..index]
+                .iter()
+                .rev()
+                .filter(|v| v.version_type == GameVersionType::Release)
+                .map(|v| v.id.clone())
+                .collect(),
+        )
+    }
+
+    /// The position of `version` in the manifest, `0` for the newest one
+    pub fn position(&self, version: &str) -> Option<usize> {
+        self.versions.iter().position(|v| v.id == version)
+    }
+}
+
+/// Get the version manifest of the game
+pub async fn get_version_manifest() -> Result<VersionManifest, ApiError> {
+    let res = cache::get_text(VERSION_MANIFEST_URL, VERSION_MANIFEST_TTL).await?;
+    Ok(serde_json::from_str(&res)?)
+}
 
 /// Get the latest version number of the game
-pub async fn get_latest_version() -> Result<String, Box<dyn Error>> {
-    let res =
-        reqwest::get("https://launchermeta.mojang.com/mc/game/version_manifest_v2.json").await?;
-    let json = res.json::<Value>().await?;
-    let version = json
-        .as_object()
-        .unwrap()
-        .get("latest")
-        .unwrap()
-        .as_object()
-        .unwrap()
-        .get("release")
-        .unwrap()
-        .as_str()
-        .unwrap()
-        .to_string();
-    Ok(version)
+pub async fn get_latest_version() -> Result<String, ApiError> {
+    Ok(get_version_manifest().await?.latest.release)
 }
 
 #[cfg(test)]
 mod test {
-    use smol_macros::test;
     use macro_rules_attribute::apply;
+    use smol_macros::test;
 
     use super::*;
 
@@ -34,4 +82,31 @@ mod test {
         let version = get_latest_version().await.unwrap();
         println!("{version}");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_releases_after() {
+        let manifest = serde_json::from_str::<VersionManifest>(
+            r#"{
+                "latest": { "release": "1.21.2", "snapshot": "24w40a" },
+                "versions": [
+                    { "id": "24w40a", "type": "snapshot", "url": "", "releaseTime": "" },
+                    { "id": "1.21.2", "type": "release" },
+                    { "id": "1.21.1", "type": "release" },
+                    { "id": "1.21.1-rc1", "type": "snapshot" },
+                    { "id": "1.21", "type": "release" },
+                    { "id": "b1.7.3", "type": "old_beta" }
+                ]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            manifest.releases_after("1.21"),
+            Some(vec!["1.21.1".to_string(), "1.21.2".to_string()])
+        );
+        assert_eq!(manifest.releases_after("1.21.2"), Some(vec![]));
+        assert_eq!(manifest.releases_after("1.22"), None);
+        assert_eq!(manifest.position("24w40a"), Some(0));
+        assert_eq!(manifest.position("1.21"), Some(4));
+        assert_eq!(manifest.position("1.22"), None);
+    }
+}
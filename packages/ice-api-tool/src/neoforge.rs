@@ -1,27 +1,42 @@
-pub async fn get_latest_installer_url(game_version: &str) -> Result<String, anyhow::Error> {
+use std::time::Duration;
+
+use crate::{cache, error::ApiError};
+
+const LATEST_VERSION_TTL: Duration = Duration::from_secs(60 * 60);
+
+const DOWNLOAD_URL: &str = "https://maven.neoforged.net/releases";
+const NEOFORGE_GAV: &str = "net/neoforged/neoforge";
+
+pub async fn get_latest_installer_url(game_version: &str) -> Result<String, ApiError> {
+    let version = get_latest_version(game_version).await?;
+
+    // https://maven.neoforged.net/releases/net/neoforged/neoforge/[VERSION]/neoforge-[VERSION]-installer.jar
+    let url = format!("{DOWNLOAD_URL}/{NEOFORGE_GAV}/{version}/neoforge-{version}-installer.jar",);
+
+    Ok(url)
+}
+
+/// Get the latest NeoForge version for `game_version`
+pub async fn get_latest_version(game_version: &str) -> Result<String, ApiError> {
     use reqwest::Url;
 
     const MAIN_ENDPOINT: &str =
         "https://maven.neoforged.net/api/maven/latest/version/releases/net/neoforged/neoforge";
     const FALLBACK_ENDPOINT: &str =
         "https://maven.creeperhost.net/api/maven/latest/version/releases/net/neoforged/neoforge";
-    const DOWNLOAD_URL: &str = "https://maven.neoforged.net/releases";
-    const NEOFORGE_GAV: &str = "net/neoforged/neoforge";
-
     // Get latest NeoForge version for given MC version (filter=MC_MAJOR.MINOR)
     let mc_version_prefix = {
         let splits: Vec<&str> = game_version.split('.').collect();
         if splits.len() < 2 {
-            return Err(anyhow::anyhow!(
-                "invalid minecraft version: {}",
-                game_version
-            ));
+            return Err(ApiError::InvalidInput(format!(
+                "invalid minecraft version: {game_version}"
+            )));
         }
-        format!("{}.{}.", splits[1], splits[2])
+        format!("{}.{}.", splits[1], splits.get(2).unwrap_or(&"0"))
     };
 
     // Build request with filter param
-    let mut url = Url::parse(MAIN_ENDPOINT)?;
+    let mut url = Url::parse(MAIN_ENDPOINT).expect("valid endpoint url");
     url.query_pairs_mut()
         .append_pair("filter", &mc_version_prefix);
     // println!("{url:?}");
@@ -33,34 +48,29 @@ pub async fn get_latest_installer_url(game_version: &str) -> Result<String, anyh
     }
 
     // Fallback logic: try main endpoint, else fallback endpoint.
-    let version = match reqwest::get(url.clone()).await {
+    let version = match cache::get_text(url.as_str(), LATEST_VERSION_TTL).await {
         Ok(resp) => {
-            let json: LatestResp = resp.json().await?;
+            let json: LatestResp = serde_json::from_str(&resp)?;
             json.version
         }
         Err(_) => {
             // fallback
-            let mut fallback_url = Url::parse(FALLBACK_ENDPOINT)?;
+            let mut fallback_url = Url::parse(FALLBACK_ENDPOINT).expect("valid endpoint url");
             fallback_url
                 .query_pairs_mut()
                 .append_pair("filter", &mc_version_prefix);
-            let res = reqwest::get(fallback_url)
-                .await
-                .map_err(|e| anyhow::anyhow!("NeoForge main & fallback endpoints failed: {e}"))?;
-            let json: LatestResp = res.json().await?;
+            let res = cache::get_text(fallback_url.as_str(), LATEST_VERSION_TTL).await?;
+            let json: LatestResp = serde_json::from_str(&res)?;
             json.version
         }
     }
-    .ok_or(anyhow::anyhow!(
-        "No NeoForge version found for MC version {game_version}"
-    ))?;
+    .ok_or(ApiError::NotFound(format!(
+        "NeoForge version for MC version {game_version}"
+    )))?;
 
     // println!("{:?}", version);
 
-    // https://maven.neoforged.net/releases/net/neoforged/neoforge/[VERSION]/neoforge-[VERSION]-installer.jar
-    let url = format!("{DOWNLOAD_URL}/{NEOFORGE_GAV}/{version}/neoforge-{version}-installer.jar",);
-
-    Ok(url)
+    Ok(version)
 }
 
 #[cfg(test)]
@@ -1,8 +1,51 @@
-use anyhow::Context;
+use std::time::Duration;
 
-pub async fn get_latest_installer_url() -> Result<String, anyhow::Error> {
-    reqwest::get("https://quiltmc.org/api/v1/download-latest-installer/java-universal")
-        .await
-        .context("failed to get latest quilt installer url")
-        .map(|x| x.url().as_str().to_string())
+use crate::{
+    cache::{self, Fetched, HttpCache},
+    error::ApiError,
+};
+
+const INSTALLER_URL: &str = "https://quiltmc.org/api/v1/download-latest-installer/java-universal";
+const INSTALLER_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+const LOADER_TTL: Duration = Duration::from_secs(60 * 60);
+
+pub async fn get_latest_installer_url() -> Result<String, ApiError> {
+    // The endpoint redirects to the installer, so the final url is cached
+    // instead of the body
+    let key = cache::key("GET", INSTALLER_URL, &[]);
+    cache::cached(HttpCache::global(), &key, INSTALLER_TTL, |_| async {
+        let res = reqwest::get(INSTALLER_URL).await?.error_for_status()?;
+        Ok(Fetched::Modified {
+            body: res.url().as_str().to_string(),
+            etag: None,
+        })
+    })
+    .await
+}
+
+/// Get the latest quilt loader version for `game_version`, preferring
+/// non-prerelease ones
+pub async fn get_latest_loader_version(game_version: &str) -> Result<String, ApiError> {
+    #[derive(Debug, serde::Deserialize)]
+    struct LoaderMeta {
+        loader: LoaderVersion,
+    }
+    #[derive(Debug, serde::Deserialize)]
+    struct LoaderVersion {
+        version: String,
+    }
+
+    let res = cache::get_text(
+        &format!("https://meta.quiltmc.org/v3/versions/loader/{game_version}"),
+        LOADER_TTL,
+    )
+    .await?;
+    let res = serde_json::from_str::<Vec<LoaderMeta>>(&res)?;
+    res.iter()
+        .find(|meta| !meta.loader.version.contains('-'))
+        .or(res.first())
+        .map(|meta| meta.loader.version.clone())
+        .ok_or(ApiError::NotFound(format!(
+            "quilt loader for {game_version}"
+        )))
 }
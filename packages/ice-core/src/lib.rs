@@ -73,7 +73,9 @@ pub struct QuiltLoader;
 
 impl ServerLoaderTrait for QuiltLoader {
     fn get_latest_installer_url(_game_version: &str) -> Result<String, anyhow::Error> {
-        smol::block_on(Compat::new(api::quilt::get_latest_installer_url()))
+        Ok(smol::block_on(Compat::new(
+            api::quilt::get_latest_installer_url(),
+        ))?)
     }
     fn install_cmd(current_dir: &Path, installer_path: &Path, game_version: &str) -> Command {
         let mut cmd = Command::new("java");
@@ -94,7 +96,9 @@ pub struct FabricLoader;
 
 impl ServerLoaderTrait for FabricLoader {
     fn get_latest_installer_url(_game_version: &str) -> Result<String, anyhow::Error> {
-        smol::block_on(Compat::new(api::fabric::get_latest_installer_url()))
+        Ok(smol::block_on(Compat::new(
+            api::fabric::get_latest_installer_url(),
+        ))?)
     }
     fn install_cmd(current_dir: &Path, installer_path: &Path, game_version: &str) -> Command {
         let mut cmd = Command::new("java");
@@ -117,9 +121,9 @@ pub struct NeoForgeLoader;
 
 impl ServerLoaderTrait for NeoForgeLoader {
     fn get_latest_installer_url(game_version: &str) -> Result<String, anyhow::Error> {
-        smol::block_on(Compat::new(api::neoforge::get_latest_installer_url(
-            game_version,
-        )))
+        Ok(smol::block_on(Compat::new(
+            api::neoforge::get_latest_installer_url(game_version),
+        ))?)
     }
     fn install_cmd(current_dir: &Path, installer_path: &Path, _game_version: &str) -> Command {
         let mut cmd = Command::new("java");
@@ -157,9 +161,9 @@ mod convert {
     use super::ServerLoader;
     use ice_api_tool::modrinth::types::Loader as ModrinthLoader;
 
-    impl Into<ModrinthLoader> for ServerLoader {
-        fn into(self) -> ice_api_tool::modrinth::types::Loader {
-            match self {
+    impl From<ServerLoader> for ModrinthLoader {
+        fn from(value: ServerLoader) -> Self {
+            match value {
                 ServerLoader::Fabric => ModrinthLoader::Fabric,
                 ServerLoader::Quilt => ModrinthLoader::Quilt,
                 ServerLoader::NeoForge => ModrinthLoader::NeoForge,
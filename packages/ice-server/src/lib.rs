@@ -196,17 +196,13 @@ impl Core {
                     Event::ServerDown => {
                         _server.handle_event(event.clone());
                     }
-                    Event::ServerLog(msg) => {
-                        if _server.retain_log(&msg) {
-                            println!("{msg}");
-                        }
+                    Event::ServerLog(msg) if _server.retain_log(&msg) => {
+                        println!("{msg}");
                     }
-                    Event::PlayerMessage { player: _, msg } => {
-                        if msg.starts_with("#") {
-                            _command_tx
-                                .send(msg.clone())
-                                .expect("failed to send to command_tx");
-                        }
+                    Event::PlayerMessage { player: _, msg } if msg.starts_with("#") => {
+                        _command_tx
+                            .send(msg.clone())
+                            .expect("failed to send to command_tx");
                     }
                     Event::ServerDone => {
                         info!("server done");
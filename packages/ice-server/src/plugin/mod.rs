@@ -31,10 +31,8 @@ pub trait Plugin: Any + Send + Sync {
             Event::ServerLog(content) => self.on_server_log(content),
             Event::ServerDone => self.on_server_done(),
             Event::PlayerMessage { player, msg } => self.on_player_message(player, msg),
-            Event::PluginCallFn { plugin_id, fn_name } => {
-                if self.id() == plugin_id {
-                    self.on_call_fn(fn_name);
-                }
+            Event::PluginCallFn { plugin_id, fn_name } if self.id() == plugin_id => {
+                self.on_call_fn(fn_name);
             }
             _ => (),
         }
@@ -169,8 +169,8 @@ impl Server {
             .cloned()
             .unwrap_or_default();
         let config = to_dynamic(config).unwrap();
-        let config = from_dynamic(&config).unwrap();
-        config
+
+        from_dynamic(&config).unwrap()
     }
 
     pub fn running(&mut self) -> bool {
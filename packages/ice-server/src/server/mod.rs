@@ -1,4 +1,7 @@
-use std::{path::PathBuf, sync::{Arc, Mutex}};
+use std::{
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
 
 use ::regex::Regex;
 use ice_util::minecraft::rtext::{build_component, Component};
@@ -57,7 +60,7 @@ impl Server {
         let mut server = self.minecraft_server.lock().unwrap();
         if server.is_some() {
             error!("server is already running");
-            Err(format!("server is already running"))
+            Err("server is already running".to_string())
         } else {
             *server = Some(MinecraftServer::run(
                 &self.jar_dir,
@@ -91,16 +94,13 @@ impl Server {
             Ok(())
         } else {
             error!("no running server");
-            Err(format!("no running server"))
+            Err("no running server".to_string())
         }
     }
 
     pub fn handle_event(&self, event: Event) {
-        match event {
-            Event::ServerDown => {
-                *self.minecraft_server.lock().unwrap() = None;
-            }
-            _ => (),
+        if let Event::ServerDown = event {
+            *self.minecraft_server.lock().unwrap() = None;
         }
     }
 
@@ -24,6 +24,9 @@ pub fn get_parent_version(version: String) -> String {
 }
 
 /// Download a file from a URL to a local path.
+///
+/// `on_progress` is called with the downloaded and the total bytes, the total
+/// is 0 if the server does not tell the length.
 pub async fn download_from_url<S: AsRef<str>, P: AsRef<Path>>(
     url: S,
     path: P,
@@ -37,8 +40,8 @@ pub async fn download_from_url<S: AsRef<str>, P: AsRef<Path>>(
         }
     }
 
-    let res = reqwest::get(url).await?;
-    let total_bytes = res.content_length().unwrap();
+    let res = reqwest::get(url).await?.error_for_status()?;
+    let total_bytes = res.content_length().unwrap_or(0);
     let mut downloaded_bytes = 0;
 
     let mut file = smol::fs::File::create(path).await?;
@@ -60,13 +63,129 @@ pub async fn download_from_url<S: AsRef<str>, P: AsRef<Path>>(
     Ok(())
 }
 
+/// What a file is expected to be, any of them may be unknown
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Checksum {
+    pub size: Option<u64>,
+    pub sha1: Option<String>,
+    pub sha512: Option<String>,
+}
+
+impl Checksum {
+    /// What the file at `path` does not match, checked by the size and the
+    /// strongest known hash, sha512 is preferred over sha1
+    pub fn mismatch<P: AsRef<Path>>(&self, path: P) -> Result<Option<String>, std::io::Error> {
+        let path = path.as_ref();
+        if let Some(size) = self.size {
+            let actual = std::fs::metadata(path)?.len();
+            if actual != size {
+                return Ok(Some(format!(
+                    "size mismatch, expected {size} bytes, got {actual} bytes"
+                )));
+            }
+        }
+        let (algo, expected, actual) = match (&self.sha512, &self.sha1) {
+            (Some(expected), _) => ("sha512", expected, fs::get_sha512_hash(path)?),
+            (None, Some(expected)) => ("sha1", expected, fs::get_sha1_hash(path)?),
+            (None, None) => return Ok(None),
+        };
+        if !actual.eq_ignore_ascii_case(expected) {
+            return Ok(Some(format!(
+                "{algo} mismatch, expected {expected}, got {actual}"
+            )));
+        }
+        Ok(None)
+    }
+
+    /// Check the file at `path`, the error tells what does not match
+    pub fn verify<P: AsRef<Path>>(&self, path: P) -> Result<(), anyhow::Error> {
+        let path = path.as_ref();
+        match self
+            .mismatch(path)
+            .with_context(|| format!("failed to check {}", path.display()))?
+        {
+            Some(mismatch) => Err(anyhow::anyhow!(mismatch)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Download a file from a URL to a local path and verify it against
+/// `checksum`
+///
+/// An existing file at `path` is kept if it matches. Otherwise the file is
+/// downloaded to a temporary file next to `path` and only renamed to `path`
+/// once it matches, so `path` never holds a partial or corrupted file.
+pub async fn download_verified<S: AsRef<str>, P: AsRef<Path>>(
+    url: S,
+    path: P,
+    checksum: &Checksum,
+    on_progress: impl Fn((u64, u64)),
+) -> Result<(), anyhow::Error> {
+    let path = path.as_ref();
+    if path.exists() && checksum.verify(path).is_ok() {
+        return Ok(());
+    }
+
+    let filename = path
+        .file_name()
+        .context("the path has no file name")?
+        .to_string_lossy();
+    let part = path.with_file_name(format!(".{filename}.part"));
+    let res = async {
+        download_from_url(url, &part, on_progress).await?;
+        checksum.verify(&part)?;
+        std::fs::rename(&part, path).context("failed to move the file in place")
+    }
+    .await;
+    if res.is_err() {
+        let _ = std::fs::remove_file(&part);
+    }
+    res
+}
+
 #[cfg(test)]
 mod test {
-    use std::{io::Read, path::PathBuf};
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::TcpListener,
+        path::PathBuf,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    };
 
-    use sha::sha1::Sha1;
+    use crate::{download_from_url, download_verified, fs::get_sha1_hash, Checksum};
 
-    use crate::{download_from_url, fs::get_sha1_hash};
+    /// Serve `body` to every request on a local port, returning the url and
+    /// the number of requests served
+    fn serve(body: &'static str) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/file.jar", listener.local_addr().unwrap());
+        let served = Arc::new(AtomicUsize::new(0));
+        let count = served.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else {
+                    return;
+                };
+                // Skip the request up to the blank line ending its headers
+                let mut reader = BufReader::new(&stream);
+                let mut line = String::new();
+                while reader.read_line(&mut line).is_ok_and(|n| n > 2) {
+                    line.clear();
+                }
+                count.fetch_add(1, Ordering::SeqCst);
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+            }
+        });
+        (url, served)
+    }
 
     #[tokio::test]
     async fn test_doawnload_from_url() {
@@ -85,4 +204,77 @@ mod test {
 
         assert_eq!(sha_1, sha_2);
     }
+
+    #[test]
+    fn test_checksum() {
+        let test_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("test");
+        std::fs::create_dir_all(&test_dir).unwrap();
+        let path = test_dir.join("checksum.txt");
+        std::fs::write(&path, "ice").unwrap();
+
+        let sha1 = "d53b257ba8da274144b4d8d998f10bbc3e7d6704".to_string();
+        assert!(Checksum::default().verify(&path).is_ok());
+        let checksum = Checksum {
+            size: Some(3),
+            sha1: Some(sha1.to_uppercase()),
+            sha512: None,
+        };
+        assert!(checksum.verify(&path).is_ok());
+
+        let truncated = Checksum {
+            size: Some(4),
+            ..checksum.clone()
+        };
+        let err = truncated.verify(&path).unwrap_err().to_string();
+        assert_eq!(err, "size mismatch, expected 4 bytes, got 3 bytes");
+
+        // sha512 is checked instead of sha1 when both are known
+        let corrupted = Checksum {
+            sha512: Some("00".to_string()),
+            ..checksum
+        };
+        let err = corrupted.verify(&path).unwrap_err().to_string();
+        assert!(err.starts_with("sha512 mismatch, expected 00, got "));
+    }
+
+    #[tokio::test]
+    async fn test_download_verified() {
+        let test_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("test")
+            .join("verified");
+        let _ = std::fs::remove_dir_all(&test_dir);
+        std::fs::create_dir_all(&test_dir).unwrap();
+        let path = test_dir.join("file.jar");
+        let part = test_dir.join(".file.jar.part");
+        let (url, served) = serve("ice");
+        let checksum = Checksum {
+            size: Some(3),
+            sha1: Some("d53b257ba8da274144b4d8d998f10bbc3e7d6704".to_string()),
+            sha512: None,
+        };
+
+        // A body not matching is thrown away
+        let corrupted = Checksum {
+            sha1: Some("00".to_string()),
+            ..checksum.clone()
+        };
+        let err = download_verified(&url, &path, &corrupted, |_| ()).await;
+        assert!(err.unwrap_err().to_string().starts_with("sha1 mismatch"));
+        assert!(!path.exists());
+        assert!(!part.exists());
+
+        // A matching one is moved in place
+        download_verified(&url, &path, &checksum, |_| ())
+            .await
+            .unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "ice");
+        assert!(!part.exists());
+        assert_eq!(served.load(Ordering::SeqCst), 2);
+
+        // A valid file is not downloaded again
+        download_verified(&url, &path, &checksum, |_| ())
+            .await
+            .unwrap();
+        assert_eq!(served.load(Ordering::SeqCst), 2);
+    }
 }
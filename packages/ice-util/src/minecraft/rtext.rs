@@ -3,7 +3,7 @@ use std::fmt::Display;
 use serde::{Deserialize, Serialize};
 
 /// Different representations of a minecraft text component
-/// 
+///
 /// Do not directly construct it, use [`ComponentObject`] or other variant's [`into`] method instead
 ///
 /// See https://zh.minecraft.wiki/w/%E6%96%87%E6%9C%AC%E7%BB%84%E4%BB%B6
@@ -0,0 +1,556 @@
+use std::collections::HashMap;
+
+use anyhow::Context;
+use color_print::cformat;
+use futures::{StreamExt, stream};
+use ice::{
+    config::LocalModsConfig,
+    core::{ContentKind, CurseForgeMod, Mod, ModrinthMod},
+};
+use ice_api_tool::{self as api, curseforge::CurseForgeClient, modrinth::ModrinthClient};
+use serde::Serialize;
+use tracing::info;
+
+use super::{curseforge, modrinth::init_logger};
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ReportFormat {
+    #[default]
+    Table,
+    Json,
+}
+
+/// Which of the game versions each entry of mods.toml has a version for
+#[derive(Serialize, Debug)]
+struct CompatReport {
+    /// The `version` of mods.toml
+    current: String,
+    /// The checked game versions, oldest first
+    game_versions: Vec<GameVersionReport>,
+    entries: Vec<EntryReport>,
+    /// The newest game version supported by every entry
+    suggested: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+struct GameVersionReport {
+    game_version: String,
+    /// The entries without a version for it
+    blockers: Vec<String>,
+    /// The entries failed to be checked
+    unknown: Vec<String>,
+}
+
+#[derive(Serialize, Debug)]
+struct EntryReport {
+    kind: ContentKind,
+    slug: String,
+    version_number: String,
+    dependency: bool,
+    /// In the order of [`CompatReport::game_versions`]
+    support: Vec<Support>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "status", rename_all = "lowercase")]
+enum Support {
+    /// The latest version on the channel of the entry
+    Supported {
+        version_number: String,
+    },
+    Unsupported,
+    Failed {
+        error: String,
+    },
+}
+
+/// The `check --matrix` command
+///
+/// Checks every Modrinth and CurseForge entry against the current game version and
+/// `versions`, which default to every release newer than the current one.
+/// They are checked oldest first whatever order they are given in.
+pub async fn check_matrix(
+    client: &ModrinthClient,
+    curseforge: &CurseForgeClient,
+    versions: Vec<String>,
+    format: ReportFormat,
+    config: &LocalModsConfig,
+) {
+    init_logger();
+
+    let game_versions = match versions.is_empty() {
+        true => newer_releases(&config.version)
+            .await
+            .map(|versions| [vec![config.version.clone()], versions].concat()),
+        false => oldest_first([vec![config.version.clone()], versions].concat()).await,
+    };
+    let game_versions = match game_versions {
+        Ok(game_versions) => game_versions,
+        Err(err) => {
+            info!("{}", cformat!("<r>Failed</> {:?}", err));
+            return;
+        }
+    };
+
+    let report = compat_report(client, curseforge, game_versions, config).await;
+    match format {
+        ReportFormat::Table => print_table(&report),
+        ReportFormat::Json => println!("{}", serde_json::to_string_pretty(&report).unwrap()),
+    }
+}
+
+/// Every release newer than `version`, oldest first
+async fn newer_releases(version: &str) -> Result<Vec<String>, anyhow::Error> {
+    let manifest = api::mojang::get_version_manifest()
+        .await
+        .context("failed to get version manifest")?;
+    manifest
+        .releases_after(version)
+        .with_context(|| format!("{version} is not in the version manifest"))
+}
+
+/// `versions` without duplicates, sorted oldest first by their positions in
+/// the version manifest
+async fn oldest_first(versions: Vec<String>) -> Result<Vec<String>, anyhow::Error> {
+    let manifest = api::mojang::get_version_manifest()
+        .await
+        .context("failed to get version manifest")?;
+    let mut versions = versions
+        .into_iter()
+        .map(|version| match manifest.position(&version) {
+            Some(position) => Ok((position, version)),
+            None => anyhow::bail!("{version} is not in the version manifest"),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    versions.sort_by_key(|(position, _)| std::cmp::Reverse(*position));
+    versions.dedup();
+    Ok(versions.into_iter().map(|(_, version)| version).collect())
+}
+
+/// The support of `game_versions`, oldest first, by the entries of mods.toml
+async fn compat_report(
+    client: &ModrinthClient,
+    curseforge: &CurseForgeClient,
+    game_versions: Vec<String>,
+    config: &LocalModsConfig,
+) -> CompatReport {
+    let modrinth_entries = config
+        .kinds()
+        .into_iter()
+        .flat_map(|kind| {
+            config
+                .get_entries(kind)
+                .into_iter()
+                .filter_map(move |m| match m {
+                    Mod::Modrinth(m) => Some((kind, m)),
+                    _ => None,
+                })
+        })
+        .collect::<Vec<_>>();
+    let curseforge_entries = config
+        .kinds()
+        .into_iter()
+        .flat_map(|kind| {
+            curseforge::entries(kind, config)
+                .into_iter()
+                .map(move |m| (kind, m))
+        })
+        .collect::<Vec<_>>();
+    // The current files, to name the versions of the CurseForge entries
+    let names = match curseforge
+        .get_files(curseforge_entries.iter().map(|(_, m)| m.file_id))
+        .await
+    {
+        Ok(files) => files
+            .into_iter()
+            .map(|f| (f.id, f.display_name))
+            .collect::<HashMap<_, _>>(),
+        Err(_) => HashMap::new(),
+    };
+
+    let mut entries = stream::iter(modrinth_entries)
+        .map(|(kind, m)| {
+            let game_versions = &game_versions;
+            async move {
+                let support = entry_support(client, kind, &m, game_versions, config).await;
+                EntryReport {
+                    kind,
+                    dependency: kind == ContentKind::Mod && config.is_dependency(&m.slug),
+                    version_number: m.version_number().to_string(),
+                    slug: m.slug,
+                    support,
+                }
+            }
+        })
+        .buffer_unordered(5)
+        .collect::<Vec<_>>()
+        .await;
+    entries.extend(
+        stream::iter(curseforge_entries)
+            .map(|(kind, m)| {
+                let game_versions = &game_versions;
+                let names = &names;
+                async move {
+                    let support =
+                        curseforge_support(curseforge, kind, &m, game_versions, config).await;
+                    EntryReport {
+                        kind,
+                        dependency: kind == ContentKind::Mod && config.is_dependency(&m.slug),
+                        version_number: names
+                            .get(&m.file_id)
+                            .cloned()
+                            .unwrap_or(m.file_id.to_string()),
+                        slug: m.slug,
+                        support,
+                    }
+                }
+            })
+            .buffer_unordered(5)
+            .collect::<Vec<_>>()
+            .await,
+    );
+    entries.sort_by(|a, b| (a.kind, &a.slug).cmp(&(b.kind, &b.slug)));
+
+    let game_versions = game_versions
+        .into_iter()
+        .enumerate()
+        .map(|(i, game_version)| {
+            let slugs_with = |f: fn(&Support) -> bool| {
+                entries
+                    .iter()
+                    .filter(|e| f(&e.support[i]))
+                    .map(|e| e.slug.clone())
+                    .collect::<Vec<_>>()
+            };
+            GameVersionReport {
+                game_version,
+                blockers: slugs_with(|s| matches!(s, Support::Unsupported)),
+                unknown: slugs_with(|s| matches!(s, Support::Failed { .. })),
+            }
+        })
+        .collect::<Vec<_>>();
+    let suggested = game_versions
+        .iter()
+        .rev()
+        .find(|v| v.blockers.is_empty() && v.unknown.is_empty())
+        .map(|v| v.game_version.clone());
+
+    CompatReport {
+        current: config.version.clone(),
+        game_versions,
+        entries,
+        suggested,
+    }
+}
+
+/// The support of `game_versions` by the entry `m`
+///
+/// All the versions of the project are requested once and filtered locally.
+async fn entry_support(
+    client: &ModrinthClient,
+    kind: ContentKind,
+    m: &ModrinthMod,
+    game_versions: &[String],
+    config: &LocalModsConfig,
+) -> Vec<Support> {
+    let versions = match client.get_project_versions(&m.slug, None, None).await {
+        Ok(versions) => versions,
+        Err(err) => {
+            let error = format!("{err}");
+            return vec![Support::Failed { error }; game_versions.len()];
+        }
+    };
+    let loaders = kind.loaders(config.loader);
+    let channel = config.entry_channel(kind, &m.slug);
+
+    game_versions
+        .iter()
+        .map(|game_version| {
+            versions
+                .iter()
+                .filter(|v| v.is_compatible(&loaders, game_version, channel))
+                .max_by_key(|v| v.date_published)
+                .map(|v| Support::Supported {
+                    version_number: v.version_number.clone(),
+                })
+                .unwrap_or(Support::Unsupported)
+        })
+        .collect()
+}
+
+/// The support of `game_versions` by the CurseForge entry `m`
+///
+/// The files of the project are requested once per game version.
+async fn curseforge_support(
+    client: &CurseForgeClient,
+    kind: ContentKind,
+    m: &CurseForgeMod,
+    game_versions: &[String],
+    config: &LocalModsConfig,
+) -> Vec<Support> {
+    let channel = config.entry_channel(kind, &m.slug);
+    stream::iter(game_versions)
+        .then(|game_version| async move {
+            match curseforge::compatible_files(
+                client,
+                kind,
+                m.project_id,
+                channel,
+                game_version,
+                config,
+            )
+            .await
+            {
+                Ok(files) => files
+                    .into_iter()
+                    .max_by_key(|f| f.file_date)
+                    .map(|f| Support::Supported {
+                        version_number: f.display_name,
+                    })
+                    .unwrap_or(Support::Unsupported),
+                Err(err) => Support::Failed {
+                    error: format!("{:#}", err),
+                },
+            }
+        })
+        .collect()
+        .await
+}
+
+fn print_table(report: &CompatReport) {
+    let cell = |support: &Support| match support {
+        Support::Supported { version_number } => version_number.clone(),
+        Support::Unsupported => "-".to_string(),
+        Support::Failed { .. } => "?".to_string(),
+    };
+
+    let slug_width = report
+        .entries
+        .iter()
+        .map(|e| e.slug.len())
+        .chain([4])
+        .max()
+        .unwrap();
+    let widths = report
+        .game_versions
+        .iter()
+        .enumerate()
+        .map(|(i, v)| {
+            report
+                .entries
+                .iter()
+                .map(|e| cell(&e.support[i]).len())
+                .chain([v.game_version.len()])
+                .max()
+                .unwrap()
+        })
+        .collect::<Vec<_>>();
+
+    let mut header = cformat!("<bold>{:<slug_width$}</>", "slug");
+    for (v, width) in report.game_versions.iter().zip(&widths) {
+        header += &cformat!("  <bold>{:<width$}</>", v.game_version);
+    }
+    println!("{}", header.trim_end());
+
+    for entry in &report.entries {
+        let mut row = format!("{:<slug_width$}", entry.slug);
+        for (support, width) in entry.support.iter().zip(&widths) {
+            let text = cell(support);
+            row += &match support {
+                Support::Supported { .. } => cformat!("  <g>{:<width$}</>", text),
+                Support::Unsupported => cformat!("  <r>{:<width$}</>", text),
+                Support::Failed { .. } => cformat!("  <y>{:<width$}</>", text),
+            };
+        }
+        println!("{}", row.trim_end());
+    }
+    println!();
+
+    for v in &report.game_versions {
+        if v.blockers.is_empty() && v.unknown.is_empty() {
+            println!("{}", cformat!("<g>Supported</> {}", v.game_version));
+            continue;
+        }
+        let mut line = cformat!("<r>Blocked</> {}", v.game_version);
+        if !v.blockers.is_empty() {
+            line += &format!(" by {}", v.blockers.join(", "));
+        }
+        if !v.unknown.is_empty() {
+            line += &format!(" (failed to check {})", v.unknown.join(", "));
+        }
+        println!("{line}");
+    }
+    match &report.suggested {
+        Some(version) => println!("{}", cformat!("suggested version: <g>{}</>", version)),
+        None => println!("{}", cformat!("suggested version: <r>none</>")),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use async_compat::Compat;
+    use ice_api_tool::mock::{MockResponse, MockServer};
+    use serde_json::{Value, json};
+
+    use super::*;
+    use crate::cli::temp_dir;
+
+    /// A version of `project_id` for `game_version` only
+    fn version(project_id: &str, game_version: &str) -> Value {
+        json!({
+            "name": game_version,
+            "version_number": game_version,
+            "changelog": null,
+            "dependencies": [],
+            "game_versions": [game_version],
+            "version_type": "release",
+            "loaders": ["fabric"],
+            "featured": false,
+            "id": format!("{project_id}{game_version}"),
+            "project_id": project_id,
+            "date_published": "2025-01-01T00:00:00Z",
+            "downloads": 0,
+            "files": []
+        })
+    }
+
+    /// A release file of the CurseForge project 10 for `game_version` only
+    fn file(id: u32, game_version: &str) -> Value {
+        json!({
+            "id": id,
+            "modId": 10,
+            "displayName": format!("delta {game_version}"),
+            "fileName": format!("delta-{game_version}.jar"),
+            "releaseType": 1,
+            "hashes": [],
+            "fileDate": "2025-01-01T00:00:00Z",
+            "fileLength": 1,
+            "downloadUrl": null,
+            "gameVersions": [game_version, "Fabric"],
+            "dependencies": [],
+            "fileFingerprint": id
+        })
+    }
+
+    /// The report of the entries of `mods` against 1.21.6 to 1.21.8, with
+    /// alpha having versions for all of them, and beta and the CurseForge
+    /// project delta for 1.21.6 and 1.21.7
+    fn report(name: &str, mods: &str) -> CompatReport {
+        let dir = temp_dir(&format!("compat-{name}"));
+        let config = format!("version = \"1.21.6\"\nloader = \"fabric\"\n\n[mods]\n{mods}");
+        fs::write(dir.join("mods.toml"), config).unwrap();
+        let config = LocalModsConfig::load(dir.join("mods.toml")).unwrap();
+        let _ = fs::remove_dir_all(&dir);
+
+        let alpha = ["1.21.6", "1.21.7", "1.21.8"].map(|v| version("alpha", v));
+        let beta = ["1.21.6", "1.21.7"].map(|v| version("beta", v));
+        let delta = [file(100, "1.21.6"), file(101, "1.21.7")];
+        // gamma is not served, so it fails to be checked
+        let api = MockServer::route(vec![
+            (
+                "GET /v2/project/alpha/version",
+                MockResponse::json(json!(alpha).to_string()),
+            ),
+            (
+                "GET /v2/project/beta/version",
+                MockResponse::json(json!(beta).to_string()),
+            ),
+            (
+                "GET /mods/10/files",
+                MockResponse::json(json!({ "data": delta }).to_string()),
+            ),
+            (
+                "POST /mods/files",
+                MockResponse::json(json!({ "data": [delta[0]] }).to_string()),
+            ),
+        ]);
+        let client = ModrinthClient::new().with_base_url(format!("{}/v2", api.url));
+        let curseforge = CurseForgeClient::new()
+            .with_base_url(&api.url)
+            .with_api_key("key");
+        let game_versions = ["1.21.6", "1.21.7", "1.21.8"].map(String::from).to_vec();
+        smol::block_on(Compat::new(compat_report(
+            &client,
+            &curseforge,
+            game_versions,
+            &config,
+        )))
+    }
+
+    #[test]
+    fn test_compat_report() {
+        let report = report(
+            "report",
+            "alpha = \"alpha1.21.6#1.21.6\"\nbeta = \"beta1.21.6#1.21.6\"\n\
+             delta = { curseforge = 10, file-id = 100 }\n",
+        );
+        // beta and delta block 1.21.8, so the newest clean game version is 1.21.7
+        assert_eq!(
+            serde_json::to_value(&report).unwrap(),
+            json!({
+                "current": "1.21.6",
+                "game_versions": [
+                    { "game_version": "1.21.6", "blockers": [], "unknown": [] },
+                    { "game_version": "1.21.7", "blockers": [], "unknown": [] },
+                    { "game_version": "1.21.8", "blockers": ["beta", "delta"], "unknown": [] }
+                ],
+                "entries": [
+                    {
+                        "kind": "mod",
+                        "slug": "alpha",
+                        "version_number": "1.21.6",
+                        "dependency": false,
+                        "support": [
+                            { "status": "supported", "version_number": "1.21.6" },
+                            { "status": "supported", "version_number": "1.21.7" },
+                            { "status": "supported", "version_number": "1.21.8" }
+                        ]
+                    },
+                    {
+                        "kind": "mod",
+                        "slug": "beta",
+                        "version_number": "1.21.6",
+                        "dependency": false,
+                        "support": [
+                            { "status": "supported", "version_number": "1.21.6" },
+                            { "status": "supported", "version_number": "1.21.7" },
+                            { "status": "unsupported" }
+                        ]
+                    },
+                    {
+                        "kind": "mod",
+                        "slug": "delta",
+                        "version_number": "delta 1.21.6",
+                        "dependency": false,
+                        "support": [
+                            { "status": "supported", "version_number": "delta 1.21.6" },
+                            { "status": "supported", "version_number": "delta 1.21.7" },
+                            { "status": "unsupported" }
+                        ]
+                    }
+                ],
+                "suggested": "1.21.7"
+            })
+        );
+    }
+
+    #[test]
+    fn test_compat_report_unknown() {
+        let report = report(
+            "unknown",
+            "alpha = \"alpha1.21.6#1.21.6\"\ngamma = \"gamma1.0#1.0\"\n",
+        );
+        for v in &report.game_versions {
+            assert!(v.blockers.is_empty());
+            assert_eq!(v.unknown, ["gamma"]);
+        }
+        assert!(matches!(
+            report.entries[1].support[0],
+            Support::Failed { .. }
+        ));
+        // No game version is suggested while an entry is unknown
+        assert_eq!(report.suggested, None);
+    }
+}
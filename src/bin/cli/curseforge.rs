@@ -0,0 +1,658 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, bail};
+use color_print::cformat;
+use futures::{StreamExt, TryStreamExt, stream};
+use ice::{
+    config::LocalModsConfig,
+    core::{ContentKind, CurseForgeMod, Mod, Side},
+};
+use ice_api_tool::{
+    cache::HttpCache,
+    curseforge::{
+        self, CurseForgeClient,
+        types::{File, Project, RelationType},
+    },
+    modrinth::types::VersionType,
+};
+use ice_util::download_verified;
+use indicatif::ProgressStyle;
+use tracing::{Instrument, info, info_span};
+use tracing_indicatif::span_ext::IndicatifSpanExt;
+
+use super::{
+    modrinth::{init_logger, keep_entry_on_side, report_held_back},
+    plan::PlannedUpdate,
+    url,
+};
+
+/// Build the [`CurseForgeClient`] used by the `mod` commands
+///
+/// The api key is taken from `CURSEFORGE_API_KEY`, and the api base url can
+/// be overridden with `ICE_CURSEFORGE_API`
+pub fn curseforge_client() -> CurseForgeClient {
+    let client = CurseForgeClient::new();
+    let client = match HttpCache::global() {
+        Some(cache) => client.with_cache(cache.clone()),
+        None => client,
+    };
+    match std::env::var("ICE_CURSEFORGE_API") {
+        Ok(base_url) => client.with_base_url(base_url),
+        Err(_) => client,
+    }
+}
+
+/// The CurseForge entries in the table of `kind`
+pub(crate) fn entries(kind: ContentKind, config: &LocalModsConfig) -> Vec<CurseForgeMod> {
+    config
+        .get_entries(kind)
+        .into_iter()
+        .filter_map(|m| match m {
+            Mod::CurseForge(m) => Some(m),
+            _ => None,
+        })
+        .collect()
+}
+
+// MARK: Sync
+
+/// Resolve local files to CurseForge files by their fingerprints
+///
+/// Files unknown to CurseForge are missing from the result.
+pub(crate) async fn resolve_files(
+    client: &CurseForgeClient,
+    paths: Vec<PathBuf>,
+) -> Result<HashMap<PathBuf, File>, anyhow::Error> {
+    if paths.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let fingerprints = stream::iter(paths)
+        .map(|path| {
+            smol::unblock(move || {
+                let bytes =
+                    fs::read(&path).context(format!("failed to read {}", path.display()))?;
+                Ok::<_, anyhow::Error>((path, curseforge::fingerprint(&bytes)))
+            })
+        })
+        .buffer_unordered(8)
+        .try_collect::<Vec<_>>()
+        .await?;
+
+    let mut files = client
+        .get_files_from_fingerprints(fingerprints.iter().map(|(_, fingerprint)| *fingerprint))
+        .await
+        .context("failed to get files from fingerprints")?;
+    Ok(fingerprints
+        .into_iter()
+        .filter_map(|(path, fingerprint)| files.remove(&fingerprint).map(|file| (path, file)))
+        .collect())
+}
+
+// MARK: Update
+
+/// The CurseForge entries of `kind` to move to their latest files
+///
+/// Only the entries installed on `side` are updated, if it is given
+pub(crate) async fn entry_updates(
+    client: &CurseForgeClient,
+    kind: ContentKind,
+    side: Option<Side>,
+    config: &LocalModsConfig,
+) -> Vec<PlannedUpdate> {
+    let curseforge_mods = entries(kind, config)
+        .into_iter()
+        .filter(|m| keep_entry_on_side(kind, &m.slug, side, config))
+        .collect::<Vec<_>>();
+
+    let results = stream::iter(curseforge_mods)
+        .map(|m| {
+            let channel = config.entry_channel(kind, &m.slug);
+            let constraint = config.entry_constraint(kind, &m.slug);
+            async move {
+                let res = async {
+                    let files = compatible_files(
+                        client,
+                        kind,
+                        m.project_id,
+                        channel,
+                        &config.version,
+                        config,
+                    )
+                    .await?;
+                    let latest = files
+                        .iter()
+                        .max_by_key(|f| f.file_date)
+                        .with_context(|| format!("no file for {}", config.version))?;
+                    let file = files
+                        .iter()
+                        .filter(|f| {
+                            constraint.allows(&f.id.to_string(), &f.display_name, &config.version)
+                        })
+                        .max_by_key(|f| f.file_date);
+                    let held_back = (file.map(|f| f.id).unwrap_or(m.file_id) != latest.id)
+                        .then(|| latest.display_name.clone());
+                    // The current file, to name the version it is moved from
+                    let cur_file = files.iter().find(|f| f.id == m.file_id);
+                    let file = file.filter(|f| f.id != m.file_id).map(|f| {
+                        let from = cur_file
+                            .map(|f| f.display_name.clone())
+                            .unwrap_or(m.file_id.to_string());
+                        (from, f.clone())
+                    });
+                    Ok::<_, anyhow::Error>((file, held_back))
+                }
+                .await;
+                (m, res, constraint)
+            }
+        })
+        .buffer_unordered(5)
+        .collect::<Vec<_>>()
+        .await;
+
+    let mut updates = vec![];
+    for (m, res, constraint) in results {
+        let (file, held_back) = match res {
+            Ok(res) => res,
+            Err(err) => {
+                info!(
+                    "{}",
+                    cformat!(
+                        "<r>Failed</> update {kind} {} ({})",
+                        m.slug,
+                        err.root_cause()
+                    )
+                );
+                continue;
+            }
+        };
+        if let Some(latest) = held_back {
+            report_held_back(&m.slug, &latest, &constraint);
+        }
+        let Some((from, file)) = file else {
+            info!(
+                "{}",
+                cformat!("<y>Unchanged</> {} = {} (CurseForge)", m.slug, m.file_id)
+            );
+            continue;
+        };
+        updates.push(PlannedUpdate {
+            kind,
+            slug: m.slug.clone(),
+            from,
+            to: file.display_name,
+            entry: Mod::CurseForge(CurseForgeMod {
+                file_id: file.id,
+                ..m
+            }),
+        });
+    }
+    updates
+}
+
+/// The latest file of a project for the game version and loader of
+/// mods.toml, on `channel`
+async fn latest_file(
+    client: &CurseForgeClient,
+    kind: ContentKind,
+    project_id: u32,
+    channel: VersionType,
+    config: &LocalModsConfig,
+) -> Result<Option<File>, anyhow::Error> {
+    let files =
+        compatible_files(client, kind, project_id, channel, &config.version, config).await?;
+    Ok(files.into_iter().max_by_key(|f| f.file_date))
+}
+
+/// The files of a project for `game_version` and the loader of mods.toml, on
+/// `channel`
+pub(crate) async fn compatible_files(
+    client: &CurseForgeClient,
+    kind: ContentKind,
+    project_id: u32,
+    channel: VersionType,
+    game_version: &str,
+    config: &LocalModsConfig,
+) -> Result<Vec<File>, anyhow::Error> {
+    let loaders = kind.curseforge_loaders(config.loader);
+    let files = client
+        .get_project_files(project_id, Some(game_version), None)
+        .await
+        .context("failed to get files")?;
+    Ok(files
+        .into_iter()
+        .filter(|f| f.is_compatible(&loaders, game_version, channel))
+        .collect())
+}
+
+// MARK: Add
+
+/// The `add --source curseforge` command
+///
+/// `slugs` may also be project ids. Required dependencies are added too
+/// (transitively), unless an entry with the same slug already exists.
+///
+/// The projects whose files are not for `side` are skipped, along with their
+/// dependencies.
+pub async fn add(
+    client: &CurseForgeClient,
+    slugs: Vec<String>,
+    kind: Option<ContentKind>,
+    current_dir: impl AsRef<Path>,
+    side: Option<Side>,
+    config: &mut LocalModsConfig,
+) {
+    init_logger();
+    let current_dir = current_dir.as_ref();
+
+    let mut queue = VecDeque::new();
+    for slug in slugs {
+        match project_of(client, &slug, kind).await {
+            Ok(project) => queue.push_back((project, kind, None)),
+            Err(err) => info!("{}", cformat!("<r>Failed</> {:?}", err)),
+        }
+    }
+
+    let mut visited = HashSet::new();
+    while let Some((project, kind, required_by)) = queue.pop_front() {
+        if !visited.insert(project.id) {
+            continue;
+        }
+        let kind = kind
+            .or(project.class_id.and_then(ContentKind::of_curseforge_class))
+            .unwrap_or(ContentKind::Mod);
+        if config.get_entry(kind, &project.slug).is_some() {
+            info!(
+                "{}",
+                cformat!("<y>Already Exist</> {} {}", kind, project.slug)
+            );
+            continue;
+        }
+
+        let res = add_project(client, kind, &project, current_dir, side, config)
+            .await
+            .context(format!("add {kind} {}", project.slug));
+        let file = match res {
+            Ok(Some(file)) => file,
+            Ok(None) => continue,
+            Err(err) => {
+                info!("{}", cformat!("<r>Failed</> {:?}", err));
+                continue;
+            }
+        };
+        if kind == ContentKind::Mod {
+            config.set_dependency(&project.slug, required_by.is_some());
+            config.save().unwrap();
+        }
+        match &required_by {
+            Some(dependent) => info!(
+                "{}",
+                cformat!(
+                    "<g>Added</> {} = {} (CurseForge, dependency of {})",
+                    project.slug,
+                    file.id,
+                    dependent
+                )
+            ),
+            None => info!(
+                "{}",
+                cformat!("<g>Added</> {} = {} (CurseForge)", project.slug, file.id)
+            ),
+        }
+
+        let dependencies = file
+            .dependencies
+            .iter()
+            .filter(|d| d.relation_type == RelationType::RequiredDependency)
+            .map(|d| d.mod_id)
+            .filter(|id| !visited.contains(id))
+            .collect::<Vec<_>>();
+        match client.get_projects(dependencies).await {
+            Ok(projects) => queue.extend(
+                projects
+                    .into_iter()
+                    .map(|p| (p, Some(kind), Some(project.slug.clone()))),
+            ),
+            Err(err) => info!(
+                "{}",
+                cformat!(
+                    "<r>Failed</> {:?}",
+                    anyhow::Error::from(err)
+                        .context(format!("get dependencies of {}", project.slug))
+                )
+            ),
+        }
+    }
+    info!("done!")
+}
+
+/// Get the project `slug_or_id`, slugs are looked up in the class of `kind`
+async fn project_of(
+    client: &CurseForgeClient,
+    slug_or_id: &str,
+    kind: Option<ContentKind>,
+) -> Result<Project, anyhow::Error> {
+    let project = match slug_or_id.parse::<u32>() {
+        Ok(id) => client.get_project(id).await,
+        Err(_) => {
+            let class_id = kind.unwrap_or(ContentKind::Mod).curseforge_class_id();
+            client.get_project_from_slug(slug_or_id, class_id).await
+        }
+    };
+    project.context(format!("failed to get project {slug_or_id}"))
+}
+
+/// Download the latest file of `project` and record it in the table of `kind`
+///
+/// Nothing is added if the file is not for `side`, reporting it as skipped.
+async fn add_project(
+    client: &CurseForgeClient,
+    kind: ContentKind,
+    project: &Project,
+    current_dir: &Path,
+    side: Option<Side>,
+    config: &mut LocalModsConfig,
+) -> Result<Option<File>, anyhow::Error> {
+    let channel = config.entry_channel(kind, &project.slug);
+    let Some(file) = latest_file(client, kind, project.id, channel, config).await? else {
+        bail!(
+            "no {channel} file of {} for {} {}",
+            project.slug,
+            config.loader,
+            config.version
+        );
+    };
+    if let Some(side) = side
+        && !is_file_on_side(&file, side)
+    {
+        info!(
+            "{}",
+            cformat!("<dim>Skipped</> {} (not for {})", project.slug, side)
+        );
+        return Ok(None);
+    }
+    download_file(&file, kind.dir(current_dir)).await?;
+
+    config.insert_entry(
+        kind,
+        Mod::CurseForge(CurseForgeMod {
+            slug: project.slug.clone(),
+            project_id: project.id,
+            file_id: file.id,
+        }),
+    );
+    config.save().unwrap();
+    Ok(Some(file))
+}
+
+/// Whether `file` runs on `side`, by the `Client` and `Server` environments
+/// listed in its game versions, every side if it lists none
+fn is_file_on_side(file: &File, side: Side) -> bool {
+    let environments = file
+        .game_versions
+        .iter()
+        .filter(|v| ["client", "server"].contains(&v.to_lowercase().as_str()))
+        .collect::<Vec<_>>();
+    environments.is_empty()
+        || environments
+            .iter()
+            .any(|v| v.eq_ignore_ascii_case(&side.to_string()))
+}
+
+// MARK: Inner
+
+/// Download a CurseForge file into `dir`, skipping it if it exists
+///
+/// This function creates a span with spinner
+async fn download_file(file: &File, dir: impl AsRef<Path>) -> Result<(), anyhow::Error> {
+    let dir = dir.as_ref();
+    let path = dir.join(&file.file_name);
+    let Some(download_url) = &file.download_url else {
+        if path.exists() {
+            return file.checksum().verify(&path);
+        }
+        bail!(
+            "the author does not allow downloading {} from other apps, put it under {} by hand",
+            file.file_name,
+            dir.display()
+        );
+    };
+
+    let checksum = file.checksum();
+    url::report_mismatch(&path, &checksum);
+    let span = info_span!("downloading", file = file.file_name);
+    span.pb_set_style(
+        &ProgressStyle::with_template(
+            "{span_child_prefix}{spinner} {span_name:.bold}{{{span_fields}}}:\n  {msg:.dim}",
+        )
+        .unwrap(),
+    );
+    async {
+        download_verified(download_url, path, &checksum, |_| {})
+            .await
+            .context("failed to download file")
+    }
+    .instrument(span)
+    .await
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use async_compat::Compat;
+    use ice_api_tool::mock::{MockResponse, MockServer};
+    use serde_json::{Value, json};
+
+    use super::*;
+    use crate::cli::temp_dir;
+
+    fn project(id: u32, slug: &str) -> Value {
+        json!({
+            "id": id,
+            "name": slug,
+            "slug": slug,
+            "summary": "",
+            "classId": 6,
+            "links": { "websiteUrl": null }
+        })
+    }
+
+    /// A release file of the project `mod_id` for 1.21.6 and Fabric
+    fn file(id: u32, mod_id: u32, display_name: &str) -> Value {
+        json!({
+            "id": id,
+            "modId": mod_id,
+            "displayName": display_name,
+            "fileName": format!("{id}.jar"),
+            "releaseType": 1,
+            "hashes": [],
+            "fileDate": "2025-01-01T00:00:00Z",
+            "fileLength": 1,
+            "downloadUrl": null,
+            "gameVersions": ["1.21.6", "Fabric"],
+            "dependencies": [],
+            "fileFingerprint": id
+        })
+    }
+
+    fn data(data: Value) -> MockResponse {
+        MockResponse::json(json!({ "data": data }).to_string())
+    }
+
+    /// A new temp dir with `mods` as its mods.toml, and the loaded config
+    fn temp_config(name: &str, mods: &str) -> (PathBuf, LocalModsConfig) {
+        let dir = temp_dir(&format!("curseforge-{name}"));
+        let config = format!("version = \"1.21.6\"\nloader = \"fabric\"\n\n[mods]\n{mods}");
+        fs::write(dir.join("mods.toml"), config).unwrap();
+        let config = LocalModsConfig::load(dir.join("mods.toml")).unwrap();
+        (dir, config)
+    }
+
+    /// Add the project 1, alpha, which requires beta (2), which requires
+    /// gamma (3), and return the requests sent to the api
+    fn run_add(dir: &Path, config: &mut LocalModsConfig) -> Vec<String> {
+        let files = MockServer::route(vec![
+            ("GET /files/11.jar", MockResponse::new(200, "x")),
+            ("GET /files/21.jar", MockResponse::new(200, "x")),
+            ("GET /files/31.jar", MockResponse::new(200, "x")),
+        ]);
+        let with_download = |mut file: Value, dependency: Option<u32>| {
+            file["downloadUrl"] =
+                format!("{}/files/{}", files.url, file["fileName"].as_str().unwrap()).into();
+            if let Some(mod_id) = dependency {
+                file["dependencies"] = json!([{ "modId": mod_id, "relationType": 3 }]);
+            }
+            file
+        };
+        // get_projects sends the same request for every project, so the
+        // responses are served in the order `add` asks for them
+        let api = MockServer::start(vec![
+            data(project(1, "alpha")),
+            data(json!([with_download(file(11, 1, "alpha 1.0"), Some(2))])),
+            data(json!([project(2, "beta")])),
+            data(json!([with_download(file(21, 2, "beta 1.0"), Some(3))])),
+            data(json!([project(3, "gamma")])),
+            data(json!([with_download(file(31, 3, "gamma 1.0"), None)])),
+        ]);
+        let client = CurseForgeClient::new()
+            .with_base_url(&api.url)
+            .with_api_key("key");
+        smol::block_on(Compat::new(add(
+            &client,
+            vec!["1".to_string()],
+            None,
+            dir,
+            None,
+            config,
+        )));
+        api.requests()
+            .into_iter()
+            .map(|r| format!("{} {}", r.method, r.path))
+            .collect()
+    }
+
+    #[test]
+    fn test_add_dependencies() {
+        let (dir, mut config) = temp_config("add", "");
+        run_add(&dir, &mut config);
+        // The dependencies of dependencies are added too
+        assert_eq!(
+            fs::read_to_string(dir.join("mods.toml")).unwrap(),
+            r#"version = "1.21.6"
+loader = "fabric"
+
+[mods]
+alpha = { curseforge = 1, file-id = 11 }
+beta = { curseforge = 2, file-id = 21, dependency = true }
+gamma = { curseforge = 3, file-id = 31, dependency = true }
+"#
+        );
+        for id in [11, 21, 31] {
+            assert!(dir.join(format!("{id}.jar")).exists());
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_add_already_exist() {
+        let (dir, mut config) =
+            temp_config("already-exist", "beta = { curseforge = 2, file-id = 20 }\n");
+        let requests = run_add(&dir, &mut config);
+        // beta is kept as it is, so gamma is never looked up
+        assert_eq!(
+            fs::read_to_string(dir.join("mods.toml")).unwrap(),
+            r#"version = "1.21.6"
+loader = "fabric"
+
+[mods]
+beta = { curseforge = 2, file-id = 20 }
+alpha = { curseforge = 1, file-id = 11 }
+"#
+        );
+        assert!(!dir.join("21.jar").exists());
+        assert!(!requests.iter().any(|r| r.starts_with("GET /mods/2/files")));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_entry_updates() {
+        let (dir, config) = temp_config(
+            "update",
+            "delta = { curseforge = 10, file-id = 100, version = \"^1.0\" }\n\
+             epsilon = { curseforge = 20, file-id = 200, channel = \"release\" }\n\
+             zeta = { curseforge = 20, file-id = 200, channel = \"beta\" }\n",
+        );
+        let _ = fs::remove_dir_all(&dir);
+        let dated = |mut file: Value, date: &str| {
+            file["fileDate"] = date.into();
+            file
+        };
+        let mut beta = dated(file(201, 20, "2.0.0-beta"), "2025-02-01T00:00:00Z");
+        beta["releaseType"] = 2.into();
+        let api = MockServer::route(vec![
+            (
+                "GET /mods/10/files",
+                data(json!([
+                    file(100, 10, "1.0.0"),
+                    dated(file(101, 10, "1.1.0"), "2025-02-01T00:00:00Z"),
+                    dated(file(102, 10, "2.0.0"), "2025-03-01T00:00:00Z"),
+                ])),
+            ),
+            (
+                "GET /mods/20/files",
+                data(json!([file(200, 20, "1.0.0"), beta])),
+            ),
+        ]);
+        let client = CurseForgeClient::new()
+            .with_base_url(&api.url)
+            .with_api_key("key");
+        let mut updates = smol::block_on(Compat::new(entry_updates(
+            &client,
+            ContentKind::Mod,
+            None,
+            &config,
+        )));
+        updates.sort_by(|a, b| a.slug.cmp(&b.slug));
+
+        // delta is held back from 2.0.0 by `^1.0`, and the beta file is left
+        // out of the release channel of epsilon while zeta takes it
+        let update = |slug: &str, project_id, from: &str, file_id, to: &str| PlannedUpdate {
+            kind: ContentKind::Mod,
+            slug: slug.to_string(),
+            from: from.to_string(),
+            to: to.to_string(),
+            entry: Mod::CurseForge(CurseForgeMod {
+                slug: slug.to_string(),
+                project_id,
+                file_id,
+            }),
+        };
+        assert_eq!(
+            updates,
+            [
+                update("delta", 10, "1.0.0", 101, "1.1.0"),
+                update("zeta", 20, "1.0.0", 201, "2.0.0-beta"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_is_file_on_side() {
+        let file = |game_versions: &[&str]| {
+            let mut file = file(1, 1, "a");
+            file["gameVersions"] = json!(game_versions);
+            serde_json::from_value::<File>(file).unwrap()
+        };
+        let client_only = file(&["1.21.6", "Fabric", "Client"]);
+        assert!(is_file_on_side(&client_only, Side::Client));
+        assert!(!is_file_on_side(&client_only, Side::Server));
+        let both = file(&["1.21.6", "Fabric", "Client", "Server"]);
+        assert!(is_file_on_side(&both, Side::Server));
+        // Files listing no environment run everywhere
+        assert!(is_file_on_side(&file(&["1.21.6", "Fabric"]), Side::Server));
+    }
+}
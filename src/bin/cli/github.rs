@@ -0,0 +1,240 @@
+use anyhow::Context;
+use color_print::cformat;
+use futures::{StreamExt, TryStreamExt, stream};
+use ice::{
+    config::{LocalModsConfig, constraint::VersionConstraint},
+    core::{ContentKind, GitHubMod, Mod, Side},
+};
+use ice_api_tool::{
+    cache::HttpCache,
+    github::{GitHubClient, types::Release},
+};
+use tracing::info;
+
+use super::{
+    modrinth::{keep_entry_on_side, report_held_back},
+    plan::PlannedUpdate,
+    url::RemoteFile,
+};
+
+/// Build the [`GitHubClient`] used by the `mod` commands
+///
+/// The token is taken from `GITHUB_TOKEN`, and the api base url can be
+/// overridden with `ICE_GITHUB_API`
+pub fn github_client() -> GitHubClient {
+    let client = GitHubClient::new();
+    let client = match HttpCache::global() {
+        Some(cache) => client.with_cache(cache.clone()),
+        None => client,
+    };
+    match std::env::var("ICE_GITHUB_API") {
+        Ok(base_url) => client.with_base_url(base_url),
+        Err(_) => client,
+    }
+}
+
+/// The GitHub entries in the table of `kind`
+pub(crate) fn entries(kind: ContentKind, config: &LocalModsConfig) -> Vec<GitHubMod> {
+    config
+        .get_entries(kind)
+        .into_iter()
+        .filter_map(|m| match m {
+            Mod::GitHub(m) => Some(m),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The asset of `release` the entry `m` installs
+fn asset_file(m: &GitHubMod, release: &Release) -> Result<RemoteFile, anyhow::Error> {
+    let asset = release.find_asset(m.asset_pattern()).with_context(|| {
+        format!(
+            "no asset of {} {} matches {}",
+            m.repo,
+            release.tag_name,
+            m.asset_pattern()
+        )
+    })?;
+    Ok(RemoteFile {
+        slug: m.slug.clone(),
+        url: asset.browser_download_url.clone(),
+        filename: asset.name.clone(),
+        size: Some(asset.size),
+        hash: None,
+    })
+}
+
+// MARK: Sync
+
+/// Resolve the assets of the GitHub entries of `kind` from their tags
+pub(crate) async fn resolve_files(
+    client: &GitHubClient,
+    kind: ContentKind,
+    config: &LocalModsConfig,
+) -> Result<Vec<RemoteFile>, anyhow::Error> {
+    stream::iter(entries(kind, config))
+        .map(|m| async move {
+            let release = client
+                .get_release_by_tag(&m.repo, &m.tag)
+                .await
+                .with_context(|| format!("failed to get release {} of {}", m.tag, m.repo))?;
+            asset_file(&m, &release)
+        })
+        .buffer_unordered(5)
+        .try_collect()
+        .await
+}
+
+// MARK: Update
+
+/// The GitHub entries of `kind` to move to the latest releases
+///
+/// Only the entries installed on `side` are updated, if it is given
+pub(crate) async fn entry_updates(
+    client: &GitHubClient,
+    kind: ContentKind,
+    side: Option<Side>,
+    config: &LocalModsConfig,
+) -> Vec<PlannedUpdate> {
+    let github_mods = entries(kind, config)
+        .into_iter()
+        .filter(|m| keep_entry_on_side(kind, &m.slug, side, config))
+        .collect::<Vec<_>>();
+
+    let results = stream::iter(github_mods)
+        .map(|m| {
+            let constraint = config.entry_constraint(kind, &m.slug);
+            async move {
+                let res = async {
+                    let (release, held_back) =
+                        constrained_release(client, &m, &constraint, &config.version).await?;
+                    // The asset is resolved now, so a release without one fails here
+                    asset_file(&m, &release)?;
+                    Ok::<_, anyhow::Error>((release.tag_name, held_back))
+                }
+                .await;
+                (m, res, constraint)
+            }
+        })
+        .buffer_unordered(5)
+        .collect::<Vec<_>>()
+        .await;
+
+    let mut updates = vec![];
+    for (m, res, constraint) in results {
+        let (tag, held_back) = match res {
+            Ok(res) => res,
+            Err(err) => {
+                info!(
+                    "{}",
+                    cformat!(
+                        "<r>Failed</> update {kind} {} ({})",
+                        m.slug,
+                        err.root_cause()
+                    )
+                );
+                continue;
+            }
+        };
+        if let Some(latest) = held_back {
+            report_held_back(&m.slug, &latest, &constraint);
+        }
+        if tag == m.tag {
+            info!(
+                "{}",
+                cformat!("<y>Unchanged</> {} = {} (GitHub)", m.slug, m.tag)
+            );
+            continue;
+        }
+        updates.push(PlannedUpdate {
+            kind,
+            slug: m.slug.clone(),
+            from: m.tag.clone(),
+            to: tag.clone(),
+            entry: Mod::GitHub(GitHubMod { tag, ..m }),
+        });
+    }
+    updates
+}
+
+/// The newest release of the entry `m` allowed by `constraint`, and the tag
+/// of the latest release if it is held back
+///
+/// The entry stays on its tag if no release is allowed.
+async fn constrained_release(
+    client: &GitHubClient,
+    m: &GitHubMod,
+    constraint: &VersionConstraint,
+    game_version: &str,
+) -> Result<(Release, Option<String>), anyhow::Error> {
+    if *constraint == VersionConstraint::Any {
+        let latest = client
+            .get_latest_release(&m.repo)
+            .await
+            .context("failed to get the latest release")?;
+        return Ok((latest, None));
+    }
+    let releases = client
+        .get_releases(&m.repo)
+        .await
+        .context("failed to get the releases")?;
+    let latest = releases.first().context("no release")?;
+    let release = match releases
+        .iter()
+        .find(|r| constraint.allows(&r.tag_name, &r.tag_name, game_version))
+    {
+        Some(release) => release.clone(),
+        None => client
+            .get_release_by_tag(&m.repo, &m.tag)
+            .await
+            .context("failed to get the current release")?,
+    };
+    let held_back = (release.tag_name != latest.tag_name).then(|| latest.tag_name.clone());
+    Ok((release, held_back))
+}
+
+// MARK: Check
+
+/// Report whether the GitHub entries are on the latest releases
+pub(crate) async fn check(client: &GitHubClient, config: &LocalModsConfig) {
+    let github_mods = config
+        .kinds()
+        .into_iter()
+        .flat_map(|kind| entries(kind, config))
+        .collect::<Vec<_>>();
+
+    let mut stream = stream::iter(github_mods)
+        .map(|m| async move {
+            client
+                .get_latest_release(&m.repo)
+                .await
+                .with_context(|| format!("check {}", m.slug))
+                .map(|release| (m, release))
+        })
+        .buffer_unordered(5);
+
+    while let Some(res) = stream.next().await {
+        match res {
+            Ok((m, release)) if release.tag_name == m.tag => {
+                info!(
+                    "{}",
+                    cformat!("<g>Latest</> {} = {} (GitHub)", m.slug, m.tag)
+                );
+            }
+            Ok((m, release)) => {
+                info!(
+                    "{}",
+                    cformat!(
+                        "<y>Outdated</> {} = {} -> {} (GitHub)",
+                        m.slug,
+                        m.tag,
+                        release.tag_name
+                    )
+                );
+            }
+            Err(err) => {
+                info!("{}", cformat!("<r>Failed</> {:?}", err));
+            }
+        }
+    }
+}
@@ -0,0 +1,291 @@
+use std::{collections::HashSet, path::Path};
+
+use color_print::cformat;
+use ice::{
+    config::{LocalModsConfig, lock::ModsLock},
+    core::{ContentKind, Mod, ModSide},
+};
+use ice_api_tool::{curseforge::CurseForgeClient, github::GitHubClient, modrinth::ModrinthClient};
+use tracing::info;
+
+use super::{
+    local, lock,
+    modrinth::{get_files, init_logger},
+};
+
+/// Whether the file of an entry is there
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Installed,
+    /// Renamed to `.disabled`, or not installed without a profile
+    Disabled,
+    Missing,
+    /// A file no entry installs
+    Extra,
+}
+
+impl State {
+    fn cell(&self, width: usize) -> String {
+        match self {
+            Self::Installed => cformat!("<g>{:<width$}</>", "installed"),
+            Self::Disabled => cformat!("<dim>{:<width$}</>", "disabled"),
+            Self::Missing => cformat!("<r>{:<width$}</>", "missing"),
+            Self::Extra => cformat!("<y>{:<width$}</>", "extra"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Row {
+    slug: String,
+    version: String,
+    state: State,
+    filename: String,
+    /// `None` for an extra file
+    side: Option<ModSide>,
+}
+
+/// The `list` command
+///
+/// The files are resolved like for mods.lock, which is used as is if it is
+/// up to date.
+pub async fn list(
+    client: &ModrinthClient,
+    curseforge: &CurseForgeClient,
+    github: &GitHubClient,
+    current_dir: impl AsRef<Path>,
+    config: &LocalModsConfig,
+) {
+    init_logger();
+
+    let lock = match lock::current(client, curseforge, github, config).await {
+        Ok(lock) => lock,
+        Err(err) => {
+            info!("{}", cformat!("<r>Failed</> {:?}", err));
+            return;
+        }
+    };
+
+    let current_dir = current_dir.as_ref();
+    let kinds = config.kinds();
+    for kind in &kinds {
+        let kind = *kind;
+        let rows = rows(kind, &kind.dir(current_dir), &lock, config);
+        if kinds.len() > 1 {
+            println!("{}", cformat!("<bold>[{}]</>", kind.table()));
+        }
+        print_rows(&rows);
+        if kinds.len() > 1 {
+            println!();
+        }
+    }
+}
+
+/// The rows of the entries of `kind` and of the extra files in `dir`, by
+/// whether their files are there
+fn rows(kind: ContentKind, dir: &Path, lock: &ModsLock, config: &LocalModsConfig) -> Vec<Row> {
+    let mut rows = vec![];
+    let mut installed = HashSet::new();
+    for (slug, m) in config
+        .get_entries(kind)
+        .into_iter()
+        .filter_map(|m| Some((m.slug()?.to_string(), m)))
+    {
+        let (version, filename, path, side) = match (&m, lock.get(kind, &slug)) {
+            (Mod::Local(m), _) => (
+                m.path.display().to_string(),
+                m.filename().to_string(),
+                Some(dir.join(m.filename())).filter(|path| path.symlink_metadata().is_ok()),
+                config.entry_side(kind, &slug).unwrap_or(ModSide::Both),
+            ),
+            (_, Some(entry)) => (
+                entry
+                    .version_number
+                    .clone()
+                    .or(entry.version_id.clone())
+                    .unwrap_or("-".to_string()),
+                entry.filename.clone(),
+                lock::installed_path(kind, entry, dir),
+                entry.side,
+            ),
+            (_, None) => ("-".to_string(), "-".to_string(), None, ModSide::Both),
+        };
+        let (state, filename) = match path {
+            // The file found by its hash may have been renamed
+            Some(path) => {
+                let filename = path.file_name().unwrap().to_string_lossy().to_string();
+                installed.insert(path);
+                (State::Installed, filename)
+            }
+            None if dir.join(format!("{filename}.disabled")).exists() => {
+                (State::Disabled, filename)
+            }
+            None if !config.is_entry_enabled(kind, &slug, None) => (State::Disabled, filename),
+            None => (State::Missing, filename),
+        };
+        rows.push(Row {
+            slug,
+            version,
+            state,
+            filename,
+            side: Some(side),
+        });
+    }
+    let local_paths = local::installed_paths(kind, dir, config);
+    for path in get_files(dir, kind.extension())
+        .into_iter()
+        .filter(|entry| !entry.file_name().to_string_lossy().starts_with("_"))
+        .map(|entry| entry.path())
+        .filter(|path| !installed.contains(path) && !local_paths.contains(path))
+    {
+        rows.push(Row {
+            slug: "-".to_string(),
+            version: "-".to_string(),
+            state: State::Extra,
+            filename: path.file_name().unwrap().to_string_lossy().to_string(),
+            side: None,
+        });
+    }
+
+    rows
+}
+
+fn print_rows(rows: &[Row]) {
+    let width = |header: &str, cell: &dyn Fn(&Row) -> usize| {
+        rows.iter().map(cell).chain([header.len()]).max().unwrap()
+    };
+    let slug_width = width("slug", &|row| row.slug.len());
+    let version_width = width("version", &|row| row.version.len());
+    let state_width = width("state", &|_| "installed".len());
+    let file_width = width("file", &|row| row.filename.len());
+
+    println!(
+        "{}",
+        cformat!(
+            "<bold>{:<slug_width$}  {:<version_width$}  {:<state_width$}  {:<file_width$}  side</>",
+            "slug",
+            "version",
+            "state",
+            "file"
+        )
+    );
+    for row in rows {
+        let side = row.side.map(|side| side.to_string());
+        println!(
+            "{:<slug_width$}  {:<version_width$}  {}  {:<file_width$}  {}",
+            row.slug,
+            row.version,
+            row.state.cell(state_width),
+            row.filename,
+            side.as_deref().unwrap_or("-")
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use ice::config::lock::LockedEntry;
+    use ice_util::fs::get_sha1_hash;
+
+    use super::*;
+    use crate::cli::temp_dir;
+
+    #[test]
+    fn test_rows() {
+        let dir = temp_dir("list");
+        fs::write(
+            dir.join("mods.toml"),
+            r#"version = "1.21.6"
+loader = "fabric"
+
+[mods]
+a = "a1#1.0"
+b = "b1#1.0"
+c = "c1#1.0"
+d = "d1#1.0"
+e = { version-id-number = "e1#1.0", enabled = false }
+"#,
+        )
+        .unwrap();
+        let config = LocalModsConfig::load(dir.join("mods.toml")).unwrap();
+        fs::write(dir.join("a-1.0.jar"), "a").unwrap();
+        fs::write(dir.join("b-renamed.jar"), "b").unwrap();
+        fs::write(dir.join("d-1.0.jar.disabled"), "d").unwrap();
+        fs::write(dir.join("stray.jar"), "stray").unwrap();
+        fs::write(dir.join("_by-hand.jar"), "by hand").unwrap();
+        let hash = |name: &str| Some(get_sha1_hash(dir.join(name)).unwrap());
+        let lock = ModsLock::new(vec![
+            LockedEntry::test_mod("a", "1.0", hash("a-1.0.jar")),
+            LockedEntry::test_mod("b", "1.0", hash("b-renamed.jar")),
+            LockedEntry::test_mod("c", "1.0", Some("0000".to_string())),
+            LockedEntry::test_mod("d", "1.0", hash("d-1.0.jar.disabled")),
+            LockedEntry::test_mod("e", "1.0", Some("0000".to_string())),
+        ]);
+
+        let row = |slug: &str, state, filename: &str| Row {
+            slug: slug.to_string(),
+            version: "1.0".to_string(),
+            state,
+            filename: filename.to_string(),
+            side: Some(ModSide::Both),
+        };
+        assert_eq!(
+            rows(ContentKind::Mod, &dir, &lock, &config),
+            [
+                row("a", State::Installed, "a-1.0.jar"),
+                // Found by its hash
+                row("b", State::Installed, "b-renamed.jar"),
+                row("c", State::Missing, "c-1.0.jar"),
+                row("d", State::Disabled, "d-1.0.jar"),
+                row("e", State::Disabled, "e-1.0.jar"),
+                Row {
+                    slug: "-".to_string(),
+                    version: "-".to_string(),
+                    state: State::Extra,
+                    filename: "stray.jar".to_string(),
+                    side: None,
+                },
+            ]
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_rows_path_entries() {
+        let dir = temp_dir("list-local");
+        fs::create_dir_all(dir.join("vendor")).unwrap();
+        fs::write(dir.join("vendor/copy.jar"), "copy").unwrap();
+        fs::write(dir.join("vendor/link.jar"), "link").unwrap();
+        fs::write(
+            dir.join("mods.toml"),
+            r#"version = "1.21.6"
+loader = "fabric"
+
+[mods]
+copy = { path = "vendor/copy.jar" }
+link = { path = "vendor/link.jar", symlink = true }
+"#,
+        )
+        .unwrap();
+        let config = LocalModsConfig::load(dir.join("mods.toml")).unwrap();
+        for m in local::entries(ContentKind::Mod, &config) {
+            local::install(&m, &dir.join(&m.path), &dir.join(m.filename())).unwrap();
+        }
+
+        // The installed files belong to their entries, none of them is extra
+        let row = |slug: &str| Row {
+            slug: slug.to_string(),
+            version: format!("vendor/{slug}.jar"),
+            state: State::Installed,
+            filename: format!("{slug}.jar"),
+            side: Some(ModSide::Both),
+        };
+        assert_eq!(
+            rows(ContentKind::Mod, &dir, &ModsLock::new(vec![]), &config),
+            [row("copy"), row("link")]
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
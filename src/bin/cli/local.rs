@@ -0,0 +1,190 @@
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+#[cfg(unix)]
+use std::os::unix::fs::symlink;
+#[cfg(windows)]
+use std::os::windows::fs::symlink_file as symlink;
+
+use anyhow::{Context, bail};
+use ice::{
+    config::LocalModsConfig,
+    core::{ContentKind, LocalMod, Mod},
+};
+use ice_util::fs::get_sha1_hash;
+
+/// The path entries in the table of `kind`
+pub(crate) fn entries(kind: ContentKind, config: &LocalModsConfig) -> Vec<LocalMod> {
+    config
+        .get_entries(kind)
+        .into_iter()
+        .filter_map(|m| match m {
+            Mod::Local(m) => Some(m),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The paths the path entries of `kind` are installed as under `dir`
+pub(crate) fn installed_paths(
+    kind: ContentKind,
+    dir: &Path,
+    config: &LocalModsConfig,
+) -> HashSet<PathBuf> {
+    entries(kind, config)
+        .iter()
+        .map(|m| dir.join(m.filename()))
+        .collect()
+}
+
+/// Whether the file at `path` is installed from `source` for `m`
+///
+/// A copy is installed once its sha1 is the one of the source, a symlink once
+/// it points to the source.
+pub(crate) fn is_installed(m: &LocalMod, source: &Path, path: &Path) -> bool {
+    if m.symlink {
+        return fs::canonicalize(source)
+            .is_ok_and(|source| fs::read_link(path).is_ok_and(|target| target == source));
+    }
+    path.symlink_metadata()
+        .is_ok_and(|metadata| metadata.is_file())
+        && matches!(
+            (get_sha1_hash(path), get_sha1_hash(source)),
+            (Ok(a), Ok(b)) if a == b
+        )
+}
+
+/// Install the file at `source` as `path`, checking it against the pin of `m`
+///
+/// A copy is written next to `path` and moved there, so an existing symlink
+/// is replaced rather than written through.
+pub(crate) fn install(m: &LocalMod, source: &Path, path: &Path) -> Result<(), anyhow::Error> {
+    if !source.is_file() {
+        bail!("{} is not a file", source.display());
+    }
+    if let Some(hash) = &m.hash {
+        let actual = hash
+            .of_file(source)
+            .with_context(|| format!("failed to hash {}", source.display()))?;
+        if !hash.same_as(&actual) {
+            bail!("hash mismatch, expected {}, got {}", hash, actual);
+        }
+    }
+
+    if is_installed(m, source, path) {
+        return Ok(());
+    }
+    let metadata = path.symlink_metadata().ok();
+    if m.symlink {
+        let source = fs::canonicalize(source)
+            .with_context(|| format!("failed to resolve {}", source.display()))?;
+        if metadata.is_some() {
+            fs::remove_file(path).context("failed to remove the old file")?;
+        }
+        symlink(&source, path).context("failed to create the symlink")?;
+        return Ok(());
+    }
+
+    let part = path.with_file_name(format!(".{}.part", m.filename()));
+    fs::copy(source, &part).context("failed to copy the file")?;
+    fs::rename(&part, path).context("failed to move the file in place")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use async_compat::Compat;
+    use ice_api_tool::{
+        curseforge::CurseForgeClient,
+        github::GitHubClient,
+        mock::{MockResponse, MockServer},
+        modrinth::ModrinthClient,
+    };
+    use ice_util::fs::get_sha512_hash;
+
+    use super::*;
+    use crate::cli::{
+        modrinth::{SyncOptions, sync},
+        plan::PlanOptions,
+        temp_dir,
+    };
+
+    /// Sync the mods.toml in `dir` without asking, Modrinth knowing none of
+    /// the files
+    fn run_sync(dir: &Path, prune: bool) {
+        let api = MockServer::route(vec![("POST /v2/version_files", MockResponse::json("{}"))]);
+        let client = ModrinthClient::new().with_base_url(format!("{}/v2", api.url));
+        let curseforge = CurseForgeClient::new().with_base_url(&api.url);
+        let github = GitHubClient::new().with_base_url(&api.url);
+        let config = LocalModsConfig::load(dir.join("mods.toml")).unwrap();
+        let options = SyncOptions {
+            prune,
+            plan: PlanOptions {
+                yes: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        smol::block_on(Compat::new(sync(
+            &client,
+            &curseforge,
+            &github,
+            dir,
+            options,
+            &config,
+        )));
+    }
+
+    #[test]
+    fn test_install_path_entries() {
+        let dir = temp_dir("local");
+        fs::create_dir_all(dir.join("vendor")).unwrap();
+        let names = ["copy", "copy-hashed", "link", "link-hashed"];
+        for name in names {
+            fs::write(dir.join(format!("vendor/{name}.jar")), name).unwrap();
+        }
+        let sha1 = get_sha1_hash(dir.join("vendor/copy-hashed.jar")).unwrap();
+        let sha512 = get_sha512_hash(dir.join("vendor/link-hashed.jar")).unwrap();
+        fs::write(
+            dir.join("mods.toml"),
+            format!(
+                r#"version = "1.21.6"
+loader = "fabric"
+
+[mods]
+copy = {{ path = "vendor/copy.jar" }}
+copy-hashed = {{ path = "vendor/copy-hashed.jar", sha1 = "{sha1}" }}
+link = {{ path = "vendor/link.jar", symlink = true }}
+link-hashed = {{ path = "vendor/link-hashed.jar", symlink = true, sha512 = "{sha512}" }}
+"#
+            ),
+        )
+        .unwrap();
+        let check = || {
+            for name in ["copy", "copy-hashed"] {
+                let path = dir.join(format!("{name}.jar"));
+                assert!(path.symlink_metadata().unwrap().is_file());
+                assert_eq!(fs::read_to_string(path).unwrap(), name);
+            }
+            for name in ["link", "link-hashed"] {
+                let target = fs::read_link(dir.join(format!("{name}.jar"))).unwrap();
+                let source = fs::canonicalize(dir.join(format!("vendor/{name}.jar"))).unwrap();
+                assert_eq!(target, source);
+            }
+        };
+
+        run_sync(&dir, false);
+        check();
+
+        // The installed files are not taken for files of no entry, which
+        // pruning removes
+        fs::write(dir.join("stray.jar"), "stray").unwrap();
+        run_sync(&dir, true);
+        check();
+        assert!(!dir.join("stray.jar").exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,390 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use color_print::cformat;
+use ice::{
+    config::{
+        LocalModsConfig,
+        lock::{LockSource, LockedEntry, ModsLock},
+    },
+    core::{ContentKind, FileHash, Mod, ModSide, Side},
+};
+use ice_api_tool::{
+    curseforge::{CurseForgeClient, types::RelationType},
+    github::GitHubClient,
+    modrinth::{ModrinthClient, types::DependencyType},
+};
+use tracing::info;
+
+use super::{
+    curseforge,
+    modrinth::{SyncOptions, get_files, init_logger},
+    plan::{self, SyncPlan},
+    profile, url,
+};
+
+// MARK: Resolve
+
+/// Resolve the entries of mods.toml to the files they install
+///
+/// The path entries are not locked, their files are local anyway.
+pub(crate) async fn resolve(
+    client: &ModrinthClient,
+    curseforge: &CurseForgeClient,
+    github: &GitHubClient,
+    config: &LocalModsConfig,
+) -> Result<ModsLock, anyhow::Error> {
+    let mut entries = vec![];
+    for kind in config.kinds() {
+        entries.extend(resolve_modrinth(client, kind, config).await?);
+        entries.extend(resolve_curseforge(curseforge, kind, config).await?);
+        entries.extend(resolve_remote(github, kind, config).await?);
+    }
+    Ok(ModsLock::new(entries))
+}
+
+/// Resolve mods.toml and write `mods.lock` next to it if anything changed
+pub(crate) async fn refresh(
+    client: &ModrinthClient,
+    curseforge: &CurseForgeClient,
+    github: &GitHubClient,
+    config: &LocalModsConfig,
+) {
+    let res = async {
+        let lock = resolve(client, curseforge, github, config).await?;
+        lock.save(config.lock_path())
+            .context("failed to write mods.lock")
+    }
+    .await;
+    match res {
+        Ok(true) => info!("{}", cformat!("<g>Updated</> mods.lock")),
+        Ok(false) => (),
+        Err(err) => info!("{}", cformat!("<r>Failed</> update mods.lock ({:?})", err)),
+    }
+}
+
+/// The resolution of mods.toml, `mods.lock` if it is up to date with it
+pub(crate) async fn current(
+    client: &ModrinthClient,
+    curseforge: &CurseForgeClient,
+    github: &GitHubClient,
+    config: &LocalModsConfig,
+) -> Result<ModsLock, anyhow::Error> {
+    match ModsLock::load(config.lock_path()) {
+        Ok(lock) if lock.outdated_entries(config).is_empty() => Ok(lock),
+        _ => resolve(client, curseforge, github, config).await,
+    }
+}
+
+/// The file of the locked `entry` under `dir`, found by its hash if it was
+/// renamed
+///
+/// An entry without a hash is only found by its file name.
+pub(crate) fn installed_path(
+    kind: ContentKind,
+    entry: &LockedEntry,
+    dir: &Path,
+) -> Option<PathBuf> {
+    let checksum = entry.checksum();
+    let hashed = checksum.sha1.is_some() || checksum.sha512.is_some();
+    let matches = |path: &Path| !hashed || matches!(checksum.mismatch(path), Ok(None));
+    let path = dir.join(&entry.filename);
+    if path.is_file() && matches(&path) {
+        return Some(path);
+    }
+    if !hashed {
+        return None;
+    }
+    get_files(dir, kind.extension())
+        .into_iter()
+        .map(|entry| entry.path())
+        .find(|path| matches(path))
+}
+
+/// The sides a project is installed on, by whether it is on each side
+fn mod_side(on_side: impl Fn(Side) -> bool) -> ModSide {
+    match (on_side(Side::Client), on_side(Side::Server)) {
+        (true, false) => ModSide::Client,
+        (false, true) => ModSide::Server,
+        _ => ModSide::Both,
+    }
+}
+
+async fn resolve_modrinth(
+    client: &ModrinthClient,
+    kind: ContentKind,
+    config: &LocalModsConfig,
+) -> Result<Vec<LockedEntry>, anyhow::Error> {
+    let modrinth_mods = config
+        .get_entries(kind)
+        .into_iter()
+        .filter_map(|m| match m {
+            Mod::Modrinth(m) => Some(m),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    if modrinth_mods.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let versions = client
+        .get_versions(modrinth_mods.iter().map(|m| m.version_id()))
+        .await
+        .context("failed to get versions")?
+        .into_iter()
+        .map(|v| (v.id.clone(), v))
+        .collect::<HashMap<_, _>>();
+    let projects = client
+        .get_projects(versions.values().map(|v| v.project_id.clone()))
+        .await
+        .context("failed to get projects")?
+        .into_iter()
+        .map(|p| (p.id.clone(), p))
+        .collect::<HashMap<_, _>>();
+    // The entries of the projects, to name the dependencies
+    let slugs = modrinth_mods
+        .iter()
+        .filter_map(|m| {
+            let version = versions.get(m.version_id())?;
+            Some((version.project_id.clone(), m.slug.clone()))
+        })
+        .collect::<HashMap<_, _>>();
+
+    modrinth_mods
+        .iter()
+        .map(|m| {
+            let version = versions
+                .get(m.version_id())
+                .with_context(|| format!("version {} of {} not found", m.version_id(), m.slug))?;
+            let project = projects
+                .get(&version.project_id)
+                .with_context(|| format!("project of {} not found", m.slug))?;
+            let file = version.get_primary_file();
+            let mut dependencies = version
+                .dependencies
+                .iter()
+                .filter(|dep| dep.dependency_type == DependencyType::Required)
+                .filter_map(|dep| {
+                    let project_id = dep.project_id.as_ref().or(dep
+                        .version_id
+                        .as_ref()
+                        .and_then(|id| versions.get(id))
+                        .map(|v| &v.project_id))?;
+                    slugs.get(project_id).cloned()
+                })
+                .collect::<Vec<_>>();
+            dependencies.sort();
+            dependencies.dedup();
+            Ok(LockedEntry {
+                kind,
+                slug: m.slug.clone(),
+                source: LockSource::Modrinth,
+                version_id: Some(version.id.clone()),
+                version_number: Some(version.version_number.clone()),
+                filename: file.filename.clone(),
+                url: Some(file.url.clone()),
+                size: Some(file.size as u64),
+                sha1: Some(file.hashes.sha1.clone()),
+                sha512: Some(file.hashes.sha512.clone()),
+                side: mod_side(|side| config.is_on_side(kind, &m.slug, project, side)),
+                dependencies,
+            })
+        })
+        .collect()
+}
+
+async fn resolve_curseforge(
+    client: &CurseForgeClient,
+    kind: ContentKind,
+    config: &LocalModsConfig,
+) -> Result<Vec<LockedEntry>, anyhow::Error> {
+    let curseforge_mods = curseforge::entries(kind, config);
+    if curseforge_mods.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let files = client
+        .get_files(curseforge_mods.iter().map(|m| m.file_id))
+        .await
+        .context("failed to get CurseForge files")?
+        .into_iter()
+        .map(|f| (f.id, f))
+        .collect::<HashMap<_, _>>();
+    let slugs = curseforge_mods
+        .iter()
+        .map(|m| (m.project_id, m.slug.clone()))
+        .collect::<HashMap<_, _>>();
+
+    curseforge_mods
+        .iter()
+        .map(|m| {
+            let file = files
+                .get(&m.file_id)
+                .with_context(|| format!("file {} of {} not found", m.file_id, m.slug))?;
+            let mut dependencies = file
+                .dependencies
+                .iter()
+                .filter(|dep| dep.relation_type == RelationType::RequiredDependency)
+                .filter_map(|dep| slugs.get(&dep.mod_id).cloned())
+                .collect::<Vec<_>>();
+            dependencies.sort();
+            dependencies.dedup();
+            Ok(LockedEntry {
+                kind,
+                slug: m.slug.clone(),
+                source: LockSource::CurseForge,
+                version_id: Some(file.id.to_string()),
+                version_number: Some(file.display_name.clone()),
+                filename: file.file_name.clone(),
+                url: file.download_url.clone(),
+                size: Some(file.file_length),
+                sha1: file.sha1().map(|s| s.to_string()),
+                sha512: None,
+                side: config.entry_side(kind, &m.slug).unwrap_or(ModSide::Both),
+                dependencies,
+            })
+        })
+        .collect()
+}
+
+/// Resolve the url, GitHub and Maven entries
+async fn resolve_remote(
+    github: &GitHubClient,
+    kind: ContentKind,
+    config: &LocalModsConfig,
+) -> Result<Vec<LockedEntry>, anyhow::Error> {
+    let files = url::remote_files(github, kind, config).await?;
+    Ok(files
+        .into_iter()
+        .map(|file| {
+            let (source, version_id, version_number) = match config.get_entry(kind, &file.slug) {
+                Some(Mod::GitHub(m)) => (LockSource::GitHub, Some(m.tag), None),
+                Some(Mod::Maven(m)) => (
+                    LockSource::Maven,
+                    Some(m.coordinates.to_string()),
+                    Some(m.coordinates.version),
+                ),
+                _ => (LockSource::Url, None, None),
+            };
+            let (sha1, sha512) = match file.hash {
+                Some(FileHash::Sha1(hash)) => (Some(hash), None),
+                Some(FileHash::Sha512(hash)) => (None, Some(hash)),
+                None => (None, None),
+            };
+            LockedEntry {
+                kind,
+                side: config.entry_side(kind, &file.slug).unwrap_or(ModSide::Both),
+                slug: file.slug,
+                source,
+                version_id,
+                version_number,
+                filename: file.filename,
+                url: Some(file.url),
+                size: file.size,
+                sha1,
+                sha512,
+                dependencies: vec![],
+            }
+        })
+        .collect())
+}
+
+// MARK: Sync
+
+/// The `sync --locked` command
+///
+/// The files are installed from `mods.lock` alone, no api is asked, and the
+/// files of no locked entry are removed. The lock must be up to date with
+/// mods.toml.
+pub async fn sync_locked<P: AsRef<Path>>(
+    current_dir: P,
+    options: SyncOptions<'_>,
+    config: &LocalModsConfig,
+) {
+    init_logger();
+
+    let lock = match ModsLock::load(config.lock_path()).context("failed to load mods.lock") {
+        Ok(lock) => lock,
+        Err(err) => {
+            info!("{}", cformat!("<r>Failed</> {:?}", err));
+            return;
+        }
+    };
+    let outdated = lock.outdated_entries(config);
+    if !outdated.is_empty() {
+        info!(
+            "{}",
+            cformat!(
+                "<r>Failed</> mods.lock is out of date for {}, run `ice mod sync` without --locked to update it",
+                outdated.join(", ")
+            )
+        );
+        return;
+    }
+
+    let current_dir = current_dir.as_ref();
+    let enabled = profile::enabled_entries(config, options.profile);
+    let mut plan = SyncPlan::default();
+    for kind in config.kinds() {
+        let entries = lock.entries(kind).collect::<Vec<_>>();
+        let dir = kind.dir(current_dir);
+        match plan::plan_sync_kind(kind, &dir, &entries, options, &enabled, config, None).await {
+            Ok(actions) => plan.actions.extend(actions),
+            Err(err) => info!("{}", cformat!("<r>Failed</> {:?}", err)),
+        }
+    }
+    if plan::run(&plan, current_dir, options.plan, config)
+        .await
+        .is_some()
+    {
+        info!("done!");
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use ice_util::fs::get_sha1_hash;
+
+    use super::*;
+    use crate::cli::{plan::PlanOptions, temp_dir};
+
+    #[test]
+    fn test_sync_locked_removes_stale_files() {
+        let dir = temp_dir("sync-locked");
+        fs::write(
+            dir.join("mods.toml"),
+            "version = \"1.21.6\"\nloader = \"fabric\"\n\n[mods]\nsodium = \"sodium0.6.1#0.6.1\"\n",
+        )
+        .unwrap();
+        fs::write(dir.join("sodium-0.6.1.jar"), "sodium 0.6.1").unwrap();
+        fs::write(dir.join("sodium-0.6.0.jar"), "sodium 0.6.0").unwrap();
+        fs::write(dir.join("_pinned.jar"), "by hand").unwrap();
+        let lock = ModsLock::new(vec![LockedEntry::test_mod(
+            "sodium",
+            "0.6.1",
+            Some(get_sha1_hash(dir.join("sodium-0.6.1.jar")).unwrap()),
+        )]);
+        let config = LocalModsConfig::load(dir.join("mods.toml")).unwrap();
+        lock.save(config.lock_path()).unwrap();
+
+        let options = SyncOptions {
+            locked: true,
+            plan: PlanOptions {
+                yes: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        smol::block_on(sync_locked(&dir, options, &config));
+
+        assert!(dir.join("sodium-0.6.1.jar").is_file());
+        assert!(!dir.join("sodium-0.6.0.jar").exists());
+        assert!(dir.join("_pinned.jar").is_file());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
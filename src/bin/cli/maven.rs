@@ -0,0 +1,226 @@
+use anyhow::Context;
+use color_print::cformat;
+use futures::{StreamExt, TryStreamExt, stream};
+use ice::{
+    config::{LocalModsConfig, constraint::VersionConstraint},
+    core::{ContentKind, FileHash, MavenMod, Mod, Side},
+};
+use ice_api_tool::maven::{self, Coordinates};
+use tracing::info;
+
+use super::{
+    modrinth::{keep_entry_on_side, report_held_back},
+    plan::PlannedUpdate,
+    url::RemoteFile,
+};
+
+/// The Maven entries in the table of `kind`
+pub(crate) fn entries(kind: ContentKind, config: &LocalModsConfig) -> Vec<MavenMod> {
+    config
+        .get_entries(kind)
+        .into_iter()
+        .filter_map(|m| match m {
+            Mod::Maven(m) => Some(m),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The jar of `coordinates` the entry `m` installs, verified by the sha1
+/// the repository publishes
+///
+/// The jar is only checked by its name if the repository has no sha1 for it.
+async fn artifact_file(
+    m: &MavenMod,
+    coordinates: &Coordinates,
+) -> Result<RemoteFile, anyhow::Error> {
+    let hash = match maven::get_sha1(&m.repository, coordinates).await {
+        Ok(sha1) => Some(FileHash::Sha1(sha1)),
+        Err(err) if err.is_not_found() => None,
+        Err(err) => {
+            return Err(err).with_context(|| format!("failed to get the sha1 of {coordinates}"));
+        }
+    };
+    Ok(RemoteFile {
+        slug: m.slug.clone(),
+        url: maven::artifact_url(&m.repository, coordinates),
+        filename: coordinates.filename(),
+        size: None,
+        hash,
+    })
+}
+
+// MARK: Sync
+
+/// Resolve the jars of the Maven entries of `kind`
+pub(crate) async fn resolve_files(
+    kind: ContentKind,
+    config: &LocalModsConfig,
+) -> Result<Vec<RemoteFile>, anyhow::Error> {
+    stream::iter(entries(kind, config))
+        .map(|m| async move { artifact_file(&m, &m.coordinates).await })
+        .buffer_unordered(5)
+        .try_collect()
+        .await
+}
+
+// MARK: Update
+
+/// The Maven entries of `kind` to move to the latest releases in their
+/// `maven-metadata.xml`
+///
+/// Only the entries installed on `side` are updated, if it is given
+pub(crate) async fn entry_updates(
+    kind: ContentKind,
+    side: Option<Side>,
+    config: &LocalModsConfig,
+) -> Vec<PlannedUpdate> {
+    let maven_mods = entries(kind, config)
+        .into_iter()
+        .filter(|m| keep_entry_on_side(kind, &m.slug, side, config))
+        .collect::<Vec<_>>();
+
+    let results = stream::iter(maven_mods)
+        .map(|m| {
+            let constraint = config.entry_constraint(kind, &m.slug);
+            async move {
+                let res = constrained_coordinates(&m, &constraint, &config.version).await;
+                (m, res, constraint)
+            }
+        })
+        .buffer_unordered(5)
+        .collect::<Vec<_>>()
+        .await;
+
+    let mut updates = vec![];
+    for (m, res, constraint) in results {
+        let (coordinates, held_back) = match res {
+            Ok(res) => res,
+            Err(err) => {
+                info!(
+                    "{}",
+                    cformat!(
+                        "<r>Failed</> update {kind} {} ({})",
+                        m.slug,
+                        err.root_cause()
+                    )
+                );
+                continue;
+            }
+        };
+        if let Some(latest) = held_back {
+            report_held_back(&m.slug, &latest, &constraint);
+        }
+        if coordinates == m.coordinates {
+            info!(
+                "{}",
+                cformat!(
+                    "<y>Unchanged</> {} = {} (Maven)",
+                    m.slug,
+                    m.coordinates.version
+                )
+            );
+            continue;
+        }
+        updates.push(PlannedUpdate {
+            kind,
+            slug: m.slug.clone(),
+            from: m.coordinates.version.clone(),
+            to: coordinates.version.clone(),
+            entry: Mod::Maven(MavenMod { coordinates, ..m }),
+        });
+    }
+    updates
+}
+
+/// The coordinates of the latest release of the artifact of `m`
+async fn latest_coordinates(m: &MavenMod) -> Result<Coordinates, anyhow::Error> {
+    let metadata = maven::get_metadata(&m.repository, &m.coordinates)
+        .await
+        .context("failed to get maven-metadata.xml")?;
+    let version = metadata
+        .release()
+        .context("no release in maven-metadata.xml")?;
+    Ok(m.coordinates.with_version(version))
+}
+
+/// The coordinates of the newest release of the artifact of `m` allowed by
+/// `constraint`, and the latest release if it is held back
+///
+/// Snapshots are never picked, and the entry stays on its version if no
+/// release is allowed.
+async fn constrained_coordinates(
+    m: &MavenMod,
+    constraint: &VersionConstraint,
+    game_version: &str,
+) -> Result<(Coordinates, Option<String>), anyhow::Error> {
+    if *constraint == VersionConstraint::Any {
+        return Ok((latest_coordinates(m).await?, None));
+    }
+    let metadata = maven::get_metadata(&m.repository, &m.coordinates)
+        .await
+        .context("failed to get maven-metadata.xml")?;
+    let latest = metadata
+        .release()
+        .context("no release in maven-metadata.xml")?;
+    let version = metadata
+        .versioning
+        .versions
+        .version
+        .iter()
+        .rev()
+        .filter(|v| !v.ends_with("-SNAPSHOT"))
+        .find(|v| constraint.allows(v, v, game_version))
+        .map(|v| v.as_str())
+        .unwrap_or(&m.coordinates.version);
+    let held_back = (version != latest).then(|| latest.to_string());
+    Ok((m.coordinates.with_version(version), held_back))
+}
+
+// MARK: Check
+
+/// Report whether the Maven entries are on the latest releases
+pub(crate) async fn check(config: &LocalModsConfig) {
+    let maven_mods = config
+        .kinds()
+        .into_iter()
+        .flat_map(|kind| entries(kind, config))
+        .collect::<Vec<_>>();
+
+    let mut stream = stream::iter(maven_mods)
+        .map(|m| async move {
+            let res = latest_coordinates(&m).await;
+            res.with_context(|| format!("check {}", m.slug))
+                .map(|coordinates| (m, coordinates))
+        })
+        .buffer_unordered(5);
+
+    while let Some(res) = stream.next().await {
+        match res {
+            Ok((m, coordinates)) if coordinates == m.coordinates => {
+                info!(
+                    "{}",
+                    cformat!(
+                        "<g>Latest</> {} = {} (Maven)",
+                        m.slug,
+                        m.coordinates.version
+                    )
+                );
+            }
+            Ok((m, coordinates)) => {
+                info!(
+                    "{}",
+                    cformat!(
+                        "<y>Outdated</> {} = {} -> {} (Maven)",
+                        m.slug,
+                        m.coordinates.version,
+                        coordinates.version
+                    )
+                );
+            }
+            Err(err) => {
+                info!("{}", cformat!("<r>Failed</> {:?}", err));
+            }
+        }
+    }
+}
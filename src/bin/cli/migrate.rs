@@ -0,0 +1,543 @@
+use std::{collections::HashMap, path::Path};
+
+use anyhow::{Context, bail};
+use color_print::cformat;
+use futures::{StreamExt, stream};
+use ice::{
+    config::LocalModsConfig,
+    core::{ContentKind, CurseForgeMod, Mod, ModrinthMod},
+};
+use ice_api_tool::{curseforge::CurseForgeClient, github::GitHubClient, modrinth::ModrinthClient};
+use tracing::info;
+
+use super::{
+    curseforge, lock,
+    modrinth::{SyncOptions, init_logger},
+    plan::{self, PlannedUpdate, SyncPlan},
+    profile,
+};
+
+/// An entry of mods.toml with no file for the target game version
+struct Blocker {
+    kind: ContentKind,
+    slug: String,
+    reason: String,
+}
+
+/// The options of the `migrate` command
+pub struct MigrateOptions<'a> {
+    /// The game version to move to
+    pub game_version: String,
+    /// Disable the entries with no file for `game_version` instead of giving up
+    pub allow_missing: bool,
+    pub sync: SyncOptions<'a>,
+}
+
+/// The `migrate` command
+///
+/// Every Modrinth and CurseForge entry is resolved for `game_version` before
+/// anything is touched. `version` and the pins of mods.toml are only rewritten
+/// and the files swapped if all of them resolve, or with `allow_missing`, in
+/// which case the blocked entries keep their pins and get `enabled = false`.
+/// The GitHub, Maven and url entries are kept as they are.
+pub async fn migrate(
+    client: &ModrinthClient,
+    curseforge: &CurseForgeClient,
+    github: &GitHubClient,
+    current_dir: impl AsRef<Path>,
+    options: MigrateOptions<'_>,
+    config: &mut LocalModsConfig,
+) {
+    init_logger();
+
+    let MigrateOptions {
+        game_version,
+        allow_missing,
+        sync: options,
+    } = options;
+
+    if game_version == config.version {
+        info!(
+            "{}",
+            cformat!("<y>Unchanged</> mods.toml is already for {}", game_version)
+        );
+        return;
+    }
+    let current_dir = current_dir.as_ref();
+    info!(
+        "resolving entries for {} -> {}...",
+        config.version, game_version
+    );
+    let mut updates = vec![];
+    let mut blockers = vec![];
+    for kind in config.kinds() {
+        let (u, b) = modrinth_migrations(client, kind, &game_version, config).await;
+        updates.extend(u);
+        blockers.extend(b);
+        let (u, b) = curseforge_migrations(curseforge, kind, &game_version, config).await;
+        updates.extend(u);
+        blockers.extend(b);
+        report_kept(kind, config);
+    }
+
+    for blocker in &blockers {
+        info!(
+            "{}",
+            cformat!("<r>Blocked</> {} ({})", blocker.slug, blocker.reason)
+        );
+    }
+    if !blockers.is_empty() && !allow_missing {
+        info!(
+            "{}",
+            cformat!(
+                "<r>Failed</> no file for {} of {}, nothing is changed, pass --allow-missing to disable them",
+                game_version,
+                blockers
+                    .iter()
+                    .map(|blocker| blocker.slug.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        );
+        return;
+    }
+
+    let mut migrated = config.clone();
+    migrated.set_version(&game_version);
+    for update in &updates {
+        migrated.insert_entry(update.kind, update.entry.clone());
+    }
+    for blocker in &blockers {
+        migrated.set_entry_enabled(blocker.kind, &blocker.slug, false);
+    }
+
+    // The disabled entries are resolved too, to find their files
+    let lock = match lock::resolve(client, curseforge, github, &migrated).await {
+        Ok(lock) => lock,
+        Err(err) => {
+            info!("{}", cformat!("<r>Failed</> {:?}", err));
+            return;
+        }
+    };
+    let enabled = profile::enabled_entries(&migrated, options.profile);
+    let mut plan = SyncPlan {
+        actions: vec![],
+        updates,
+    };
+    for kind in migrated.kinds() {
+        info!("checking existed {}...", kind.table());
+        let entries = lock.entries(kind).collect::<Vec<_>>();
+        match plan::plan_sync_kind(
+            kind,
+            &kind.dir(current_dir),
+            &entries,
+            options,
+            &enabled,
+            &migrated,
+            Some((client, curseforge)),
+        )
+        .await
+        {
+            Ok(actions) => plan.actions.extend(actions),
+            Err(err) => info!("{}", cformat!("<r>Failed</> {:?}", err)),
+        }
+    }
+    let Some(failed) = plan::run(&plan, current_dir, options.plan, &migrated).await else {
+        return;
+    };
+
+    let from = config.version.clone();
+    *config = migrated;
+    config.save().unwrap();
+    info!(
+        "{}",
+        cformat!("<g>Migrated</> mods.toml {} -> {}", from, game_version)
+    );
+    if !failed.is_empty() {
+        info!(
+            "{}",
+            cformat!(
+                "<y>Warning</> failed for {}, run `ice mod sync` to retry",
+                failed
+                    .iter()
+                    .map(|(_, slug)| slug.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        );
+    }
+    info!("done!")
+}
+
+/// The Modrinth entries of `kind` moved to their newest versions for
+/// `game_version` allowed by their constraints, and the ones with none
+async fn modrinth_migrations(
+    client: &ModrinthClient,
+    kind: ContentKind,
+    game_version: &str,
+    config: &LocalModsConfig,
+) -> (Vec<PlannedUpdate>, Vec<Blocker>) {
+    let modrinth_mods = config
+        .get_entries(kind)
+        .into_iter()
+        .filter_map(|m| match m {
+            Mod::Modrinth(m) => Some(m),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let loaders = kind.loaders(config.loader);
+
+    let results = stream::iter(modrinth_mods)
+        .map(|m| {
+            let channel = config.entry_channel(kind, &m.slug);
+            let constraint = config.entry_constraint(kind, &m.slug);
+            let loaders = &loaders;
+            async move {
+                let res = async {
+                    let versions = client
+                        .get_project_versions(&m.slug, None, None)
+                        .await
+                        .context("failed to get versions")?
+                        .into_iter()
+                        .filter(|v| v.is_compatible(loaders, game_version, channel))
+                        .collect::<Vec<_>>();
+                    if versions.is_empty() {
+                        bail!("no version for {game_version}");
+                    }
+                    versions
+                        .into_iter()
+                        .filter(|v| constraint.allows(&v.id, &v.version_number, game_version))
+                        .max_by_key(|v| v.date_published)
+                        .with_context(|| {
+                            format!("no version for {game_version} is in {constraint}")
+                        })
+                }
+                .await;
+                (m, res)
+            }
+        })
+        .buffer_unordered(5)
+        .collect::<Vec<_>>()
+        .await;
+
+    let mut updates = vec![];
+    let mut blockers = vec![];
+    for (m, res) in results {
+        let version = match res {
+            Ok(version) => version,
+            Err(err) => {
+                blockers.push(Blocker {
+                    kind,
+                    slug: m.slug,
+                    reason: err.root_cause().to_string(),
+                });
+                continue;
+            }
+        };
+        if version.id == m.version_id() {
+            continue;
+        }
+        updates.push(PlannedUpdate {
+            kind,
+            from: m.version_number().to_string(),
+            to: version.version_number.clone(),
+            entry: Mod::Modrinth(ModrinthMod {
+                slug: m.slug.clone(),
+                version_id_number: format!("{}#{}", version.id, version.version_number),
+            }),
+            slug: m.slug,
+        });
+    }
+    (updates, blockers)
+}
+
+/// The CurseForge entries of `kind` moved to their newest files for
+/// `game_version` allowed by their constraints, and the ones with none
+async fn curseforge_migrations(
+    client: &CurseForgeClient,
+    kind: ContentKind,
+    game_version: &str,
+    config: &LocalModsConfig,
+) -> (Vec<PlannedUpdate>, Vec<Blocker>) {
+    let curseforge_mods = curseforge::entries(kind, config);
+    if curseforge_mods.is_empty() {
+        return (vec![], vec![]);
+    }
+    // The current files, to name the versions the entries are moved from
+    let names = match client
+        .get_files(curseforge_mods.iter().map(|m| m.file_id))
+        .await
+    {
+        Ok(files) => files
+            .into_iter()
+            .map(|f| (f.id, f.display_name))
+            .collect::<HashMap<_, _>>(),
+        Err(_) => HashMap::new(),
+    };
+
+    let results = stream::iter(curseforge_mods)
+        .map(|m| {
+            let channel = config.entry_channel(kind, &m.slug);
+            let constraint = config.entry_constraint(kind, &m.slug);
+            async move {
+                let res = async {
+                    let files = curseforge::compatible_files(
+                        client,
+                        kind,
+                        m.project_id,
+                        channel,
+                        game_version,
+                        config,
+                    )
+                    .await?;
+                    if files.is_empty() {
+                        bail!("no file for {game_version}");
+                    }
+                    files
+                        .into_iter()
+                        .filter(|f| {
+                            constraint.allows(&f.id.to_string(), &f.display_name, game_version)
+                        })
+                        .max_by_key(|f| f.file_date)
+                        .with_context(|| format!("no file for {game_version} is in {constraint}"))
+                }
+                .await;
+                (m, res)
+            }
+        })
+        .buffer_unordered(5)
+        .collect::<Vec<_>>()
+        .await;
+
+    let mut updates = vec![];
+    let mut blockers = vec![];
+    for (m, res) in results {
+        let file = match res {
+            Ok(file) => file,
+            Err(err) => {
+                blockers.push(Blocker {
+                    kind,
+                    slug: m.slug,
+                    reason: err.root_cause().to_string(),
+                });
+                continue;
+            }
+        };
+        if file.id == m.file_id {
+            continue;
+        }
+        updates.push(PlannedUpdate {
+            kind,
+            slug: m.slug.clone(),
+            from: names
+                .get(&m.file_id)
+                .cloned()
+                .unwrap_or(m.file_id.to_string()),
+            to: file.display_name,
+            entry: Mod::CurseForge(CurseForgeMod {
+                file_id: file.id,
+                ..m
+            }),
+        });
+    }
+    (updates, blockers)
+}
+
+/// Report the entries of `kind` whose sources do not tell the game versions
+/// of their files, they are kept as they are
+fn report_kept(kind: ContentKind, config: &LocalModsConfig) {
+    for m in config.get_entries(kind) {
+        let (slug, source) = match &m {
+            Mod::GitHub(m) => (&m.slug, "GitHub"),
+            Mod::Maven(m) => (&m.slug, "Maven"),
+            Mod::Url(m) => (&m.slug, "url"),
+            _ => continue,
+        };
+        info!(
+            "{}",
+            cformat!(
+                "<y>Kept</> {} ({} entries are not resolved for a game version, check it by hand)",
+                slug,
+                source
+            )
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{fs, path::PathBuf};
+
+    use async_compat::Compat;
+    use ice_api_tool::mock::{MockResponse, MockServer};
+    use ice_util::fs::{get_sha1_hash, get_sha512_hash};
+    use serde_json::{Value, json};
+
+    use super::*;
+    use crate::cli::{plan::PlanOptions, temp_dir};
+
+    const MODS_TOML: &str = r#"version = "1.21.6"
+loader = "fabric"
+
+[mods]
+alpha = "alpha1.0#1.0"
+beta = "beta1.0#1.0"
+"#;
+
+    fn version(id: &str, project_id: &str, game_version: &str, file: Value) -> Value {
+        json!({
+            "name": id,
+            "version_number": id.trim_start_matches(project_id),
+            "changelog": null,
+            "dependencies": [],
+            "game_versions": [game_version],
+            "version_type": "release",
+            "loaders": ["fabric"],
+            "featured": false,
+            "id": id,
+            "project_id": project_id,
+            "date_published": "2025-01-01T00:00:00Z",
+            "downloads": 0,
+            "files": [file]
+        })
+    }
+
+    fn project(slug: &str) -> Value {
+        json!({
+            "id": slug,
+            "slug": slug,
+            "title": slug,
+            "description": "",
+            "project_type": "mod",
+            "client_side": "required",
+            "server_side": "required",
+            "license": { "id": "MIT", "name": "MIT License", "url": null },
+            "categories": [],
+            "game_versions": ["1.21.6", "1.21.8"],
+            "loaders": ["fabric"],
+            "versions": [],
+            "downloads": 0
+        })
+    }
+
+    /// Migrate the config in a new temp dir to 1.21.8, which beta has no
+    /// version for, and return the dir
+    fn run_migrate(name: &str, allow_missing: bool) -> PathBuf {
+        let dir = temp_dir(&format!("migrate-{name}"));
+        fs::write(dir.join("mods.toml"), MODS_TOML).unwrap();
+        // The file of the alpha version for 1.21.8
+        fs::write(dir.join("_alpha"), "alpha 2").unwrap();
+        let sha1 = get_sha1_hash(dir.join("_alpha")).unwrap();
+        let sha512 = get_sha512_hash(dir.join("_alpha")).unwrap();
+        fs::remove_file(dir.join("_alpha")).unwrap();
+        let file = |name: &str, server: &str| {
+            json!({
+                "hashes": { "sha1": sha1, "sha512": sha512 },
+                "url": format!("{server}/files/{name}"),
+                "filename": name,
+                "primary": true,
+                "size": 7
+            })
+        };
+
+        // The versions name the urls of the files, so those are served apart
+        let files = MockServer::route(vec![(
+            "GET /files/alpha-2.0.jar",
+            MockResponse::new(200, "alpha 2"),
+        )]);
+        let alpha1 = version(
+            "alpha1.0",
+            "alpha",
+            "1.21.6",
+            file("alpha-1.0.jar", &files.url),
+        );
+        let alpha2 = version(
+            "alpha2.0",
+            "alpha",
+            "1.21.8",
+            file("alpha-2.0.jar", &files.url),
+        );
+        let beta1 = version(
+            "beta1.0",
+            "beta",
+            "1.21.6",
+            file("beta-1.0.jar", &files.url),
+        );
+        let api = MockServer::route(vec![
+            (
+                "GET /v2/project/alpha/version",
+                MockResponse::json(json!([alpha1, alpha2]).to_string()),
+            ),
+            (
+                "GET /v2/project/beta/version",
+                MockResponse::json(json!([beta1]).to_string()),
+            ),
+            (
+                "GET /v2/versions",
+                MockResponse::json(json!([alpha2, beta1]).to_string()),
+            ),
+            (
+                "GET /v2/projects",
+                MockResponse::json(json!([project("alpha"), project("beta")]).to_string()),
+            ),
+        ]);
+
+        let client = ModrinthClient::new().with_base_url(format!("{}/v2", api.url));
+        let curseforge = CurseForgeClient::new().with_base_url(&api.url);
+        let github = GitHubClient::new().with_base_url(&api.url);
+        let mut config = LocalModsConfig::load(dir.join("mods.toml")).unwrap();
+        let options = MigrateOptions {
+            game_version: "1.21.8".to_string(),
+            allow_missing,
+            sync: SyncOptions {
+                plan: PlanOptions {
+                    yes: true,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        };
+        smol::block_on(Compat::new(migrate(
+            &client,
+            &curseforge,
+            &github,
+            &dir,
+            options,
+            &mut config,
+        )));
+        dir
+    }
+
+    #[test]
+    fn test_migrate_blocked() {
+        let dir = run_migrate("blocked", false);
+        // Nothing is changed while an entry has no version for 1.21.8
+        assert_eq!(
+            fs::read_to_string(dir.join("mods.toml")).unwrap(),
+            MODS_TOML
+        );
+        assert!(!dir.join("alpha-2.0.jar").exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_migrate_allow_missing() {
+        let dir = run_migrate("allow-missing", true);
+        let config = fs::read_to_string(dir.join("mods.toml")).unwrap();
+        assert_eq!(
+            config,
+            r#"version = "1.21.8"
+loader = "fabric"
+
+[mods]
+alpha = "alpha2.0#2.0"
+beta = { version-id-number = "beta1.0#1.0", enabled = false }
+"#
+        );
+        assert_eq!(
+            fs::read_to_string(dir.join("alpha-2.0.jar")).unwrap(),
+            "alpha 2"
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -1,9 +1,33 @@
+mod compat;
+mod curseforge;
+mod github;
+mod list;
+mod local;
+mod lock;
+mod maven;
+mod migrate;
 mod modrinth;
+mod mrpack;
+mod plan;
+mod profile;
+mod remove;
 mod server;
+mod tree;
+mod url;
 
 use clap::{Parser, Subcommand};
-use ice::config::LocalModsConfig;
+use color_print::cformat;
+use compat::ReportFormat;
+use ice::{
+    config::LocalModsConfig,
+    core::{ContentKind, Side},
+};
+use ice_api_tool::cache::HttpCache;
 use ice_core::ServerLoader;
+use migrate::MigrateOptions;
+use modrinth::SyncOptions;
+use mrpack::{ExportFormat, ExportOptions};
+use plan::PlanOptions;
 use std::{
     env,
     path::{Path, PathBuf},
@@ -18,6 +42,19 @@ pub(crate) struct Cli {
 
     #[arg(short, long)]
     working_dir: Option<PathBuf>,
+
+    /// Ignore the cached api responses under `.ice/cache`
+    #[arg(long, global = true)]
+    refresh: bool,
+}
+
+/// Where mods are got from
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Source {
+    #[default]
+    Modrinth,
+    #[value(name = "curseforge")]
+    CurseForge,
 }
 
 #[derive(Subcommand)]
@@ -35,6 +72,17 @@ pub enum ModCommands {
     Check {
         #[arg(short, long)]
         version: Option<String>,
+        /// Check against several game versions and suggest the newest one
+        /// every mod supports
+        #[arg(long, conflicts_with = "version")]
+        matrix: bool,
+        /// The game versions to check with `--matrix`, in any order,
+        /// defaults to every release newer than `version` of mods.toml
+        #[arg(long, value_delimiter = ',', requires = "matrix")]
+        versions: Vec<String>,
+        /// The output format of `--matrix`
+        #[arg(long, value_enum, default_value_t)]
+        format: ReportFormat,
     },
     /// Initialize a mods.toml
     Init {
@@ -44,11 +92,137 @@ pub enum ModCommands {
         loader: ServerLoader,
     },
     /// Sync mods
-    Sync,
+    Sync {
+        /// Only keep the mods for this side, defaults to `side` of mods.toml
+        #[arg(short, long, value_enum)]
+        side: Option<Side>,
+        /// Remove the files unknown to every source instead of keeping them
+        #[arg(long)]
+        prune: bool,
+        /// Install exactly the files of mods.lock, without asking any api
+        #[arg(long)]
+        locked: bool,
+        /// Only install the mods this profile of mods.toml selects
+        #[arg(short, long)]
+        profile: Option<String>,
+        #[command(flatten)]
+        plan: PlanArgs,
+    },
     /// Update mods
-    Update,
-    /// Add mod
-    Add { slugs: Vec<String> },
+    Update {
+        /// Only update the mods for this side, defaults to `side` of mods.toml
+        #[arg(short, long, value_enum)]
+        side: Option<Side>,
+        #[command(flatten)]
+        plan: PlanArgs,
+    },
+    /// Move every entry of mods.toml to another game version
+    Migrate {
+        /// The game version to move to
+        #[arg(long = "to", value_name = "GAME_VERSION")]
+        game_version: String,
+        /// Disable the entries with no file for the game version instead of
+        /// giving up
+        #[arg(long)]
+        allow_missing: bool,
+        /// Only keep the mods for this side, defaults to `side` of mods.toml
+        #[arg(short, long, value_enum)]
+        side: Option<Side>,
+        /// Only install the mods this profile of mods.toml selects
+        #[arg(short, long)]
+        profile: Option<String>,
+        #[command(flatten)]
+        plan: PlanArgs,
+    },
+    /// Add mods, resource packs, shader packs or datapacks
+    Add {
+        slugs: Vec<String>,
+        /// What to add them as, defaults to their project type
+        #[arg(short = 't', long = "type", value_enum)]
+        kind: Option<ContentKind>,
+        /// Only download the mods for this side, defaults to `side` of mods.toml
+        #[arg(short, long, value_enum)]
+        side: Option<Side>,
+        /// Where to get them from
+        #[arg(long, value_enum, default_value_t)]
+        source: Source,
+    },
+    /// Remove mods, resource packs, shader packs or datapacks and their files
+    Remove {
+        #[arg(required = true)]
+        slugs: Vec<String>,
+        /// Which table to remove them from, defaults to any one they are in
+        #[arg(short = 't', long = "type", value_enum)]
+        kind: Option<ContentKind>,
+    },
+    /// List the entries of mods.toml and whether their files are installed
+    List,
+    /// Print the dependency tree of the entries of mods.toml
+    Tree,
+    /// Show which entries of mods.toml require a mod
+    Why {
+        slug: String,
+        /// Which table it is in, defaults to any one it is in
+        #[arg(short = 't', long = "type", value_enum)]
+        kind: Option<ContentKind>,
+    },
+    /// Search mods for the version and loader of mods.toml
+    Search {
+        #[arg(required = true)]
+        query: Vec<String>,
+        #[arg(short, long, default_value_t = 10)]
+        limit: u32,
+        /// Only show mods that can run on a server
+        #[arg(long)]
+        server: bool,
+    },
+    /// Create a mods.toml from a `.mrpack` modpack
+    Import {
+        file: PathBuf,
+        /// Where to extract the overrides, defaults to the parent of the current dir
+        #[arg(long)]
+        server_dir: Option<PathBuf>,
+    },
+    /// Export mods.toml as a modpack
+    Export {
+        #[arg(short, long, default_value_t = ExportFormat::Mrpack, value_enum)]
+        format: ExportFormat,
+        /// Defaults to `<name>-<pack-version>.mrpack` in the current dir
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Defaults to the name of the current dir
+        #[arg(long)]
+        name: Option<String>,
+        #[arg(long, default_value = "1.0.0")]
+        pack_version: String,
+        /// Defaults to the latest version of the loader for the game version
+        #[arg(long)]
+        loader_version: Option<String>,
+    },
+}
+
+/// The arguments of the commands that plan before they act
+#[derive(clap::Args)]
+pub struct PlanArgs {
+    /// Only print what would be done
+    #[arg(long)]
+    dry_run: bool,
+    /// Do it without asking
+    #[arg(short, long)]
+    yes: bool,
+    /// The output format of `--dry-run`
+    #[arg(long, value_enum, default_value_t, requires = "dry_run")]
+    format: ReportFormat,
+}
+
+impl From<PlanArgs> for PlanOptions {
+    fn from(args: PlanArgs) -> Self {
+        PlanOptions {
+            dry_run: args.dry_run,
+            yes: args.yes,
+            format: args.format,
+        }
+    }
 }
 
 impl ModCommands {
@@ -60,24 +234,196 @@ impl ModCommands {
             modrinth::init(version, loader, current_dir).await;
             return;
         }
+        if let ModCommands::Import { file, server_dir } = self {
+            let client = modrinth::modrinth_client();
+            mrpack::import(&client, file, server_dir, current_dir).await;
+            return;
+        }
 
         info!("loading mods.toml...");
         let mut config = LocalModsConfig::load(config_path).unwrap();
+        let client = modrinth::modrinth_client();
+        let curseforge = curseforge::curseforge_client();
+        let github = github::github_client();
 
         match self {
-            ModCommands::Sync => {
-                modrinth::sync(current_dir, &config).await;
+            ModCommands::Sync {
+                side,
+                prune,
+                locked,
+                profile,
+                plan,
+            } => {
+                if !locked {
+                    modrinth::pin_constraints(&client, !plan.dry_run, &mut config).await;
+                }
+                let profile = match profile.map(|name| config.profile(&name)).transpose() {
+                    Ok(profile) => profile,
+                    Err(err) => {
+                        println!("{}", cformat!("<r>Failed</> {err}"));
+                        return;
+                    }
+                };
+                let side = side
+                    .or(profile.and_then(|profile| profile.side))
+                    .or(config.side);
+                let options = SyncOptions {
+                    side,
+                    profile,
+                    prune,
+                    locked,
+                    plan: plan.into(),
+                };
+                if options.locked {
+                    lock::sync_locked(current_dir, options, &config).await;
+                } else {
+                    modrinth::sync(&client, &curseforge, &github, current_dir, options, &config)
+                        .await;
+                    if !options.plan.dry_run {
+                        lock::refresh(&client, &curseforge, &github, &config).await;
+                    }
+                }
+            }
+            ModCommands::Update { side, plan } => {
+                let side = side.or(config.side);
+                let options = PlanOptions::from(plan);
+                modrinth::pin_constraints(&client, !options.dry_run, &mut config).await;
+                modrinth::update(
+                    &client,
+                    &curseforge,
+                    &github,
+                    current_dir,
+                    side,
+                    options,
+                    &mut config,
+                )
+                .await;
+                if !options.dry_run {
+                    lock::refresh(&client, &curseforge, &github, &config).await;
+                }
+            }
+            ModCommands::Migrate {
+                game_version,
+                allow_missing,
+                side,
+                profile,
+                plan,
+            } => {
+                // Cloned so the profile outlives the changes to `config`
+                let profile = match profile
+                    .map(|name| config.profile(&name).cloned())
+                    .transpose()
+                {
+                    Ok(profile) => profile,
+                    Err(err) => {
+                        println!("{}", cformat!("<r>Failed</> {err}"));
+                        return;
+                    }
+                };
+                let side = side
+                    .or(profile.as_ref().and_then(|profile| profile.side))
+                    .or(config.side);
+                let options = MigrateOptions {
+                    game_version,
+                    allow_missing,
+                    sync: SyncOptions {
+                        side,
+                        profile: profile.as_ref(),
+                        prune: false,
+                        locked: false,
+                        plan: plan.into(),
+                    },
+                };
+                let dry_run = options.sync.plan.dry_run;
+                migrate::migrate(
+                    &client,
+                    &curseforge,
+                    &github,
+                    current_dir,
+                    options,
+                    &mut config,
+                )
+                .await;
+                if !dry_run {
+                    lock::refresh(&client, &curseforge, &github, &config).await;
+                }
             }
-            ModCommands::Update => {
-                modrinth::update(current_dir, &mut config).await;
+            ModCommands::Add {
+                slugs,
+                kind,
+                side,
+                source: Source::CurseForge,
+            } => {
+                let side = side.or(config.side);
+                modrinth::pin_constraints(&client, true, &mut config).await;
+                curseforge::add(&curseforge, slugs, kind, current_dir, side, &mut config).await;
+                lock::refresh(&client, &curseforge, &github, &config).await;
             }
-            ModCommands::Add { slugs } => {
-                modrinth::add(slugs, current_dir, &mut config).await;
+            ModCommands::Add {
+                slugs, kind, side, ..
+            } => {
+                let side = side.or(config.side);
+                modrinth::pin_constraints(&client, true, &mut config).await;
+                modrinth::add(&client, slugs, kind, current_dir, side, &mut config).await;
+                lock::refresh(&client, &curseforge, &github, &config).await;
             }
-            ModCommands::Check { version } => {
-                modrinth::check(version, current_dir, &config).await;
+            ModCommands::Remove { slugs, kind } => {
+                remove::remove(
+                    &client,
+                    &curseforge,
+                    &github,
+                    slugs,
+                    kind,
+                    current_dir,
+                    &mut config,
+                )
+                .await;
+                lock::refresh(&client, &curseforge, &github, &config).await;
             }
-            ModCommands::Init { .. } => (),
+            ModCommands::List => {
+                list::list(&client, &curseforge, &github, current_dir, &config).await;
+            }
+            ModCommands::Tree => {
+                tree::tree(&client, &curseforge, &github, &config).await;
+            }
+            ModCommands::Why { slug, kind } => {
+                tree::why(&client, &curseforge, &github, slug, kind, &config).await;
+            }
+            ModCommands::Search {
+                query,
+                limit,
+                server,
+            } => {
+                modrinth::search(&client, query.join(" "), limit, server, &config).await;
+            }
+            ModCommands::Export {
+                format,
+                output,
+                name,
+                pack_version,
+                loader_version,
+            } => {
+                let options = ExportOptions {
+                    format,
+                    output,
+                    name,
+                    pack_version,
+                    loader_version,
+                };
+                mrpack::export(&client, options, current_dir, &config).await;
+            }
+            ModCommands::Check {
+                matrix: true,
+                versions,
+                format,
+                ..
+            } => {
+                compat::check_matrix(&client, &curseforge, versions, format, &config).await;
+            }
+            ModCommands::Check { version, .. } => {
+                modrinth::check(&client, &github, version, current_dir, &config).await;
+            }
+            ModCommands::Init { .. } | ModCommands::Import { .. } => (),
         }
     }
 }
@@ -126,6 +472,9 @@ impl Cli {
         let current_dir = self
             .working_dir
             .unwrap_or(env::current_dir().expect("failed to get current_dir"));
+        HttpCache::new(current_dir.join(".ice").join("cache"))
+            .with_refresh(self.refresh)
+            .install();
 
         match self.command {
             Commands::Mod(command) => {
@@ -136,3 +485,12 @@ impl Cli {
         }
     }
 }
+
+/// A new empty dir for the test `name`, under the temp dir of the system
+#[cfg(test)]
+pub(crate) fn temp_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("ice-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
@@ -1,30 +1,46 @@
 use std::{
-    collections::HashSet,
+    collections::{HashMap, HashSet, VecDeque},
     fs::{self, DirEntry},
     path::{Path, PathBuf},
-    sync::Arc,
 };
 
 use anyhow::Context;
 use color_print::cformat;
-use futures::{stream, StreamExt};
+use futures::{StreamExt, TryStreamExt, stream};
 use ice::{
-    config::LocalModsConfig,
-    core::{Mod, ModrinthMod},
+    config::{LocalModsConfig, TomlProfile, constraint::VersionConstraint},
+    core::{ContentKind, Mod, ModrinthMod, Side},
 };
 use ice_api_tool::{
     self as api,
-    modrinth::{get_latest_version_from_slug, utils::download_version_file, HashMethod},
+    cache::HttpCache,
+    curseforge::CurseForgeClient,
+    error::ApiError,
+    github::GitHubClient,
+    modrinth::{
+        HashMethod, ModrinthClient, SearchQuery,
+        types::{
+            Dependency, DependencyType, Loader, Project, ProjectType, SideSupport, Version,
+            VersionType,
+        },
+        utils::download_version_file,
+    },
 };
 use ice_core::ServerLoader;
 use ice_util::fs::get_sha1_hash;
 use indicatif::ProgressStyle;
-use tracing::{info, info_span, Instrument, Span};
-use tracing_indicatif::{span_ext::IndicatifSpanExt, IndicatifLayer};
+use tracing::{Instrument, Span, info, info_span};
+use tracing_indicatif::{IndicatifLayer, span_ext::IndicatifSpanExt};
 use tracing_subscriber::EnvFilter;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
-fn init_logger() {
+use super::{
+    curseforge, github, lock, maven,
+    plan::{self, PlanOptions, PlannedUpdate, SyncPlan},
+    profile, url,
+};
+
+pub(crate) fn init_logger() {
     let indicatif_layer = IndicatifLayer::new();
 
     let filter_layer = EnvFilter::try_from_default_env()
@@ -41,40 +57,70 @@ fn init_logger() {
                 .with_writer(indicatif_layer.get_stderr_writer()),
         )
         .with(indicatif_layer)
-        .init();
+        .try_init()
+        .ok();
+}
+
+/// Build the [`ModrinthClient`] used by the `mod` commands
+///
+/// The api base url can be overridden with `ICE_MODRINTH_API`
+pub fn modrinth_client() -> ModrinthClient {
+    let client = ModrinthClient::new().with_user_agent(concat!(
+        "AzurIce/ice/",
+        env!("CARGO_PKG_VERSION"),
+        " (github.com/AzurIce/ice)"
+    ));
+    let client = match HttpCache::global() {
+        Some(cache) => client.with_cache(cache.clone()),
+        None => client,
+    };
+    match std::env::var("ICE_MODRINTH_API") {
+        Ok(base_url) => client.with_base_url(base_url),
+        Err(_) => client,
+    }
 }
 
 // MARK: CLI
-pub async fn check(version: Option<String>, _current_dir: &Path, config: &LocalModsConfig) {
+pub async fn check(
+    client: &ModrinthClient,
+    github: &GitHubClient,
+    version: Option<String>,
+    _current_dir: &Path,
+    config: &LocalModsConfig,
+) {
     init_logger();
     let version = version.unwrap_or(config.version.clone());
 
-    let loader = ice_core_loader_to_modrinth_loader(config.loader);
-    let mut stream = stream::iter(
+    let mut stream = stream::iter(config.kinds().into_iter().flat_map(|kind| {
         config
-            .mods
-            .iter()
-            .map(|(slug, toml_mod)| Mod::from((slug.clone(), toml_mod.clone())))
-            .filter_map(|m| {
-                if let Mod::Modrinth(m) = m {
-                    Some(m)
-                } else {
-                    None
-                }
-            }),
-    )
-    .map(|m| async {
-        get_latest_version_from_slug(m.slug.clone(), vec![loader], &version)
-            .await
-            .context("failed to get latest version")
-            .map(|v| (m, v))
+            .get_entries(kind)
+            .into_iter()
+            .filter_map(move |m| match m {
+                Mod::Modrinth(m) => Some((kind, m)),
+                _ => None,
+            })
+    }))
+    .map(|(kind, m)| {
+        let version = &version;
+        async move {
+            client
+                .get_latest_version_from_slug(
+                    m.slug.clone(),
+                    kind.loaders(config.loader),
+                    version,
+                    config.entry_channel(kind, &m.slug),
+                )
+                .await
+                .context("failed to get latest version")
+                .map(|v| (m, v))
+        }
     })
     .buffer_unordered(5);
 
     while let Some(res) = stream.next().await {
         match res {
             Ok((m, v)) => {
-                if m.version_id() == &v.id {
+                if m.version_id() == v.id {
                     info!(
                         "{}",
                         cformat!("<g>Latest</> {} = {}", m.slug, m.version_number())
@@ -91,11 +137,18 @@ pub async fn check(version: Option<String>, _current_dir: &Path, config: &LocalM
                     );
                 }
             }
-            Err(err) => {
+            Err(err) if is_not_found(&err) => {
                 info!("{}", cformat!("<r>Not Found</> {:?}", err));
             }
+            Err(err) => {
+                info!("{}", cformat!("<r>Failed</> {:?}", err));
+            }
         }
     }
+
+    // The releases do not depend on the game version
+    github::check(github, config).await;
+    maven::check(config).await;
 }
 
 /// Initialize a `mods.toml` under `current_dir`
@@ -123,246 +176,1108 @@ pub async fn init<P: AsRef<Path>, S: AsRef<str>>(
     config.save().unwrap();
 }
 
+/// The options of the `sync` command
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SyncOptions<'a> {
+    /// Only the entries installed on this side are kept, if it is given
+    pub side: Option<Side>,
+    /// Only the entries this profile selects are installed, the files of the
+    /// others are disabled
+    pub profile: Option<&'a TomlProfile>,
+    /// Remove the files unknown to every source instead of keeping them
+    pub prune: bool,
+    /// Install the files of mods.lock instead of resolving mods.toml
+    pub locked: bool,
+    pub plan: PlanOptions,
+}
+
 /// The `sync` command
-pub async fn sync<P: AsRef<Path>>(current_dir: P, config: &LocalModsConfig) {
+///
+/// Every entry is resolved and the existing files are looked up before
+/// anything is touched, see [`plan::run`].
+pub async fn sync<P: AsRef<Path>>(
+    client: &ModrinthClient,
+    curseforge: &CurseForgeClient,
+    github: &GitHubClient,
+    current_dir: P,
+    options: SyncOptions<'_>,
+    config: &LocalModsConfig,
+) {
     init_logger();
 
     let current_dir = current_dir.as_ref();
+    let enabled = profile::enabled_entries(config, options.profile);
+    // The disabled entries are resolved too, to find their files
+    let lock = match lock::resolve(client, curseforge, github, config).await {
+        Ok(lock) => lock,
+        Err(err) => {
+            info!("{}", cformat!("<r>Failed</> {:?}", err));
+            return;
+        }
+    };
+    let mut plan = SyncPlan::default();
+    for kind in config.kinds() {
+        info!("checking existed {}...", kind.table());
+        let entries = lock.entries(kind).collect::<Vec<_>>();
+        match plan::plan_sync_kind(
+            kind,
+            &kind.dir(current_dir),
+            &entries,
+            options,
+            &enabled,
+            config,
+            Some((client, curseforge)),
+        )
+        .await
+        {
+            Ok(actions) => plan.actions.extend(actions),
+            Err(err) => info!("{}", cformat!("<r>Failed</> {:?}", err)),
+        }
+    }
+    if plan::run(&plan, current_dir, options.plan, config)
+        .await
+        .is_some()
+    {
+        info!("done!");
+    }
+}
 
-    let mut synced_mods = HashSet::<String>::new();
+/// Whether the entry `slug` of a source that does not tell the sides of a
+/// project is installed on `side`, reporting it as skipped if not
+///
+/// See [`ice::config::ModsConfig::is_entry_on_side`]
+pub(crate) fn keep_entry_on_side(
+    kind: ContentKind,
+    slug: &str,
+    side: Option<Side>,
+    config: &LocalModsConfig,
+) -> bool {
+    match side {
+        Some(side) if !config.is_entry_on_side(kind, slug, side) => {
+            info!(
+                "{}",
+                cformat!("<dim>Skipped</> {} (not for {})", slug, side)
+            );
+            false
+        }
+        _ => true,
+    }
+}
 
-    // First, use [`sync_file`] to sync all existed mod files
-    info!("checking existed mods...");
-    let jar_files = get_jar_files(current_dir)
-        .into_iter()
-        .filter(|entry| !entry.file_name().to_string_lossy().starts_with("_"));
-    let mut stream = stream::iter(jar_files)
-        .map(|entry| async {
-            let filename = entry.file_name();
-            let filename = filename.to_str().unwrap();
+/// The `update` command
+///
+/// The newer versions of every entry are looked up first, then their files
+/// are replaced once the plan is confirmed, see [`plan::run`]. An entry is
+/// only moved in mods.toml once its file is in place.
+///
+/// Only the entries installed on `side` are updated, if it is given
+pub async fn update(
+    client: &ModrinthClient,
+    curseforge: &CurseForgeClient,
+    github: &GitHubClient,
+    current_dir: impl AsRef<Path>,
+    side: Option<Side>,
+    options: PlanOptions,
+    config: &mut LocalModsConfig,
+) {
+    init_logger();
 
-            sync_file(entry, config, &current_dir)
-                .await
-                .context(format!("sync file {}", filename))
+    let current_dir = current_dir.as_ref();
+    let mut updates = vec![];
+    for kind in config.kinds() {
+        updates.extend(entry_updates(client, kind, side, config).await);
+        updates.extend(curseforge::entry_updates(curseforge, kind, side, config).await);
+        updates.extend(github::entry_updates(github, kind, side, config).await);
+        updates.extend(maven::entry_updates(kind, side, config).await);
+    }
+
+    // The files of the entries before and after, to know what to replace
+    let mut old = config.clone();
+    old.retain_entries(|kind, key| {
+        updates
+            .iter()
+            .any(|update| update.kind == kind && update.slug == key)
+    });
+    let mut new = old.clone();
+    for update in &updates {
+        new.insert_entry(update.kind, update.entry.clone());
+    }
+    let locks = futures::try_join!(
+        lock::resolve(client, curseforge, github, &old),
+        lock::resolve(client, curseforge, github, &new)
+    );
+    let (old, new) = match locks {
+        Ok(locks) => locks,
+        Err(err) => {
+            info!("{}", cformat!("<r>Failed</> {:?}", err));
+            return;
+        }
+    };
+
+    let plan = SyncPlan {
+        actions: plan::plan_updates(current_dir, &updates, &old, &new, config),
+        updates,
+    };
+    let Some(failed) = plan::run(&plan, current_dir, options, config).await else {
+        return;
+    };
+    for update in plan.updates {
+        if failed.contains(&(update.kind, update.slug.clone())) {
+            continue;
+        }
+        config.insert_entry(update.kind, update.entry);
+        config.save().unwrap();
+        info!(
+            "{}",
+            cformat!(
+                "<g>Updated</> {} = {} -> {}",
+                update.slug,
+                update.from,
+                update.to
+            )
+        );
+    }
+    info!("done!")
+}
+
+/// The Modrinth entries of `kind` to move to newer versions
+///
+/// Only the entries installed on `side` are updated, if it is given
+async fn entry_updates(
+    client: &ModrinthClient,
+    kind: ContentKind,
+    side: Option<Side>,
+    config: &LocalModsConfig,
+) -> Vec<PlannedUpdate> {
+    let modrinth_mods = config
+        .get_entries(kind)
+        .into_iter()
+        .filter_map(|m| match m {
+            Mod::Modrinth(m) => Some(m),
+            _ => None,
         })
-        .buffer_unordered(5);
+        .collect::<Vec<_>>();
+    if modrinth_mods.is_empty() {
+        return vec![];
+    }
 
-    while let Some(res) = stream.next().await {
-        match res {
-            Ok(res) => match res {
-                SyncRes::Downloaded(slug, version) => {
-                    synced_mods.insert(slug.clone());
-                    info!("{}", cformat!("<g>Updated</> {} to {}", slug, version));
-                }
-                SyncRes::Removed(path) => {
+    let res = async {
+        let versions = client
+            .get_versions(modrinth_mods.iter().map(|m| m.version_id()))
+            .await
+            .context("failed to get versions")?
+            .into_iter()
+            .map(|v| (v.id.clone(), v))
+            .collect::<HashMap<_, _>>();
+        let projects = client
+            .get_projects(versions.values().map(|v| v.project_id.clone()))
+            .await
+            .context("failed to get projects")?
+            .into_iter()
+            .map(|p| (p.id.clone(), p))
+            .collect::<HashMap<_, _>>();
+        Ok::<_, anyhow::Error>((versions, projects))
+    }
+    .await;
+    let (versions, projects) = match res {
+        Ok(res) => res,
+        Err(err) => {
+            info!("{}", cformat!("<r>Failed</> {:?}", err));
+            return vec![];
+        }
+    };
+    let modrinth_mods = modrinth_mods
+        .into_iter()
+        .filter_map(|m| {
+            let Some((version, project)) = versions
+                .get(m.version_id())
+                .and_then(|v| Some((v, projects.get(&v.project_id)?)))
+            else {
+                info!(
+                    "{}",
+                    cformat!("<r>Failed</> update {kind} {} (version not found)", m.slug)
+                );
+                return None;
+            };
+            match side {
+                Some(side) if !config.is_on_side(kind, &m.slug, project, side) => {
                     info!(
                         "{}",
-                        cformat!(
-                            "<r>Removed</> {}",
-                            path.file_name().unwrap().to_str().unwrap()
-                        )
+                        cformat!("<dim>Skipped</> {} (not for {})", m.slug, side)
                     );
+                    None
                 }
-                SyncRes::Unchanged(slug) => {
-                    synced_mods.insert(slug.clone());
-                    info!("{}", cformat!("<y>Unchanged</> {}", slug));
+                _ => Some((m, version, project)),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let loaders = kind.loaders(config.loader);
+    let game_version = &config.version;
+    let latest_versions = match client
+        .get_latest_versions_from_hashes(
+            modrinth_mods
+                .iter()
+                .map(|(_, version, _)| &version.get_primary_file().hashes.sha1),
+            HashMethod::Sha1,
+            &loaders,
+            [game_version],
+        )
+        .await
+    {
+        Ok(versions) => versions,
+        Err(err) => {
+            info!("{}", cformat!("<r>Failed</> {:?}", err));
+            return vec![];
+        }
+    };
+
+    let results = stream::iter(modrinth_mods)
+        .map(|(m, cur_version, project)| {
+            let latest_version = latest_versions.get(&cur_version.get_primary_file().hashes.sha1);
+            let channel = config.entry_channel(kind, &m.slug);
+            let constraint = config.entry_constraint(kind, &m.slug);
+            let loaders = &loaders;
+            async move {
+                let res = async {
+                    if constraint != VersionConstraint::Any {
+                        return constrained_version(
+                            client,
+                            project,
+                            cur_version,
+                            loaders,
+                            game_version,
+                            channel,
+                            &constraint,
+                        )
+                        .await;
+                    }
+                    let version = match latest_version {
+                        Some(version) if version.version_type.is_on_channel(channel) => {
+                            version.clone()
+                        }
+                        // The bulk endpoint knows nothing about channels,
+                        // so look for the latest version on the channel
+                        _ => {
+                            client
+                                .get_latest_version_from_slug(
+                                    &project.slug,
+                                    loaders.clone(),
+                                    game_version,
+                                    channel,
+                                )
+                                .await?
+                        }
+                    };
+                    Ok((version, None))
                 }
-                _ => (),
-            },
+                .await;
+                (m, res, constraint)
+            }
+        })
+        .buffer_unordered(5)
+        .collect::<Vec<_>>()
+        .await;
+
+    let mut updates = vec![];
+    for (m, res, constraint) in results {
+        let (version, held_back) = match res {
+            Ok(res) => res,
             Err(err) => {
-                info!("{}", cformat!("<r>Failed</> {:?}", err));
+                info!(
+                    "{}",
+                    cformat!(
+                        "<r>Failed</> update {kind} {} ({})",
+                        m.slug,
+                        err.root_cause()
+                    )
+                );
+                continue;
             }
+        };
+        if let Some(latest) = held_back {
+            report_held_back(&m.slug, &latest, &constraint);
+        }
+        if version.id == m.version_id() {
+            info!(
+                "{}",
+                cformat!("<y>Unchanged</> {} = {}", m.slug, m.version_id_number)
+            );
+            continue;
         }
+        updates.push(PlannedUpdate {
+            kind,
+            from: m.version_number().to_string(),
+            to: version.version_number.clone(),
+            entry: Mod::Modrinth(ModrinthMod {
+                slug: m.slug.clone(),
+                version_id_number: format!("{}#{}", version.id, version.version_number),
+            }),
+            slug: m.slug,
+        });
     }
+    updates
+}
 
-    // Then, download mods not existed
-    info!("downloading other mods...");
-    let mut stream = stream::iter(config.get_mods().into_iter().filter(|value| {
-        if let Mod::Modrinth(modrinth_mod) = value {
-            !synced_mods.contains(&modrinth_mod.slug)
-        } else {
-            false
-        }
-    }))
-    .map(|modrinth_mod| async {
-        match modrinth_mod {
-            Mod::Modrinth(modrinth_mod) => download_modrinth_mod(&modrinth_mod, &current_dir)
-                .await
-                .map_err(|err| {
-                    anyhow::anyhow!(format!(
-                        "download mod {} = {} ({})",
-                        modrinth_mod.slug,
-                        modrinth_mod.version_id_number,
-                        err.root_cause()
-                    ))
-                })
-                .map(|_| modrinth_mod),
-            _ => unreachable!(),
-        }
-    })
-    .buffer_unordered(5);
+/// The newest version of `project` allowed by `constraint`, `cur_version` if
+/// there is none
+///
+/// Also gives the version number of the latest version if it is held back.
+async fn constrained_version(
+    client: &ModrinthClient,
+    project: &Project,
+    cur_version: &Version,
+    loaders: &[Loader],
+    game_version: &str,
+    channel: VersionType,
+    constraint: &VersionConstraint,
+) -> Result<(Version, Option<String>), anyhow::Error> {
+    let versions = client
+        .get_project_versions(&project.slug, None, None)
+        .await
+        .context("failed to get versions")?
+        .into_iter()
+        .filter(|v| v.is_compatible(loaders, game_version, channel))
+        .collect::<Vec<_>>();
+    let latest = versions.iter().max_by_key(|v| v.date_published);
+    let version = versions
+        .iter()
+        .filter(|v| constraint.allows(&v.id, &v.version_number, game_version))
+        .max_by_key(|v| v.date_published)
+        .unwrap_or(cur_version);
+    let held_back = latest
+        .filter(|latest| latest.id != version.id)
+        .map(|latest| latest.version_number.clone());
+    Ok((version.clone(), held_back))
+}
 
-    while let Some(res) = stream.next().await {
-        match res {
-            Ok(res) => {
+/// Report that a newer version of the entry `slug` is out of its `version`
+/// constraint
+pub(crate) fn report_held_back(slug: &str, latest: &str, constraint: &VersionConstraint) {
+    info!(
+        "{}",
+        cformat!(
+            "<y>Held back</> {} ({} is out of {})",
+            slug,
+            latest,
+            constraint
+        )
+    );
+}
+
+/// Pin the newest version allowed by its constraint to every entry written
+/// as a bare constraint, see [`ice::config::ModsConfig::unpinned_entries`]
+///
+/// The pins are saved to mods.toml unless `save` is false, for dry runs.
+pub async fn pin_constraints(client: &ModrinthClient, save: bool, config: &mut LocalModsConfig) {
+    let unpinned = config
+        .kinds()
+        .into_iter()
+        .flat_map(|kind| {
+            config
+                .unpinned_entries(kind)
+                .into_iter()
+                .map(move |(slug, constraint)| (kind, slug, constraint))
+        })
+        .collect::<Vec<_>>();
+    if unpinned.is_empty() {
+        return;
+    }
+
+    let results = stream::iter(unpinned)
+        .map(|(kind, slug, constraint)| {
+            let config = &*config;
+            async move {
+                let res = async {
+                    let loaders = kind.loaders(config.loader);
+                    let channel = config.entry_channel(kind, &slug);
+                    client
+                        .get_project_versions(&slug, None, None)
+                        .await
+                        .context("failed to get versions")?
+                        .into_iter()
+                        .filter(|v| {
+                            v.is_compatible(&loaders, &config.version, channel)
+                                && constraint.allows(&v.id, &v.version_number, &config.version)
+                        })
+                        .max_by_key(|v| v.date_published)
+                        .with_context(|| {
+                            format!("no version for {} is in {constraint}", config.version)
+                        })
+                }
+                .await;
+                (kind, slug, constraint, res)
+            }
+        })
+        .buffer_unordered(5)
+        .collect::<Vec<_>>()
+        .await;
+
+    for (kind, slug, constraint, res) in results {
+        let version = match res {
+            Ok(version) => version,
+            Err(err) => {
                 info!(
                     "{}",
-                    cformat!("<g>Downloaded</> {} = {}", res.slug, res.version_id_number)
+                    cformat!("<r>Failed</> pin {kind} {} ({})", slug, err.root_cause())
                 );
+                continue;
             }
-            Err(err) => {
-                info!("{}", cformat!("<r>Failed</> {}", err));
-            }
-        }
+        };
+        info!(
+            "{}",
+            cformat!(
+                "<g>Pinned</> {} = {}#{} ({})",
+                slug,
+                version.id,
+                version.version_number,
+                constraint
+            )
+        );
+        config.insert_entry(
+            kind,
+            Mod::Modrinth(ModrinthMod {
+                slug,
+                version_id_number: format!("{}#{}", version.id, version.version_number),
+            }),
+        );
+    }
+    if save {
+        config.save().unwrap();
     }
-    info!("done!");
 }
 
-/// The `update` command
-pub async fn update(current_dir: impl AsRef<Path>, config: &mut LocalModsConfig) {
+/// The `add` command
+///
+/// Required dependencies of the added mods are added too (transitively),
+/// optional ones are offered.
+///
+/// The kind of each project is taken from its project type unless `kind`
+/// is given.
+///
+/// Entries not installed on `side` are recorded but not downloaded.
+pub async fn add(
+    client: &ModrinthClient,
+    slugs: Vec<String>,
+    kind: Option<ContentKind>,
+    current_dir: impl AsRef<Path>,
+    side: Option<Side>,
+    config: &mut LocalModsConfig,
+) {
     init_logger();
 
     let current_dir = current_dir.as_ref();
 
-    let _config = Arc::new(config.clone());
-    let mut stream = stream::iter(get_jar_files(current_dir))
-        .map(|file| {
-            let filename = file.file_name();
-            let filename = filename.into_string().unwrap();
-            let _config = _config.clone();
-            async move {
-                update_mod(file, _config, current_dir)
-                    .await
-                    .context(format!("update mod {}", filename))
+    let mut seen = HashSet::new();
+    let slugs = slugs
+        .into_iter()
+        .filter(|slug| seen.insert(slug.clone()))
+        .collect::<Vec<_>>();
+
+    let mut resolver = match DependencyResolver::new(client, config).await {
+        Ok(resolver) => resolver,
+        Err(err) => {
+            info!("{}", cformat!("<r>Failed</> {:?}", err));
+            return;
+        }
+    };
+
+    // First, resolve the requested mods with their required dependencies
+    let mut resolved = vec![];
+    for slug in slugs {
+        let project = match project_of_kind(client, &slug, kind).await {
+            Ok(project) => project,
+            Err(err) => {
+                info!("{}", cformat!("<r>Failed</> {:?}", err));
+                continue;
             }
+        };
+        let project_kind = kind.or(ContentKind::of_project(&project)).unwrap();
+        if project_kind != ContentKind::Mod {
+            if let Some(Mod::Modrinth(existed)) = config.get_entry(project_kind, &slug) {
+                info!(
+                    "{}",
+                    cformat!(
+                        "<y>Already Exist</> {} {} = {}",
+                        project_kind,
+                        slug,
+                        existed.version_id_number
+                    )
+                );
+                continue;
+            }
+            match add_content(client, project_kind, &project, current_dir, side, config).await {
+                Ok(version) => info!(
+                    "{}",
+                    cformat!(
+                        "<g>Added</> {} {} = {}#{}",
+                        project_kind,
+                        project.slug,
+                        version.id,
+                        version.version_number
+                    )
+                ),
+                Err(err) => info!("{}", cformat!("<r>Failed</> {:?}", err)),
+            }
+            continue;
+        }
+
+        if let Some(Mod::Modrinth(modrinth_mod)) = config.get_mod(&slug) {
+            if config.is_dependency(&slug) {
+                config.set_dependency(&slug, false);
+                config.save().unwrap();
+                info!(
+                    "{}",
+                    cformat!("<g>Marked</> {} as explicitly requested", slug)
+                );
+            } else {
+                info!(
+                    "{}",
+                    cformat!(
+                        "<y>Already Exist</> {} = {}",
+                        slug,
+                        modrinth_mod.version_id_number
+                    )
+                );
+            }
+            continue;
+        }
+
+        match resolver
+            .resolve(&slug, None)
+            .await
+            .context(format!("add mod {slug}"))
+        {
+            Ok(mods) => resolved.extend(mods),
+            Err(err) => info!("{}", cformat!("<r>Failed</> {:?}", err)),
+        }
+    }
+
+    // Then, offer the optional dependencies
+    loop {
+        let offers = match resolver.take_optional().await {
+            Ok(offers) => offers,
+            Err(err) => {
+                info!("{}", cformat!("<r>Failed</> {:?}", err));
+                break;
+            }
+        };
+        if offers.is_empty() {
+            break;
+        }
+        for (project, dependent) in offers {
+            if !confirm(format!(
+                "{} is an optional dependency of {}, add it?",
+                project.slug, dependent
+            )) {
+                info!(
+                    "{}",
+                    cformat!(
+                        "<y>Skipped</> optional dependency {} of {}",
+                        project.slug,
+                        dependent
+                    )
+                );
+                continue;
+            }
+            match resolver
+                .resolve(&project.slug, Some(dependent))
+                .await
+                .context(format!("add mod {}", project.slug))
+            {
+                Ok(mods) => resolved.extend(mods),
+                Err(err) => info!("{}", cformat!("<r>Failed</> {:?}", err)),
+            }
+        }
+    }
+
+    // Mods not for `side` are only recorded
+    if let Some(side) = side {
+        let not_on_side;
+        (resolved, not_on_side) = resolved
+            .into_iter()
+            .partition(|m| config.is_on_side(ContentKind::Mod, &m.project.slug, &m.project, side));
+        for ResolvedMod {
+            project,
+            version,
+            required_by,
+        } in not_on_side
+        {
+            config.insert_mod(Mod::Modrinth(ModrinthMod {
+                slug: project.slug.clone(),
+                version_id_number: format!("{}#{}", version.id, version.version_number),
+            }));
+            config.set_dependency(&project.slug, required_by.is_some());
+            config.save().unwrap();
+            info!(
+                "{}",
+                cformat!(
+                    "<g>Added</> {} = {}#{} (not downloaded, not for {})",
+                    project.slug,
+                    version.id,
+                    version.version_number,
+                    side
+                )
+            );
+        }
+    }
+
+    // Finally, download and record them
+    let mut stream = stream::iter(resolved)
+        .map(|resolved_mod| async {
+            download_version(&resolved_mod.version, current_dir)
+                .await
+                .context(format!("add mod {}", resolved_mod.project.slug))
+                .map(|_| resolved_mod)
         })
         .buffer_unordered(5);
 
     while let Some(res) = stream.next().await {
         match res {
-            Ok(res) => match res {
-                UpdateRes::Updated {
-                    slug,
-                    version_id,
-                    version_number,
-                } => {
-                    config.insert_mod(Mod::Modrinth(ModrinthMod {
-                        slug: slug.clone(),
-                        version_id_number: format!("{}#{}", version_id, version_number),
-                    }));
-                    config.save().unwrap();
-                    info!(
+            Ok(ResolvedMod {
+                project,
+                version,
+                required_by,
+            }) => {
+                config.insert_mod(Mod::Modrinth(ModrinthMod {
+                    slug: project.slug.clone(),
+                    version_id_number: format!("{}#{}", version.id, version.version_number),
+                }));
+                config.set_dependency(&project.slug, required_by.is_some());
+                config.save().unwrap();
+                match required_by {
+                    Some(dependent) => info!(
                         "{}",
-                        cformat!("<g>Updated</> {} = {}#{}", slug, version_id, version_number)
-                    );
-                }
-                UpdateRes::Unchanged {
-                    slug,
-                    version_id,
-                    version_number,
-                } => {
-                    info!(
+                        cformat!(
+                            "<g>Added</> {} = {}#{} (dependency of {})",
+                            project.slug,
+                            version.id,
+                            version.version_number,
+                            dependent
+                        )
+                    ),
+                    None => info!(
                         "{}",
                         cformat!(
-                            "<y>Unchanged</> {} = {}#{}",
-                            slug,
-                            version_id,
-                            version_number
+                            "<g>Added</> {} = {}#{}",
+                            project.slug,
+                            version.id,
+                            version.version_number
                         )
-                    );
+                    ),
                 }
-                UpdateRes::Skipped => (),
-            },
+            }
             Err(err) => {
-                info!("{}", cformat!("<r>Failed</> {err}"));
+                info!("{}", cformat!("<r>Failed</> {:?}", err));
             }
         }
     }
-
     info!("done!")
 }
 
-/// The `add` command
-pub async fn add(slugs: Vec<String>, current_dir: impl AsRef<Path>, config: &mut LocalModsConfig) {
+/// Get the project `slug`, making sure it can be added as `kind`
+async fn project_of_kind(
+    client: &ModrinthClient,
+    slug: &str,
+    kind: Option<ContentKind>,
+) -> Result<Project, anyhow::Error> {
+    let project = client
+        .get_project(slug)
+        .await
+        .context(format!("failed to get project {slug}"))?;
+    match kind {
+        Some(kind) if !kind.accepts(&project) => {
+            anyhow::bail!("{slug} is a {}, not a {kind}", project.project_type)
+        }
+        None if ContentKind::of_project(&project).is_none() => {
+            anyhow::bail!(
+                "{slug} is a {}, which is not supported",
+                project.project_type
+            )
+        }
+        _ => Ok(project),
+    }
+}
+
+/// Add a resource pack, shader pack or datapack, they have no dependencies
+async fn add_content(
+    client: &ModrinthClient,
+    kind: ContentKind,
+    project: &Project,
+    current_dir: &Path,
+    side: Option<Side>,
+    config: &mut LocalModsConfig,
+) -> Result<Version, anyhow::Error> {
+    let version = client
+        .get_latest_version_from_slug(
+            &project.slug,
+            kind.loaders(config.loader),
+            &config.version,
+            config.entry_channel(kind, &project.slug),
+        )
+        .await
+        .context(format!("failed to resolve {}", project.slug))?;
+    if side.is_none_or(|side| config.is_on_side(kind, &project.slug, project, side)) {
+        download_version(&version, kind.dir(current_dir))
+            .await
+            .context(format!("add {kind} {}", project.slug))?;
+    }
+    config.insert_entry(
+        kind,
+        Mod::Modrinth(ModrinthMod {
+            slug: project.slug.clone(),
+            version_id_number: format!("{}#{}", version.id, version.version_number),
+        }),
+    );
+    config.save()?;
+    Ok(version)
+}
+
+/// The `search` command
+///
+/// Only mods matching the `version` and `loader` of mods.toml are shown
+pub async fn search(
+    client: &ModrinthClient,
+    query: String,
+    limit: u32,
+    server: bool,
+    config: &LocalModsConfig,
+) {
     init_logger();
 
-    let current_dir = current_dir.as_ref();
+    let query = SearchQuery {
+        project_type: Some(ProjectType::Mod),
+        versions: vec![config.version.clone()],
+        loaders: config.loader.to_compatible_loaders(),
+        server_side: if server {
+            vec![SideSupport::Required, SideSupport::Optional]
+        } else {
+            vec![]
+        },
+        limit,
+        ..SearchQuery::new(query)
+    };
+    let results = match client.search(&query).await {
+        Ok(results) => results,
+        Err(err) => {
+            info!("{}", cformat!("<r>Failed</> {:?}", err));
+            return;
+        }
+    };
 
-    let slugs: Vec<String> = slugs
-        .into_iter()
-        .collect::<HashSet<_>>()
-        .into_iter()
-        .collect();
-
-    let _config = Arc::new(config.clone());
-    let mut stream = stream::iter(slugs)
-        .map(|slug| {
-            let slug = slug.clone();
-            async {
-                let _slug = slug.clone();
-                add_mod(slug, _config.clone(), current_dir)
+    for hit in &results.hits {
+        let installed = if config.mods.contains_key(&hit.slug) {
+            cformat!(" <g>[installed]</>")
+        } else {
+            String::new()
+        };
+        info!(
+            "{}",
+            cformat!(
+                "<bold>{}</>{} <dim>({}, {} downloads, client: {}, server: {})</>",
+                hit.slug,
+                installed,
+                hit.title,
+                format_count(hit.downloads),
+                hit.client_side,
+                hit.server_side
+            )
+        );
+        info!("  {}", hit.description);
+    }
+    info!(
+        "showing {} of {} results for {} {}",
+        results.hits.len(),
+        results.total_hits,
+        config.loader,
+        config.version
+    );
+}
+
+/// Format a count like `1.2k` or `34.5M`
+fn format_count(count: u64) -> String {
+    match count {
+        0..1_000 => count.to_string(),
+        1_000..1_000_000 => format!("{:.1}k", count as f64 / 1e3),
+        _ => format!("{:.1}M", count as f64 / 1e6),
+    }
+}
+
+// MARK: Dependencies
+
+/// A mod resolved by the [`DependencyResolver`]
+struct ResolvedMod {
+    project: Project,
+    version: Version,
+    /// The mod that pulled this one in, `None` if it was explicitly requested
+    required_by: Option<String>,
+}
+
+/// Walks the Modrinth version dependencies of mods to add, transitively
+struct DependencyResolver<'a> {
+    client: &'a ModrinthClient,
+    loaders: Vec<Loader>,
+    game_version: String,
+    channel: VersionType,
+    /// project id -> slug, for mods in mods.toml or already resolved
+    known: HashMap<String, String>,
+    /// (slug, project id) pairs, for every known mod declaring a project incompatible
+    incompatibilities: Vec<(String, String)>,
+    /// (project id, dependent slug) pairs of optional dependencies not offered yet
+    optional: Vec<(String, String)>,
+}
+
+impl<'a> DependencyResolver<'a> {
+    async fn new(
+        client: &'a ModrinthClient,
+        config: &LocalModsConfig,
+    ) -> Result<Self, anyhow::Error> {
+        let modrinth_mods = config
+            .get_mods()
+            .into_iter()
+            .filter_map(|m| match m {
+                Mod::Modrinth(m) => Some(m),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let versions = client
+            .get_versions(modrinth_mods.iter().map(|m| m.version_id()))
+            .await
+            .context("failed to get versions of mods.toml")?;
+
+        let mut resolver = Self {
+            client,
+            loaders: config.loader.to_compatible_loaders(),
+            game_version: config.version.clone(),
+            channel: config.default_channel(),
+            known: HashMap::new(),
+            incompatibilities: vec![],
+            optional: vec![],
+        };
+        for version in versions {
+            let Some(modrinth_mod) = modrinth_mods.iter().find(|m| m.version_id() == version.id)
+            else {
+                continue;
+            };
+            resolver
+                .record(&modrinth_mod.slug, &version.project_id, &version)
+                .await?;
+        }
+        Ok(resolver)
+    }
+
+    /// Remember a mod as known, along with the projects it is incompatible with
+    async fn record(
+        &mut self,
+        slug: &str,
+        project_id: &str,
+        version: &Version,
+    ) -> Result<(), anyhow::Error> {
+        self.known.insert(project_id.to_string(), slug.to_string());
+        for dependency in &version.dependencies {
+            if dependency.dependency_type == DependencyType::Incompatible
+                && let Some(incompatible) = self.dependency_project_id(dependency).await?
+            {
+                self.incompatibilities
+                    .push((slug.to_string(), incompatible));
+            }
+        }
+        Ok(())
+    }
+
+    /// The project id of a dependency, which may only be given as a version id
+    async fn dependency_project_id(
+        &self,
+        dependency: &Dependency,
+    ) -> Result<Option<String>, anyhow::Error> {
+        match (&dependency.project_id, &dependency.version_id) {
+            (Some(project_id), _) => Ok(Some(project_id.clone())),
+            (None, Some(version_id)) => {
+                Ok(Some(self.client.get_version(version_id).await?.project_id))
+            }
+            (None, None) => Ok(None),
+        }
+    }
+
+    /// Resolve `slug` and its required dependencies that are not known yet
+    ///
+    /// Nothing is resolved if one of them is incompatible with a known mod
+    /// or with another one of them.
+    async fn resolve(
+        &mut self,
+        slug: &str,
+        required_by: Option<String>,
+    ) -> Result<Vec<ResolvedMod>, anyhow::Error> {
+        let mut resolved: Vec<ResolvedMod> = vec![];
+        let mut optional = vec![];
+
+        let mut queue = VecDeque::from([(slug.to_string(), None, required_by)]);
+        while let Some((id_or_slug, version_id, required_by)) = queue.pop_front() {
+            let project = self
+                .client
+                .get_project(&id_or_slug)
+                .await
+                .context(format!("failed to get project {id_or_slug}"))?;
+            if self.known.contains_key(&project.id)
+                || resolved.iter().any(|m| m.project.id == project.id)
+            {
+                continue;
+            }
+
+            let version = match version_id {
+                Some(version_id) => self.client.get_version(version_id).await?,
+                None => self
+                    .client
+                    .get_latest_version_from_slug(
+                        &project.slug,
+                        self.loaders.clone(),
+                        &self.game_version,
+                        self.channel,
+                    )
                     .await
-                    .context(format!("add mod {}", _slug))
+                    .context(match &required_by {
+                        Some(dependent) => {
+                            format!(
+                                "failed to resolve {} (dependency of {dependent})",
+                                project.slug
+                            )
+                        }
+                        None => format!("failed to resolve {}", project.slug),
+                    })?,
+            };
+
+            for dependency in &version.dependencies {
+                let Some(project_id) = self.dependency_project_id(dependency).await? else {
+                    continue;
+                };
+                match dependency.dependency_type {
+                    DependencyType::Required => queue.push_back((
+                        project_id,
+                        dependency.version_id.clone(),
+                        Some(project.slug.clone()),
+                    )),
+                    DependencyType::Optional => optional.push((project_id, project.slug.clone())),
+                    DependencyType::Incompatible | DependencyType::Embedded => (),
+                }
             }
-        })
-        .buffer_unordered(5);
 
-    while let Some(res) = stream.next().await {
-        match res {
-            Ok(res) => match res {
-                AddRes::Added {
-                    slug,
-                    version_id,
-                    version_number,
-                } => {
-                    config.insert_mod(Mod::Modrinth(ModrinthMod {
-                        slug: slug.clone(),
-                        version_id_number: format!("{}#{}", version_id, version_number),
-                    }));
-                    config.save().unwrap();
-                    info!(
-                        "{}",
-                        cformat!("<g>Added</> {} = {}#{}", slug, version_id, version_number)
+            resolved.push(ResolvedMod {
+                project,
+                version,
+                required_by,
+            });
+        }
+
+        self.check_incompatibilities(&resolved).await?;
+        for m in &resolved {
+            self.record(&m.project.slug, &m.project.id, &m.version)
+                .await?;
+        }
+        self.optional.extend(optional);
+        Ok(resolved)
+    }
+
+    async fn check_incompatibilities(&self, resolved: &[ResolvedMod]) -> Result<(), anyhow::Error> {
+        for m in resolved {
+            for dependency in &m.version.dependencies {
+                if dependency.dependency_type != DependencyType::Incompatible {
+                    continue;
+                }
+                let Some(project_id) = self.dependency_project_id(dependency).await? else {
+                    continue;
+                };
+                if let Some(slug) = self.known.get(&project_id) {
+                    anyhow::bail!(
+                        "{} is incompatible with {}, which is already in mods.toml",
+                        m.project.slug,
+                        slug
                     );
                 }
-                AddRes::AlreadyExist {
-                    slug,
-                    version_id,
-                    version_number,
-                } => {
-                    info!(
-                        "{}",
-                        cformat!(
-                            "<y>Already Exist</> {} = {}#{}",
-                            slug,
-                            version_id,
-                            version_number
-                        )
+                if let Some(other) = resolved.iter().find(|other| other.project.id == project_id) {
+                    anyhow::bail!(
+                        "{} is incompatible with {}, which would be added with it",
+                        m.project.slug,
+                        other.project.slug
                     );
                 }
-            },
-            Err(err) => {
-                info!("{}", cformat!("<r>Failed</> {err}"));
             }
         }
+        for (slug, project_id) in &self.incompatibilities {
+            if let Some(m) = resolved.iter().find(|m| &m.project.id == project_id) {
+                anyhow::bail!(
+                    "{} is incompatible with {}, which is already in mods.toml",
+                    m.project.slug,
+                    slug
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Take the optional dependencies that are not known yet, with their dependent
+    async fn take_optional(&mut self) -> Result<Vec<(Project, String)>, anyhow::Error> {
+        let mut seen = HashSet::new();
+        let optional = std::mem::take(&mut self.optional)
+            .into_iter()
+            .filter(|(project_id, _)| {
+                !self.known.contains_key(project_id) && seen.insert(project_id.clone())
+            })
+            .collect::<Vec<_>>();
+
+        let mut projects = self
+            .client
+            .get_projects(optional.iter().map(|(project_id, _)| project_id))
+            .await
+            .context("failed to get optional dependencies")?
+            .into_iter()
+            .map(|p| (p.id.clone(), p))
+            .collect::<HashMap<_, _>>();
+        Ok(optional
+            .into_iter()
+            .filter_map(|(project_id, dependent)| {
+                projects
+                    .remove(&project_id)
+                    .map(|project| (project, dependent))
+            })
+            .collect())
     }
-    info!("done!")
+}
+
+/// Whether `err` is caused by a resource confirmed to not exist
+fn is_not_found(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<ApiError>()
+        .is_some_and(|err| err.is_not_found())
+}
+
+/// Ask a yes/no question, defaulting to no
+///
+/// Returns `false` without asking if stdin is not a terminal
+pub(crate) fn confirm(prompt: impl AsRef<str>) -> bool {
+    use std::io::{self, IsTerminal, Write};
+
+    if !io::stdin().is_terminal() {
+        return false;
+    }
+    print!("{} (y/[n]) ", prompt.as_ref());
+    io::stdout().flush().unwrap();
+
+    let mut input = String::new();
+    io::stdin().read_line(&mut input).unwrap();
+    matches!(input.trim().to_lowercase().as_str(), "y" | "yes")
 }
 
 // MARK: Inner
 
-/// Download a modrinth mod
+/// Download the primary file of a modrinth version
 ///
 /// This function creates a span with spinner
-async fn download_modrinth_mod(
-    modrinth_mod: &ModrinthMod,
-    dir: impl AsRef<Path>,
-) -> Result<(), anyhow::Error> {
-    let span = info_span!(
-        "downloading",
-        slug = modrinth_mod.slug,
-        version_id_number = modrinth_mod.version_id_number
+async fn download_version(version: &Version, dir: impl AsRef<Path>) -> Result<(), anyhow::Error> {
+    let version_file = version.get_primary_file();
+    url::report_mismatch(
+        &dir.as_ref().join(&version_file.filename),
+        &version_file.checksum(),
     );
+    let span = info_span!("downloading", file = version_file.filename);
     span.pb_set_style(
         &ProgressStyle::with_template(
             "{span_child_prefix}{spinner} {span_name:.bold}{{{span_fields}}}:\n  {msg:.dim}",
@@ -370,18 +1285,9 @@ async fn download_modrinth_mod(
         .unwrap(),
     );
     async {
-        let span = Span::current();
-
-        span.pb_set_message("fetching project version...");
-        let version = api::modrinth::get_version(modrinth_mod.version_id()).await?;
-
-        span.pb_set_message("downloading...");
-        let version_file = version.get_primary_file();
         download_version_file(version_file, dir)
             .await
-            .context("failed to download version file")?;
-
-        Ok(())
+            .context("failed to download version file")
     }
     .instrument(span)
     .await
@@ -390,7 +1296,7 @@ async fn download_modrinth_mod(
 /// Remove a file
 ///
 /// This function creates a span with spinner
-fn remove_file(path: impl AsRef<Path>) -> Result<(), anyhow::Error> {
+pub(crate) fn remove_file(path: impl AsRef<Path>) -> Result<(), anyhow::Error> {
     let path = path.as_ref();
     let span = info_span!(
         "removing",
@@ -402,34 +1308,27 @@ fn remove_file(path: impl AsRef<Path>) -> Result<(), anyhow::Error> {
         )
         .unwrap(),
     );
-    span.in_scope(|| fs::remove_file(&path))?;
+    span.in_scope(|| fs::remove_file(path))?;
     Ok(())
 }
 
-#[derive(Debug)]
-enum SyncRes {
-    Skipped,
-    Downloaded(String, String),
-    Removed(PathBuf),
-    Unchanged(String),
+/// A local mod file, with its modrinth version and project if it is known
+pub(crate) struct ResolvedFile {
+    pub path: PathBuf,
+    pub version: Option<(Version, Project)>,
 }
 
-/// Sync a local .jar file with mods.toml
+/// Resolve local files to modrinth versions and projects
 ///
 /// This function creates a span with spinner
 ///
-/// If the file is a modrinth mod file:
-/// - If the version is not match, remove the file and download the new version
-/// - If the file is not in mods.toml, remove it
-///
-/// Else:
-/// - Do nothing
-async fn sync_file(
-    entry: DirEntry,
-    config: &LocalModsConfig,
-    current_dir: impl AsRef<Path>,
-) -> Result<SyncRes, anyhow::Error> {
-    let span = info_span!("syncing", file = entry.file_name().to_str().unwrap());
+/// The versions and projects are fetched with the bulk endpoints, so
+/// this takes two requests however many files there are.
+pub(crate) async fn resolve_files(
+    client: &ModrinthClient,
+    paths: Vec<PathBuf>,
+) -> Result<Vec<ResolvedFile>, anyhow::Error> {
+    let span = info_span!("resolving", files = paths.len());
     span.pb_set_style(
         &ProgressStyle::with_template(
             "{span_child_prefix}{spinner} {span_name:.bold}{{{span_fields}}}:\n  {msg:.dim}",
@@ -438,235 +1337,324 @@ async fn sync_file(
     );
     async {
         let span = Span::current();
-        let path = entry.path();
 
-        span.pb_set_message("calculating sha1 hash...");
-        let _path = path.clone();
-        let hash = smol::unblock(move || get_sha1_hash(_path).unwrap()).await;
+        span.pb_set_message("calculating sha1 hashes...");
+        let hashes = stream::iter(paths)
+            .map(|path| {
+                smol::unblock(move || {
+                    let hash = get_sha1_hash(&path)
+                        .context(format!("failed to hash {}", path.display()))?;
+                    Ok::<_, anyhow::Error>((path, hash))
+                })
+            })
+            .buffer_unordered(8)
+            .try_collect::<Vec<_>>()
+            .await?;
 
-        span.pb_set_message("fetching version...");
-        let version = api::modrinth::get_version_from_hash(&hash, HashMethod::Sha1).await;
-        if version.is_err() {
-            remove_file(&path)?;
-            return Ok(SyncRes::Removed(path));
-        }
-        let version = version.unwrap();
-
-        span.pb_set_message("fetching project...");
-        let project = api::modrinth::get_project(version.project_id).await?;
-
-        if let Some(value) = config.get_mod(&project.slug) {
-            if let Mod::Modrinth(modrinth_mod) = value {
-                if modrinth_mod.version_id() != version.id {
-                    span.pb_set_message("version not match, redownloading...");
-                    remove_file(&path)?;
-                    download_modrinth_mod(&modrinth_mod, &current_dir).await?;
-                    return Ok(SyncRes::Downloaded(project.slug, version.version_number));
-                }
-            }
-            Ok(SyncRes::Unchanged(project.slug))
-        } else {
-            span.pb_set_message("not in mods.toml, removing...");
-            remove_file(&path)?;
-            Ok(SyncRes::Removed(path))
-        }
+        span.pb_set_message("fetching versions...");
+        let mut versions = client
+            .get_versions_from_hashes(hashes.iter().map(|(_, hash)| hash), HashMethod::Sha1)
+            .await
+            .context("failed to get versions from hashes")?;
+
+        span.pb_set_message("fetching projects...");
+        let project_ids = versions
+            .values()
+            .map(|v| v.project_id.clone())
+            .collect::<HashSet<_>>();
+        let projects = client
+            .get_projects(project_ids)
+            .await
+            .context("failed to get projects")?
+            .into_iter()
+            .map(|p| (p.id.clone(), p))
+            .collect::<HashMap<_, _>>();
+
+        hashes
+            .into_iter()
+            .map(|(path, sha1)| {
+                // The request succeeded, so a missing hash is confirmed to be unknown to Modrinth
+                let version = match versions.remove(&sha1) {
+                    Some(version) => {
+                        let project = projects
+                            .get(&version.project_id)
+                            .cloned()
+                            .ok_or(ApiError::NotFound(format!(
+                                "project {}",
+                                version.project_id
+                            )))
+                            .context(format!("failed to resolve {}", path.display()))?;
+                        Some((version, project))
+                    }
+                    None => None,
+                };
+                Ok(ResolvedFile { path, version })
+            })
+            .collect()
     }
     .instrument(span)
     .await
 }
 
-enum UpdateRes {
-    Skipped,
-    Updated {
-        slug: String,
-        version_id: String,
-        version_number: String,
-    },
-    Unchanged {
-        slug: String,
-        version_id: String,
-        version_number: String,
-    },
+/// The files with `extension` directly under `dir`
+pub(crate) fn get_files(dir: &Path, extension: &str) -> Vec<DirEntry> {
+    if !dir.exists() {
+        return vec![];
+    }
+    fs::read_dir(dir)
+        .unwrap()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            entry
+                .path()
+                .extension()
+                .map(|s| s.to_str().unwrap())
+                .unwrap_or("")
+                == extension
+        })
+        .collect::<Vec<DirEntry>>()
 }
 
-async fn update_mod(
-    entry: DirEntry,
-    config: Arc<LocalModsConfig>,
-    current_dir: impl AsRef<Path>,
-) -> Result<UpdateRes, anyhow::Error> {
-    let path = entry.path();
+#[cfg(test)]
+mod test {
+    use async_compat::Compat;
+    use ice_api_tool::mock::{MockResponse, MockServer};
+    use serde_json::json;
 
-    let span = info_span!(
-        "updating",
-        file = path.file_name().unwrap().to_str().unwrap()
-    );
-    span.pb_set_style(
-        &ProgressStyle::with_template(
-            "{span_child_prefix}{spinner} {span_name:.bold}{{{span_fields}}}:\n  {msg:.dim}",
-        )
-        .unwrap(),
-    );
+    use super::*;
 
-    async {
-        let span = Span::current();
-        let path = entry.path();
-
-        span.pb_set_message("calculating sha1 hash...");
-        let _path = path.clone();
-        let hash = smol::unblock(move || get_sha1_hash(_path).unwrap()).await;
-
-        let cur_version = api::modrinth::get_version_from_hash(&hash, HashMethod::Sha1).await;
-        if cur_version.is_err() {
-            return Ok(UpdateRes::Skipped);
+    /// The routes of projects `a`, `b` and `c`, each with one version whose
+    /// dependencies are given as `(dependency type, project id)` pairs
+    fn routes(dependencies: [&[(&str, &str)]; 3]) -> Vec<(String, MockResponse)> {
+        let mut routes = vec![];
+        for (id, dependencies) in ["a", "b", "c"].into_iter().zip(dependencies) {
+            let project = json!({
+                "id": id,
+                "slug": id,
+                "title": id,
+                "description": "",
+                "project_type": "mod",
+                "client_side": "required",
+                "server_side": "required",
+                "license": { "id": "MIT", "name": "MIT License", "url": null },
+                "categories": [],
+                "game_versions": ["1.21.6"],
+                "loaders": ["fabric"],
+                "versions": [format!("{id}1")],
+                "downloads": 0
+            });
+            let dependencies = dependencies
+                .iter()
+                .map(|(dependency_type, project_id)| {
+                    json!({
+                        "version_id": null,
+                        "project_id": project_id,
+                        "file_name": null,
+                        "dependency_type": dependency_type
+                    })
+                })
+                .collect::<Vec<_>>();
+            let version = json!({
+                "name": id,
+                "version_number": "1.0",
+                "changelog": null,
+                "dependencies": dependencies,
+                "game_versions": ["1.21.6"],
+                "version_type": "release",
+                "loaders": ["fabric"],
+                "featured": false,
+                "id": format!("{id}1"),
+                "project_id": id,
+                "date_published": "2025-01-01T00:00:00Z",
+                "downloads": 0,
+                "files": []
+            });
+            routes.push((format!("GET /v2/project/{id}"), project.to_string()));
+            routes.push((
+                format!("GET /v2/project/{id}/version"),
+                json!([version]).to_string(),
+            ));
+            routes.push((format!("GET /v2/version/{id}1"), version.to_string()));
         }
-        let cur_version = cur_version.unwrap();
-
-        span.pb_set_message("fetching project...");
-        let project = api::modrinth::get_project(cur_version.project_id).await?;
-
-        let loaders = config.loader.to_compatible_loaders();
-        let game_version = config.version.clone();
-
-        span.pb_set_message("fetching latest version...");
-        let version = api::modrinth::get_latest_version_from_hash(
-            &hash,
-            HashMethod::Sha1,
-            &loaders,
-            game_version.clone(),
-        )
-        .await?;
+        routes
+            .into_iter()
+            .map(|(route, body)| (route, MockResponse::json(body)))
+            .collect()
+    }
 
-        if version.id == cur_version.id {
-            return Ok(UpdateRes::Unchanged {
-                slug: project.slug,
-                version_id: cur_version.id,
-                version_number: cur_version.version_number,
-            });
+    /// Resolve `a` with the given projects and `pinned` in mods.toml
+    fn resolve_a(
+        dependencies: [&[(&str, &str)]; 3],
+        pinned: &[&str],
+    ) -> Result<Vec<(String, Option<String>)>, anyhow::Error> {
+        let mut routes = routes(dependencies);
+        // mods.toml is resolved from its pins in one request
+        let versions = routes
+            .iter()
+            .filter(|(route, _)| {
+                pinned
+                    .iter()
+                    .any(|id| *route == format!("GET /v2/version/{id}1"))
+            })
+            .map(|(_, response)| response.body.clone())
+            .collect::<Vec<_>>();
+        routes.push((
+            "GET /v2/versions".to_string(),
+            MockResponse::json(format!("[{}]", versions.join(","))),
+        ));
+        let server = MockServer::route(
+            routes
+                .iter()
+                .map(|(route, response)| (route.as_str(), response.clone()))
+                .collect(),
+        );
+        let client = ModrinthClient::new().with_base_url(format!("{}/v2", server.url));
+        let mut config =
+            LocalModsConfig::new("1.21.6".to_string(), ServerLoader::Fabric, "mods.toml");
+        for id in pinned {
+            config.insert_entry(
+                ContentKind::Mod,
+                Mod::Modrinth(ModrinthMod {
+                    slug: id.to_string(),
+                    version_id_number: format!("{id}1#1.0"),
+                }),
+            );
         }
 
-        span.pb_set_message("downloading...");
-        let version_file = version.get_primary_file();
-        download_version_file(version_file, current_dir).await?;
-        span.pb_set_message("removing old file...");
-        remove_file(path)?;
-        Ok(UpdateRes::Updated {
-            slug: project.slug,
-            version_id: version.id,
-            version_number: version.version_number,
-        })
+        smol::block_on(Compat::new(async {
+            let mut resolver = DependencyResolver::new(&client, &config).await?;
+            let resolved = resolver.resolve("a", None).await?;
+            Ok(resolved
+                .into_iter()
+                .map(|m| (m.project.slug, m.required_by))
+                .collect())
+        }))
     }
-    .instrument(span)
-    .await
-}
 
-enum AddRes {
-    Added {
-        slug: String,
-        version_id: String,
-        version_number: String,
-    },
-    AlreadyExist {
-        slug: String,
-        version_id: String,
-        version_number: String,
-    },
-}
+    #[test]
+    fn test_resolve_transitive_dependencies() {
+        let resolved = resolve_a(
+            [
+                &[("required", "b")],
+                &[("required", "c")],
+                &[("optional", "a")],
+            ],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(
+            resolved,
+            [
+                ("a".to_string(), None),
+                ("b".to_string(), Some("a".to_string())),
+                ("c".to_string(), Some("b".to_string())),
+            ]
+        );
 
-fn ice_core_loader_to_modrinth_loader(
-    value: ice_core::ServerLoader,
-) -> ice_api_tool::modrinth::types::Loader {
-    match value {
-        ice_core::ServerLoader::Fabric => ice_api_tool::modrinth::types::Loader::Fabric,
-        ice_core::ServerLoader::Quilt => ice_api_tool::modrinth::types::Loader::Quilt,
-        ice_core::ServerLoader::NeoForge => ice_api_tool::modrinth::types::Loader::NeoForge,
+        // The dependencies already in mods.toml are not added again
+        let resolved = resolve_a([&[("required", "b")], &[("required", "c")], &[]], &["c"]);
+        assert_eq!(
+            resolved.unwrap(),
+            [
+                ("a".to_string(), None),
+                ("b".to_string(), Some("a".to_string())),
+            ]
+        );
     }
-}
 
-async fn add_mod(
-    slug: impl AsRef<str>,
-    config: Arc<LocalModsConfig>,
-    current_dir: impl AsRef<Path>,
-) -> Result<AddRes, anyhow::Error> {
-    let slug = slug.as_ref().to_string();
+    #[test]
+    fn test_resolve_incompatible() {
+        // b, pulled in by a, is incompatible with c in mods.toml
+        let err = resolve_a(
+            [&[("required", "b")], &[("incompatible", "c")], &[]],
+            &["c"],
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "b is incompatible with c, which is already in mods.toml"
+        );
 
-    let span = info_span!("adding", slug = slug);
-    span.pb_set_style(
-        &ProgressStyle::with_template(
-            "{span_child_prefix}{spinner} {span_name:.bold}{{{span_fields}}}:\n  {msg:.dim}",
+        // c in mods.toml declares b incompatible
+        let err = resolve_a(
+            [&[("required", "b")], &[], &[("incompatible", "b")]],
+            &["c"],
         )
-        .unwrap(),
-    );
-    async {
-        let span = Span::current();
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "b is incompatible with c, which is already in mods.toml"
+        );
+    }
 
-        if let Some(value) = config.get_mod(&slug) {
-            if let Mod::Modrinth(modrinth_mod) = value {
-                return Ok(AddRes::AlreadyExist {
-                    slug: modrinth_mod.slug.clone(),
-                    version_number: modrinth_mod.version_number().to_string(),
-                    version_id: modrinth_mod.version_id().to_string(),
-                });
-            }
-        }
+    #[test]
+    fn test_resolve_cycle() {
+        let resolved = resolve_a([&[("required", "b")], &[("required", "a")], &[]], &[]);
+        assert_eq!(
+            resolved.unwrap(),
+            [
+                ("a".to_string(), None),
+                ("b".to_string(), Some("a".to_string())),
+            ]
+        );
+    }
 
-        let game_version = config.version.clone();
-        let loader = ice_core_loader_to_modrinth_loader(config.loader);
+    #[test]
+    fn test_pin_constraints() {
+        let version = |id: &str, number: &str, game_version: &str, date: &str| {
+            json!({
+                "name": number,
+                "version_number": number,
+                "changelog": null,
+                "dependencies": [],
+                "game_versions": [game_version],
+                "version_type": "release",
+                "loaders": ["fabric"],
+                "featured": false,
+                "id": id,
+                "project_id": "sodium",
+                "date_published": date,
+                "downloads": 0,
+                "files": []
+            })
+        };
+        let versions = json!([
+            version("s1", "mc1.21.6-0.5.11", "1.21.6", "2025-01-01T00:00:00Z"),
+            version("s2", "mc1.21.6-0.6.0", "1.21.6", "2025-02-01T00:00:00Z"),
+            version("s3", "mc1.21.5-0.5.12", "1.21.5", "2025-03-01T00:00:00Z"),
+        ]);
+        let server = MockServer::route(vec![
+            (
+                "GET /v2/project/sodium/version",
+                MockResponse::json(versions.to_string()),
+            ),
+            (
+                "GET /v2/project/iris/version",
+                MockResponse::json(versions.to_string()),
+            ),
+        ]);
+        let client = ModrinthClient::new().with_base_url(format!("{}/v2", server.url));
 
-        span.pb_set_message("fetching latest version...");
-        let version =
-            get_latest_version_from_slug(&slug, vec![loader], game_version.clone()).await?;
+        let dir = crate::cli::temp_dir("pin");
+        fs::write(
+            dir.join("mods.toml"),
+            "version = \"1.21.6\"\nloader = \"fabric\"\n\n[mods]\nsodium = \"^0.5\"\niris = \"~1.8\"\n",
+        )
+        .unwrap();
+        let mut config = LocalModsConfig::load(dir.join("mods.toml")).unwrap();
+        smol::block_on(Compat::new(pin_constraints(&client, true, &mut config)));
 
-        span.pb_set_message("downloading...");
-        let version_file = version.get_primary_file();
-        download_version_file(version_file, current_dir).await?;
-        Ok(AddRes::Added {
-            slug,
-            version_number: version.version_number,
-            version_id: version.id,
-        })
-    }
-    .instrument(span)
-    .await
-}
+        // 0.5.12 is not for 1.21.6, and no version is in `~1.8`
+        assert_eq!(
+            fs::read_to_string(dir.join("mods.toml")).unwrap(),
+            r#"version = "1.21.6"
+loader = "fabric"
 
-fn get_jar_files(dir: &Path) -> Vec<DirEntry> {
-    if !dir.exists() {
-        return vec![];
+[mods]
+sodium = { version-id-number = "s1#mc1.21.6-0.5.11", version = "^0.5" }
+iris = "~1.8"
+"#
+        );
+        let _ = fs::remove_dir_all(&dir);
     }
-    fs::read_dir(dir)
-        .unwrap()
-        .filter_map(|entry| entry.ok())
-        .filter(|entry| {
-            entry
-                .path()
-                .extension()
-                .map(|s| s.to_str().unwrap())
-                .unwrap_or("")
-                == "jar"
-        })
-        .collect::<Vec<DirEntry>>()
 }
-
-// async fn get_modrinth_files(dir: &Path) -> Vec<(DirEntry, String, Version)> {
-//     //? Get modrinth files
-//     let jar_files = get_jar_files(dir);
-//     let mut join_set = JoinSet::new();
-//     for f in jar_files {
-//         join_set.spawn(async {
-//             let path = f.path();
-//             let hash = tokio::task::spawn_blocking(|| get_sha1_hash(path).unwrap())
-//                 .await
-//                 .unwrap();
-//             let version = api::modrinth::get_version_from_hash(&hash, HashMethod::Sha1).await;
-//             version.map(|v| (f, hash, v)).ok()
-//         });
-//     }
-//     let mut modrinth_files = vec![];
-//     while let Some(res) = join_set.join_next().await {
-//         let res = res.unwrap();
-//         if let Some(res) = res {
-//             modrinth_files.push(res)
-//         }
-//     }
-//     modrinth_files
-// }
@@ -0,0 +1,770 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::{self, File},
+    io::{Read, Write},
+    path::{Component, Path, PathBuf},
+};
+
+use anyhow::{Context, bail};
+use color_print::cformat;
+use ice::{
+    config::LocalModsConfig,
+    core::{ContentKind, FileHash, Mod, ModrinthMod, Side, UrlMod, url_filename},
+};
+use ice_api_tool::{
+    self as api,
+    modrinth::{
+        HashMethod, ModrinthClient,
+        mrpack::{self, Env, Index, IndexFile},
+        types::{SideSupport, VersionFileHashes},
+    },
+};
+use ice_core::ServerLoader;
+use ice_util::fs::{get_sha1_hash, get_sha512_hash};
+use tracing::info;
+use zip::{CompressionMethod, ZipArchive, ZipWriter, write::SimpleFileOptions};
+
+use super::{modrinth::init_logger, url::RemoteFile};
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// Modrinth modpack
+    Mrpack,
+}
+
+/// The key of `loader` in the `dependencies` of a `.mrpack`
+fn mrpack_loader_key(loader: ServerLoader) -> &'static str {
+    match loader {
+        ServerLoader::Fabric => "fabric-loader",
+        ServerLoader::Quilt => "quilt-loader",
+        ServerLoader::NeoForge => "neoforge",
+    }
+}
+
+/// The game version and loader a `.mrpack` depends on
+fn mrpack_target(
+    dependencies: &BTreeMap<String, String>,
+) -> anyhow::Result<(String, ServerLoader)> {
+    let version = dependencies
+        .get("minecraft")
+        .context("the modpack does not depend on minecraft")?
+        .clone();
+    let loader = [
+        ServerLoader::Fabric,
+        ServerLoader::Quilt,
+        ServerLoader::NeoForge,
+    ]
+    .into_iter()
+    .find(|loader| dependencies.contains_key(mrpack_loader_key(*loader)));
+    match loader {
+        Some(loader) => Ok((version, loader)),
+        None if dependencies.contains_key("forge") => bail!("forge is not supported"),
+        None => bail!("the modpack does not depend on a supported loader"),
+    }
+}
+
+async fn get_latest_loader_version(
+    loader: ServerLoader,
+    game_version: &str,
+) -> Result<String, api::error::ApiError> {
+    match loader {
+        ServerLoader::Fabric => api::fabric::get_latest_loader_version(game_version).await,
+        ServerLoader::Quilt => api::quilt::get_latest_loader_version(game_version).await,
+        ServerLoader::NeoForge => api::neoforge::get_latest_version(game_version).await,
+    }
+}
+
+/// The options of the `export` command
+pub struct ExportOptions {
+    pub format: ExportFormat,
+    pub output: Option<PathBuf>,
+    pub name: Option<String>,
+    pub pack_version: String,
+    pub loader_version: Option<String>,
+}
+
+/// The `export` command
+///
+/// The Modrinth entries are exported with their files on Modrinth and the url
+/// entries with their synced files, the other sources are skipped. The
+/// `overrides/`, `client-overrides/` and `server-overrides/` folders next to
+/// mods.toml are packed as is.
+pub async fn export(
+    client: &ModrinthClient,
+    options: ExportOptions,
+    current_dir: impl AsRef<Path>,
+    config: &LocalModsConfig,
+) {
+    init_logger();
+    let current_dir = current_dir.as_ref();
+
+    match options.format {
+        ExportFormat::Mrpack => match export_mrpack(client, options, current_dir, config).await {
+            Ok(path) => info!("{}", cformat!("<g>Exported</> {}", path.display())),
+            Err(err) => info!("{}", cformat!("<r>Failed</> {:?}", err)),
+        },
+    }
+}
+
+async fn export_mrpack(
+    client: &ModrinthClient,
+    options: ExportOptions,
+    current_dir: &Path,
+    config: &LocalModsConfig,
+) -> anyhow::Result<PathBuf> {
+    if !config.get_entries(ContentKind::Datapack).is_empty() {
+        info!(
+            "{}",
+            cformat!("<y>Skipped</> datapacks (not supported by .mrpack)")
+        );
+    }
+    let mut mods = vec![];
+    let mut url_files = vec![];
+    for kind in config.kinds() {
+        if kind == ContentKind::Datapack {
+            continue;
+        }
+        for m in config.get_entries(kind) {
+            let (slug, reason) = match m {
+                Mod::Modrinth(m) => {
+                    mods.push((kind, m));
+                    continue;
+                }
+                Mod::Url(m) => match url_index_file(kind, &m, current_dir, config) {
+                    Some(file) => {
+                        url_files.push(file);
+                        continue;
+                    }
+                    None => (m.slug, "its file is not synced, run `ice mod sync` first"),
+                },
+                Mod::CurseForge(m) => (m.slug, "CurseForge entries are not supported by .mrpack"),
+                Mod::GitHub(m) => (m.slug, "GitHub entries are not supported by .mrpack"),
+                Mod::Maven(m) => (m.slug, "Maven entries are not supported by .mrpack"),
+                Mod::Local(m) => (m.slug, "local entries are not supported by .mrpack"),
+                _ => continue,
+            };
+            info!("{}", cformat!("<y>Skipped</> {} ({})", slug, reason));
+        }
+    }
+
+    info!("resolving {} files...", mods.len());
+    let versions = client
+        .get_versions(mods.iter().map(|(_, m)| m.version_id()))
+        .await
+        .context("failed to get versions")?
+        .into_iter()
+        .map(|v| (v.id.clone(), v))
+        .collect::<HashMap<_, _>>();
+    let projects = client
+        .get_projects(versions.values().map(|v| v.project_id.clone()))
+        .await
+        .context("failed to get projects")?
+        .into_iter()
+        .map(|p| (p.id.clone(), p))
+        .collect::<HashMap<_, _>>();
+
+    let mut files = vec![];
+    for (kind, m) in &mods {
+        let Some(version) = versions.get(m.version_id()) else {
+            bail!("version {} of {} not found", m.version_id(), m.slug);
+        };
+        let Some(project) = projects.get(&version.project_id) else {
+            bail!("project {} of {} not found", version.project_id, m.slug);
+        };
+        let file = version.get_primary_file();
+        files.push(IndexFile {
+            path: format!("{}/{}", kind.table(), file.filename),
+            hashes: file.hashes.clone(),
+            env: Some(Env {
+                client: mrpack_side(project.client_side),
+                server: mrpack_side(project.server_side),
+            }),
+            downloads: vec![file.url.clone()],
+            file_size: file.size as u64,
+        });
+    }
+    files.extend(url_files);
+
+    let loader_version = match options.loader_version {
+        Some(loader_version) => loader_version,
+        None => get_latest_loader_version(config.loader, &config.version)
+            .await
+            .with_context(|| format!("failed to get the latest {} version", config.loader))?,
+    };
+    let dependencies = BTreeMap::from([
+        ("minecraft".to_string(), config.version.clone()),
+        (mrpack_loader_key(config.loader).to_string(), loader_version),
+    ]);
+
+    let name = options.name.unwrap_or_else(|| {
+        current_dir
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or("ice".to_string())
+    });
+    let output = options
+        .output
+        .unwrap_or_else(|| current_dir.join(format!("{}-{}.mrpack", name, options.pack_version)));
+    let index = Index {
+        format_version: 1,
+        game: "minecraft".to_string(),
+        version_id: options.pack_version,
+        name,
+        summary: None,
+        files,
+        dependencies,
+    };
+
+    let current_dir = current_dir.to_path_buf();
+    let path = output.clone();
+    smol::unblock(move || write_mrpack(&path, &index, &current_dir))
+        .await
+        .with_context(|| format!("failed to write {}", output.display()))?;
+    Ok(output)
+}
+
+/// The file of the url entry `m` in a `.mrpack`
+///
+/// The index needs both hashes and the size, so they are taken from the
+/// synced file once it matches the pinned hash, `None` if it does not.
+fn url_index_file(
+    kind: ContentKind,
+    m: &UrlMod,
+    current_dir: &Path,
+    config: &LocalModsConfig,
+) -> Option<IndexFile> {
+    let path = kind.dir(current_dir).join(m.filename());
+    let checksum = RemoteFile::from(m.clone()).checksum();
+    if !matches!(checksum.mismatch(&path), Ok(None)) {
+        return None;
+    }
+    let side = |side| match config.is_entry_on_side(kind, &m.slug, side) {
+        true => SideSupport::Required,
+        false => SideSupport::Unsupported,
+    };
+    Some(IndexFile {
+        path: format!("{}/{}", kind.table(), m.filename()),
+        hashes: VersionFileHashes {
+            sha1: get_sha1_hash(&path).ok()?,
+            sha512: get_sha512_hash(&path).ok()?,
+        },
+        env: Some(Env {
+            client: side(Side::Client),
+            server: side(Side::Server),
+        }),
+        downloads: vec![m.url.clone()],
+        file_size: fs::metadata(&path).ok()?.len(),
+    })
+}
+
+/// Modrinth doesn't know the side of some projects, they are kept everywhere
+fn mrpack_side(side: SideSupport) -> SideSupport {
+    match side {
+        SideSupport::Unknown => SideSupport::Required,
+        side => side,
+    }
+}
+
+fn write_mrpack(path: &Path, index: &Index, overrides_root: &Path) -> anyhow::Result<()> {
+    let mut zip = ZipWriter::new(File::create(path)?);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    zip.start_file(mrpack::INDEX_PATH, options)?;
+    zip.write_all(serde_json::to_string_pretty(index)?.as_bytes())?;
+
+    for overrides in [
+        mrpack::OVERRIDES,
+        mrpack::CLIENT_OVERRIDES,
+        mrpack::SERVER_OVERRIDES,
+    ] {
+        let dir = overrides_root.join(overrides);
+        if dir.is_dir() {
+            add_dir(&mut zip, &dir, overrides, options)?;
+        }
+    }
+
+    zip.finish()?;
+    Ok(())
+}
+
+/// Add the content of `dir` to `zip` under `prefix`, recursively
+fn add_dir(
+    zip: &mut ZipWriter<File>,
+    dir: &Path,
+    prefix: &str,
+    options: SimpleFileOptions,
+) -> anyhow::Result<()> {
+    let mut entries = fs::read_dir(dir)?.collect::<Result<Vec<_>, _>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let name = format!("{prefix}/{}", entry.file_name().to_string_lossy());
+        if entry.file_type()?.is_dir() {
+            zip.add_directory(&name, options)?;
+            add_dir(zip, &entry.path(), &name, options)?;
+        } else {
+            zip.start_file(&name, options)?;
+            zip.write_all(&fs::read(entry.path())?)?;
+        }
+    }
+    Ok(())
+}
+
+/// The kind of a file in a `.mrpack` by its path, `None` if it is not managed
+fn content_kind_of_path(path: &str) -> Option<ContentKind> {
+    [
+        ContentKind::Mod,
+        ContentKind::ResourcePack,
+        ContentKind::ShaderPack,
+    ]
+    .into_iter()
+    .find(|kind| {
+        path.strip_prefix(kind.table())
+            .and_then(|path| path.strip_prefix('/'))
+            .is_some_and(|name| {
+                !name.contains('/') && name.ends_with(&format!(".{}", kind.extension()))
+            })
+    })
+}
+
+/// The content of a `.mrpack` used by the server
+struct Mrpack {
+    index: Index,
+    /// `overrides/` and then `server-overrides/`, relative to the instance
+    overrides: Vec<(PathBuf, Vec<u8>)>,
+}
+
+fn read_mrpack(path: &Path) -> anyhow::Result<Mrpack> {
+    let mut zip = ZipArchive::new(File::open(path)?)?;
+
+    let index = zip
+        .by_name(mrpack::INDEX_PATH)
+        .with_context(|| format!("{} not found", mrpack::INDEX_PATH))?;
+    let index = serde_json::from_reader::<_, Index>(index)
+        .with_context(|| format!("failed to parse {}", mrpack::INDEX_PATH))?;
+
+    let mut overrides = vec![];
+    for prefix in [mrpack::OVERRIDES, mrpack::SERVER_OVERRIDES] {
+        for i in 0..zip.len() {
+            let mut file = zip.by_index(i)?;
+            if file.is_dir() {
+                continue;
+            }
+            let Some(path) = file
+                .enclosed_name()
+                .and_then(|path| path.strip_prefix(prefix).ok().map(Path::to_path_buf))
+            else {
+                continue;
+            };
+            // Nothing may escape the instance, like `overrides/../foo`
+            if !path.components().all(|c| matches!(c, Component::Normal(_))) {
+                bail!("invalid path in the modpack: {}", file.name());
+            }
+            let mut content = vec![];
+            file.read_to_end(&mut content)?;
+            overrides.push((path, content));
+        }
+    }
+
+    Ok(Mrpack { index, overrides })
+}
+
+/// Add a file of a `.mrpack` not found on Modrinth as a url entry pinned by
+/// its sha512, keyed by its file name without the extension
+fn import_url_file(kind: ContentKind, file: &IndexFile, config: &mut LocalModsConfig) {
+    let Some(url) = file.downloads.first() else {
+        info!(
+            "{}",
+            cformat!("<y>Skipped</> {} (no download url)", file.path)
+        );
+        return;
+    };
+    let filename = file.path.rsplit('/').next().unwrap_or(&file.path);
+    let slug = Path::new(filename)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or(filename.to_string());
+    if config.get_entry(kind, &slug).is_some() {
+        info!(
+            "{}",
+            cformat!(
+                "<y>Skipped</> {} ({} is already in mods.toml)",
+                file.path,
+                slug
+            )
+        );
+        return;
+    }
+    config.insert_entry(
+        kind,
+        Mod::Url(UrlMod {
+            slug: slug.clone(),
+            url: url.clone(),
+            hash: FileHash::Sha512(file.hashes.sha512.clone()),
+            filename: (url_filename(url) != filename).then(|| filename.to_string()),
+        }),
+    );
+    info!(
+        "{}",
+        cformat!(
+            "<g>Added</> {} {} = {} (not found on Modrinth)",
+            kind,
+            slug,
+            url
+        )
+    );
+}
+
+/// The `import` command
+///
+/// Creates mods.toml under `current_dir` from a `.mrpack`, the overrides are
+/// extracted into `server_dir`, the parent of `current_dir` by default. The
+/// files not found on Modrinth are added as url entries.
+pub async fn import(
+    client: &ModrinthClient,
+    file: PathBuf,
+    server_dir: Option<PathBuf>,
+    current_dir: impl AsRef<Path>,
+) {
+    init_logger();
+    let current_dir = current_dir.as_ref();
+
+    let config_path = current_dir.join("mods.toml");
+    if config_path.exists() {
+        println!("mods.toml is already exists!");
+        return;
+    }
+    let server_dir =
+        server_dir.unwrap_or_else(|| current_dir.parent().unwrap_or(current_dir).to_path_buf());
+
+    if let Err(err) = import_mrpack(client, &file, &server_dir, &config_path).await {
+        info!("{}", cformat!("<r>Failed</> {:?}", err));
+    }
+}
+
+async fn import_mrpack(
+    client: &ModrinthClient,
+    file: &Path,
+    server_dir: &Path,
+    config_path: &Path,
+) -> anyhow::Result<()> {
+    let path = file.to_path_buf();
+    let pack = smol::unblock(move || read_mrpack(&path))
+        .await
+        .with_context(|| format!("failed to read {}", file.display()))?;
+    let (version, loader) = mrpack_target(&pack.index.dependencies)?;
+    info!(
+        "importing {} {} ({} {})",
+        pack.index.name, pack.index.version_id, loader, version
+    );
+
+    let mut mods = vec![];
+    for file in &pack.index.files {
+        if file
+            .env
+            .is_some_and(|env| env.server == SideSupport::Unsupported)
+        {
+            info!(
+                "{}",
+                cformat!("<dim>Skipped</> {} (client only)", file.path)
+            );
+        } else if let Some(kind) = content_kind_of_path(&file.path) {
+            mods.push((kind, file));
+        } else {
+            info!(
+                "{}",
+                cformat!("<y>Skipped</> {} (not managed by mods.toml)", file.path)
+            );
+        }
+    }
+
+    let versions = client
+        .get_versions_from_hashes(mods.iter().map(|(_, f)| &f.hashes.sha1), HashMethod::Sha1)
+        .await
+        .context("failed to get versions")?;
+    let projects = client
+        .get_projects(versions.values().map(|v| v.project_id.clone()))
+        .await
+        .context("failed to get projects")?
+        .into_iter()
+        .map(|p| (p.id.clone(), p))
+        .collect::<HashMap<_, _>>();
+
+    let mut config = LocalModsConfig::new(version, loader, config_path);
+    for (kind, file) in mods {
+        let Some((version, project)) = versions
+            .get(&file.hashes.sha1)
+            .and_then(|v| Some((v, projects.get(&v.project_id)?)))
+        else {
+            import_url_file(kind, file, &mut config);
+            continue;
+        };
+        config.insert_entry(
+            kind,
+            Mod::Modrinth(ModrinthMod {
+                slug: project.slug.clone(),
+                version_id_number: format!("{}#{}", version.id, version.version_number),
+            }),
+        );
+        info!(
+            "{}",
+            cformat!(
+                "<g>Added</> {} {} = {}#{}",
+                kind,
+                project.slug,
+                version.id,
+                version.version_number
+            )
+        );
+    }
+    config.save().context("failed to save mods.toml")?;
+
+    for (path, content) in &pack.overrides {
+        if path.starts_with("mods") {
+            info!(
+                "{}",
+                cformat!(
+                    "<y>Warning</> {} is not tracked by mods.toml",
+                    path.display()
+                )
+            );
+        }
+        let path = server_dir.join(path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, content).with_context(|| format!("failed to write {}", path.display()))?;
+    }
+    if !pack.overrides.is_empty() {
+        info!(
+            "extracted {} override files into {}",
+            pack.overrides.len(),
+            server_dir.display()
+        );
+    }
+
+    info!("done! run `ice mod sync` to download them");
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use async_compat::Compat;
+    use ice_api_tool::mock::{MockResponse, MockServer};
+
+    use super::*;
+    use crate::cli::temp_dir;
+
+    fn index(files: Vec<IndexFile>) -> Index {
+        Index {
+            format_version: 1,
+            game: "minecraft".to_string(),
+            version_id: "1.0.0".to_string(),
+            name: "pack".to_string(),
+            summary: None,
+            files,
+            dependencies: BTreeMap::from([
+                ("minecraft".to_string(), "1.21.6".to_string()),
+                ("fabric-loader".to_string(), "0.16.14".to_string()),
+            ]),
+        }
+    }
+
+    #[test]
+    fn test_mrpack_target() {
+        let dependencies = |keys: &[&str]| {
+            keys.iter()
+                .map(|key| (key.to_string(), "1.21.6".to_string()))
+                .collect::<BTreeMap<_, _>>()
+        };
+        let target = mrpack_target(&dependencies(&["minecraft", "quilt-loader"])).unwrap();
+        assert_eq!(target, ("1.21.6".to_string(), ServerLoader::Quilt));
+        let target = mrpack_target(&dependencies(&["minecraft", "neoforge"])).unwrap();
+        assert_eq!(target, ("1.21.6".to_string(), ServerLoader::NeoForge));
+
+        let err = mrpack_target(&dependencies(&["minecraft", "forge"])).unwrap_err();
+        assert_eq!(err.to_string(), "forge is not supported");
+        assert!(mrpack_target(&dependencies(&["minecraft"])).is_err());
+        assert!(mrpack_target(&dependencies(&["fabric-loader"])).is_err());
+    }
+
+    #[test]
+    fn test_content_kind_of_path() {
+        assert_eq!(
+            content_kind_of_path("mods/sodium.jar"),
+            Some(ContentKind::Mod)
+        );
+        assert_eq!(
+            content_kind_of_path("resourcepacks/faithful.zip"),
+            Some(ContentKind::ResourcePack)
+        );
+        assert_eq!(
+            content_kind_of_path("shaderpacks/complementary.zip"),
+            Some(ContentKind::ShaderPack)
+        );
+        assert_eq!(content_kind_of_path("mods/sodium.zip"), None);
+        assert_eq!(content_kind_of_path("mods/nested/sodium.jar"), None);
+        assert_eq!(content_kind_of_path("modsodium.jar"), None);
+        assert_eq!(content_kind_of_path("config/sodium.jar"), None);
+    }
+
+    #[test]
+    fn test_write_read_mrpack() {
+        let dir = temp_dir("mrpack-round-trip");
+        fs::create_dir_all(dir.join("overrides/config")).unwrap();
+        fs::write(dir.join("overrides/config/a.toml"), "a").unwrap();
+        fs::create_dir_all(dir.join("server-overrides")).unwrap();
+        fs::write(dir.join("server-overrides/server.properties"), "b").unwrap();
+        fs::create_dir_all(dir.join("client-overrides")).unwrap();
+        fs::write(dir.join("client-overrides/options.txt"), "c").unwrap();
+        let file = IndexFile {
+            path: "mods/sodium.jar".to_string(),
+            hashes: VersionFileHashes {
+                sha1: "abc".to_string(),
+                sha512: "def".to_string(),
+            },
+            env: Some(Env {
+                client: SideSupport::Required,
+                server: SideSupport::Unsupported,
+            }),
+            downloads: vec!["https://cdn.modrinth.com/sodium.jar".to_string()],
+            file_size: 3,
+        };
+
+        let path = dir.join("pack.mrpack");
+        let index = index(vec![file]);
+        write_mrpack(&path, &index, &dir).unwrap();
+        let pack = read_mrpack(&path).unwrap();
+        assert_eq!(
+            serde_json::to_value(&pack.index).unwrap(),
+            serde_json::to_value(&index).unwrap()
+        );
+        // The client overrides are not extracted on the server
+        assert_eq!(
+            pack.overrides,
+            [
+                (PathBuf::from("config/a.toml"), b"a".to_vec()),
+                (PathBuf::from("server.properties"), b"b".to_vec()),
+            ]
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_read_mrpack_rejects_escaping_paths() {
+        let dir = temp_dir("mrpack-escape");
+        let path = dir.join("pack.mrpack");
+        let mut zip = ZipWriter::new(File::create(&path).unwrap());
+        let options = SimpleFileOptions::default();
+        zip.start_file(mrpack::INDEX_PATH, options).unwrap();
+        zip.write_all(serde_json::to_string(&index(vec![])).unwrap().as_bytes())
+            .unwrap();
+        zip.start_file("overrides/../x", options).unwrap();
+        zip.write_all(b"x").unwrap();
+        zip.finish().unwrap();
+
+        let err = read_mrpack(&path).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "invalid path in the modpack: overrides/../x"
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_url_index_file() {
+        let dir = temp_dir("mrpack-url");
+        fs::write(dir.join("foo.jar"), "foo").unwrap();
+        let config_path = dir.join("mods.toml");
+        fs::write(
+            &config_path,
+            "version = \"1.21.6\"\nloader = \"fabric\"\n\n[mods]\n",
+        )
+        .unwrap();
+        let config = LocalModsConfig::load(&config_path).unwrap();
+        let sha1 = get_sha1_hash(dir.join("foo.jar")).unwrap();
+        let m = UrlMod {
+            slug: "foo".to_string(),
+            url: "https://example.com/foo.jar".to_string(),
+            hash: FileHash::Sha1(sha1.clone()),
+            filename: None,
+        };
+
+        let file = url_index_file(ContentKind::Mod, &m, &dir, &config).unwrap();
+        assert_eq!(file.path, "mods/foo.jar");
+        assert_eq!(file.hashes.sha1, sha1);
+        assert_eq!(
+            file.hashes.sha512,
+            get_sha512_hash(dir.join("foo.jar")).unwrap()
+        );
+        assert_eq!(file.file_size, 3);
+        assert_eq!(file.downloads, ["https://example.com/foo.jar"]);
+
+        // Not exported from a file that does not match the pin
+        fs::write(dir.join("foo.jar"), "bar").unwrap();
+        assert!(url_index_file(ContentKind::Mod, &m, &dir, &config).is_none());
+        fs::remove_file(dir.join("foo.jar")).unwrap();
+        assert!(url_index_file(ContentKind::Mod, &m, &dir, &config).is_none());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_import_files_not_on_modrinth() {
+        let dir = temp_dir("mrpack-import");
+        let file = |path: &str, url: &str, server: SideSupport| IndexFile {
+            path: path.to_string(),
+            hashes: VersionFileHashes {
+                sha1: format!("{path}-sha1"),
+                sha512: format!("{path}-sha512"),
+            },
+            env: Some(Env {
+                client: SideSupport::Required,
+                server,
+            }),
+            downloads: vec![url.to_string()],
+            file_size: 3,
+        };
+        let path = dir.join("pack.mrpack");
+        let files = vec![
+            file(
+                "mods/foo.jar",
+                "https://example.com/files/foo.jar",
+                SideSupport::Required,
+            ),
+            file(
+                "mods/bar-1.0.jar",
+                "https://example.com/download?id=3",
+                SideSupport::Optional,
+            ),
+            file(
+                "mods/menu.jar",
+                "https://example.com/files/menu.jar",
+                SideSupport::Unsupported,
+            ),
+        ];
+        write_mrpack(&path, &index(files), &dir).unwrap();
+        // Modrinth knows none of the files
+        let server = MockServer::route(vec![("POST /v2/version_files", MockResponse::json("{}"))]);
+        let client = ModrinthClient::new().with_base_url(format!("{}/v2", server.url));
+
+        let config_path = dir.join("mods").join("mods.toml");
+        fs::create_dir_all(dir.join("mods")).unwrap();
+        smol::block_on(Compat::new(import_mrpack(
+            &client,
+            &path,
+            &dir,
+            &config_path,
+        )))
+        .unwrap();
+        assert_eq!(
+            fs::read_to_string(&config_path).unwrap(),
+            r#"version = "1.21.6"
+loader = "fabric"
+
+[mods]
+foo = { url = "https://example.com/files/foo.jar", sha512 = "mods/foo.jar-sha512" }
+"bar-1.0" = { url = "https://example.com/download?id=3", sha512 = "mods/bar-1.0.jar-sha512", filename = "bar-1.0.jar" }
+"#
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,1175 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::{self, Display, Formatter},
+    fs,
+    io::{self, IsTerminal},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, bail};
+use color_print::cformat;
+use futures::{StreamExt, stream};
+use ice::{
+    config::{
+        LocalModsConfig,
+        lock::{LockedEntry, ModsLock},
+    },
+    core::{ContentKind, FileHash, LocalMod, Mod, Side},
+};
+use ice_api_tool::{curseforge::CurseForgeClient, modrinth::ModrinthClient};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use super::{
+    compat::ReportFormat,
+    curseforge, local, lock,
+    modrinth::{SyncOptions, confirm, get_files, remove_file, resolve_files},
+    profile::DISABLED_EXTENSION,
+    url::{self, RemoteFile},
+};
+
+/// What `sync`, `update` or `migrate` does, computed before any file is touched
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub(crate) struct SyncPlan {
+    /// What is done to the files
+    pub actions: Vec<PlannedAction>,
+    /// The entries of mods.toml `update` or `migrate` moves to other versions
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub updates: Vec<PlannedUpdate>,
+}
+
+/// Something done to a file under the directory of `kind`
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct PlannedAction {
+    pub kind: ContentKind,
+    /// The entry the file is of, `None` if it is of no entry
+    pub slug: Option<String>,
+    pub file: String,
+    #[serde(flatten)]
+    pub op: Operation,
+    pub reason: Reason,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "kebab-case")]
+pub(crate) enum Operation {
+    /// Fetch the missing file
+    Download {
+        source: FileSource,
+    },
+    /// Fetch `with` and remove the file, unless it is the same one
+    Replace {
+        with: String,
+        source: FileSource,
+    },
+    Remove,
+    Keep,
+    /// Rename the file to `to`, to disable or enable its entry
+    Rename {
+        to: String,
+    },
+}
+
+/// Where the file of an entry comes from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub(crate) enum FileSource {
+    /// Downloaded and verified against the size and hashes known
+    Url {
+        url: String,
+        size: Option<u64>,
+        sha1: Option<String>,
+        sha512: Option<String>,
+    },
+    /// Copied or linked from the source of a path entry, relative to the
+    /// directory of mods.toml
+    Path { path: PathBuf, symlink: bool },
+    /// The author does not allow other apps to download it
+    Manual,
+}
+
+impl FileSource {
+    fn of(entry: &LockedEntry) -> Self {
+        match &entry.url {
+            Some(url) => Self::Url {
+                url: url.clone(),
+                size: entry.size,
+                sha1: entry.sha1.clone(),
+                sha512: entry.sha512.clone(),
+            },
+            None => Self::Manual,
+        }
+    }
+}
+
+/// Why an action is planned
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "code", rename_all = "kebab-case")]
+pub(crate) enum Reason {
+    /// The file of the entry is not there
+    Missing,
+    /// The file does not match the entry
+    Mismatch {
+        detail: String,
+    },
+    /// The file differs from the source of its path entry
+    SourceChanged,
+    /// The file is of another version of the entry
+    VersionChanged {
+        from: String,
+        to: String,
+    },
+    UpToDate,
+    NotForSide {
+        side: Side,
+    },
+    /// The file is of a project mods.toml has no entry for
+    NotInConfig,
+    /// The file is unknown to every source
+    Unknown,
+    /// The file is of no entry of mods.lock
+    NotLocked,
+    Disabled,
+    Enabled,
+}
+
+impl Display for Reason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Missing => f.write_str("missing"),
+            Self::Mismatch { detail } => f.write_str(detail),
+            Self::SourceChanged => f.write_str("the source changed"),
+            Self::VersionChanged { from, to } => write!(f, "{from} -> {to}"),
+            Self::UpToDate => f.write_str("up to date"),
+            Self::NotForSide { side } => write!(f, "not for {side}"),
+            Self::NotInConfig => f.write_str("not in mods.toml"),
+            Self::Unknown => f.write_str("unknown"),
+            Self::NotLocked => f.write_str("not in mods.lock"),
+            Self::Disabled => f.write_str("disabled"),
+            Self::Enabled => f.write_str("enabled"),
+        }
+    }
+}
+
+/// An entry of mods.toml `update` or `migrate` moves to another version
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct PlannedUpdate {
+    pub kind: ContentKind,
+    pub slug: String,
+    pub from: String,
+    pub to: String,
+    /// The entry written to mods.toml once its file is in place
+    #[serde(skip)]
+    pub entry: Mod,
+}
+
+/// The options of the commands that plan before they act
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PlanOptions {
+    /// Only print the plan
+    pub dry_run: bool,
+    /// Apply the plan without asking
+    pub yes: bool,
+    /// How the plan of a dry run is printed
+    pub format: ReportFormat,
+}
+
+impl SyncPlan {
+    /// Whether applying the plan changes anything
+    pub fn has_changes(&self) -> bool {
+        !self.updates.is_empty()
+            || self
+                .actions
+                .iter()
+                .any(|action| !matches!(action.op, Operation::Keep))
+    }
+
+    /// Print the changes of the plan, and how many files are unchanged
+    pub fn print(&self) {
+        for update in &self.updates {
+            println!(
+                "{}",
+                cformat!(
+                    "<g>update</>   {} = {} -> {} [{}]",
+                    update.slug,
+                    update.from,
+                    update.to,
+                    update.kind.table()
+                )
+            );
+        }
+        let mut kept = 0;
+        for action in &self.actions {
+            let file = match action.kind {
+                ContentKind::Mod => action.file.clone(),
+                kind => format!("{}/{}", kind.table(), action.file),
+            };
+            let line = match &action.op {
+                Operation::Download { .. } => {
+                    cformat!("<g>download</> {} ({})", file, action.reason)
+                }
+                Operation::Replace { with, .. } if *with == action.file => {
+                    cformat!("<y>replace</>  {} ({})", file, action.reason)
+                }
+                Operation::Replace { with, .. } => {
+                    cformat!("<y>replace</>  {} -> {} ({})", file, with, action.reason)
+                }
+                Operation::Remove => cformat!("<r>remove</>   {} ({})", file, action.reason),
+                Operation::Rename { to } => {
+                    cformat!("<c>rename</>   {} -> {} ({})", file, to, action.reason)
+                }
+                Operation::Keep => {
+                    kept += 1;
+                    continue;
+                }
+            };
+            println!("{line}");
+        }
+        match self.has_changes() {
+            true => println!("{kept} unchanged"),
+            false => println!("nothing to do, {kept} unchanged"),
+        }
+    }
+}
+
+// MARK: Run
+
+/// Print the plan on a dry run, or apply it once confirmed
+///
+/// Without `yes`, a plan with changes is printed and confirmed, and never
+/// applied if stdin is not a terminal to confirm it. Returns the entries whose
+/// files failed, `None` if the plan is not applied.
+pub(crate) async fn run(
+    plan: &SyncPlan,
+    current_dir: &Path,
+    options: PlanOptions,
+    config: &LocalModsConfig,
+) -> Option<HashSet<(ContentKind, String)>> {
+    if options.dry_run {
+        match options.format {
+            ReportFormat::Table => plan.print(),
+            ReportFormat::Json => println!("{}", serde_json::to_string_pretty(plan).unwrap()),
+        }
+        return None;
+    }
+    if plan.has_changes() && !options.yes {
+        plan.print();
+        if !io::stdin().is_terminal() {
+            info!(
+                "{}",
+                cformat!(
+                    "<r>Failed</> stdin is not a terminal to confirm the changes, pass --yes to apply them"
+                )
+            );
+            return None;
+        }
+        if !confirm("apply these changes?") {
+            info!("aborted");
+            return None;
+        }
+    }
+    Some(apply(plan, current_dir, config).await)
+}
+
+/// Apply the actions of `plan`, the downloads last
+///
+/// Returns the entries whose files failed.
+async fn apply(
+    plan: &SyncPlan,
+    current_dir: &Path,
+    config: &LocalModsConfig,
+) -> HashSet<(ContentKind, String)> {
+    let mut failed = HashSet::new();
+    let (fetches, others): (Vec<_>, Vec<_>) = plan.actions.iter().partition(|action| {
+        matches!(
+            action.op,
+            Operation::Download { .. } | Operation::Replace { .. }
+        )
+    });
+
+    for action in others {
+        let dir = action.kind.dir(current_dir);
+        let slug = action.slug.as_deref().unwrap_or(&action.file);
+        let res = match &action.op {
+            Operation::Keep => {
+                match action.reason {
+                    Reason::Unknown => info!(
+                        "{}",
+                        cformat!(
+                            "<y>Kept</> {} (unknown, remove it with --prune)",
+                            action.file
+                        )
+                    ),
+                    _ => info!("{}", cformat!("<y>Unchanged</> {}", slug)),
+                }
+                Ok(())
+            }
+            Operation::Remove => remove_file(dir.join(&action.file)).map(|_| {
+                info!(
+                    "{}",
+                    cformat!("<r>Removed</> {} ({})", action.file, action.reason)
+                )
+            }),
+            Operation::Rename { to } => fs::rename(dir.join(&action.file), dir.join(to))
+                .with_context(|| format!("failed to rename {}", action.file))
+                .map(|_| match action.reason {
+                    Reason::Enabled => info!("{}", cformat!("<g>Enabled</> {}", slug)),
+                    _ => info!("{}", cformat!("<dim>Disabled</> {}", slug)),
+                }),
+            Operation::Download { .. } | Operation::Replace { .. } => unreachable!(),
+        };
+        if let Err(err) = res {
+            info!("{}", cformat!("<r>Failed</> {:?}", err));
+            if let Some(slug) = &action.slug {
+                failed.insert((action.kind, slug.clone()));
+            }
+        }
+    }
+
+    let mut stream = stream::iter(fetches)
+        .map(|action| async move {
+            let dir = action.kind.dir(current_dir);
+            let slug = action.slug.as_deref().unwrap_or(&action.file);
+            let res = match &action.op {
+                Operation::Download { source } => {
+                    fetch(action.kind, slug, &action.file, source, &dir, config).await
+                }
+                Operation::Replace { with, source } => {
+                    async {
+                        fetch(action.kind, slug, with, source, &dir, config).await?;
+                        if *with != action.file {
+                            remove_file(dir.join(&action.file))?;
+                        }
+                        Ok(())
+                    }
+                    .await
+                }
+                _ => unreachable!(),
+            };
+            (action, res)
+        })
+        .buffer_unordered(5);
+
+    while let Some((action, res)) = stream.next().await {
+        let slug = action.slug.as_deref().unwrap_or(&action.file);
+        let (filename, source) = match &action.op {
+            Operation::Download { source } => (&action.file, source),
+            Operation::Replace { with, source } => (with, source),
+            _ => unreachable!(),
+        };
+        if let Err(err) = res {
+            let verb = match source {
+                FileSource::Path { .. } => "install",
+                _ => "download",
+            };
+            info!(
+                "{}",
+                cformat!(
+                    "<r>Failed</> {} {} {} ({})",
+                    verb,
+                    action.kind,
+                    slug,
+                    err.root_cause()
+                )
+            );
+            if let Some(slug) = &action.slug {
+                failed.insert((action.kind, slug.clone()));
+            }
+            continue;
+        }
+        match (source, &action.op) {
+            (
+                FileSource::Path {
+                    path,
+                    symlink: true,
+                },
+                _,
+            ) => info!(
+                "{}",
+                cformat!("<g>Linked</> {} -> {}", slug, path.display())
+            ),
+            (FileSource::Path { path, .. }, _) => {
+                info!("{}", cformat!("<g>Copied</> {} = {}", slug, path.display()))
+            }
+            (_, Operation::Replace { .. }) if *filename == action.file => info!(
+                "{}",
+                cformat!("<g>Replaced</> {} ({})", action.file, action.reason)
+            ),
+            (_, Operation::Replace { .. }) => info!(
+                "{}",
+                cformat!(
+                    "<g>Replaced</> {} -> {} ({})",
+                    action.file,
+                    filename,
+                    action.reason
+                )
+            ),
+            _ => info!("{}", cformat!("<g>Downloaded</> {} = {}", slug, filename)),
+        }
+    }
+    failed
+}
+
+/// Put the file `filename` of the entry `slug` under `dir` from `source`
+async fn fetch(
+    kind: ContentKind,
+    slug: &str,
+    filename: &str,
+    source: &FileSource,
+    dir: &Path,
+    config: &LocalModsConfig,
+) -> Result<(), anyhow::Error> {
+    match source {
+        FileSource::Url {
+            url,
+            size,
+            sha1,
+            sha512,
+        } => {
+            let file = RemoteFile {
+                slug: slug.to_string(),
+                url: url.clone(),
+                filename: filename.to_string(),
+                size: *size,
+                hash: sha512
+                    .clone()
+                    .map(FileHash::Sha512)
+                    .or(sha1.clone().map(FileHash::Sha1)),
+            };
+            url::download(&file, dir).await
+        }
+        FileSource::Path { path, .. } => {
+            let Some(Mod::Local(m)) = config.get_entry(kind, slug) else {
+                bail!("{slug} is not a path entry");
+            };
+            let base = config.path().parent().unwrap_or(Path::new(""));
+            local::install(&m, &base.join(path), &dir.join(filename))
+        }
+        FileSource::Manual => bail!(
+            "the author does not allow downloading {} from other apps, put it under {} by hand",
+            filename,
+            dir.display()
+        ),
+    }
+}
+
+// MARK: Sync
+
+/// Plan syncing the files of `kind` under `dir` with the `entries` resolved
+/// from `config`
+///
+/// The files of the entries not in `enabled` are renamed to `.disabled`. The
+/// files of no entry are looked up on Modrinth and CurseForge if `clients`
+/// are given, to tell the old versions of the entries and the projects not in
+/// mods.toml from the unknown files. With `locked`, the files of no entry of
+/// mods.lock are removed, so the directory is exactly the one of the lock.
+pub(crate) async fn plan_sync_kind(
+    kind: ContentKind,
+    dir: &Path,
+    entries: &[&LockedEntry],
+    options: SyncOptions<'_>,
+    enabled: &LocalModsConfig,
+    config: &LocalModsConfig,
+    clients: Option<(&ModrinthClient, &CurseForgeClient)>,
+) -> Result<Vec<PlannedAction>, anyhow::Error> {
+    let SyncOptions {
+        side,
+        prune,
+        locked,
+        ..
+    } = options;
+    let mut planner = Planner {
+        kind,
+        dir,
+        actions: vec![],
+        expected: HashSet::new(),
+        versions: HashMap::new(),
+    };
+
+    for entry in entries {
+        planner.plan_entry(entry, side, enabled);
+    }
+    let base = config.path().parent().unwrap_or(Path::new(""));
+    for m in local::entries(kind, config) {
+        planner.plan_local(&m, &base.join(&m.path), side, enabled, config);
+    }
+
+    let extra_files = get_files(dir, kind.extension())
+        .into_iter()
+        .filter(|entry| !entry.file_name().to_string_lossy().starts_with("_"))
+        .map(|entry| entry.path())
+        .filter(|path| !planner.expected.contains(path))
+        .collect::<Vec<_>>();
+    let owners = match clients {
+        Some((client, curseforge)) if !extra_files.is_empty() => {
+            file_owners(client, curseforge, kind, extra_files.clone(), config).await?
+        }
+        _ => HashMap::new(),
+    };
+    for path in extra_files {
+        planner.plan_extra(&path, owners.get(&path), side, prune, locked, config);
+    }
+    Ok(planner.actions)
+}
+
+/// The project a file of no entry is of
+struct FileOwner {
+    /// The entry of the project, `None` if mods.toml has none
+    slug: Option<String>,
+    version: String,
+}
+
+/// Look up the files on Modrinth, then the ones unknown to it on CurseForge
+async fn file_owners(
+    client: &ModrinthClient,
+    curseforge: &CurseForgeClient,
+    kind: ContentKind,
+    paths: Vec<PathBuf>,
+    config: &LocalModsConfig,
+) -> Result<HashMap<PathBuf, FileOwner>, anyhow::Error> {
+    let mut owners = HashMap::new();
+    let mut unknown = vec![];
+    for file in resolve_files(client, paths).await? {
+        let Some((version, project)) = file.version else {
+            unknown.push(file.path);
+            continue;
+        };
+        let slug = config
+            .get_entry(kind, &project.slug)
+            .map(|_| project.slug.clone());
+        let version = version.version_number;
+        owners.insert(file.path, FileOwner { slug, version });
+    }
+
+    let curseforge_mods = curseforge::entries(kind, config);
+    if curseforge_mods.is_empty() {
+        return Ok(owners);
+    }
+    for (path, file) in curseforge::resolve_files(curseforge, unknown).await? {
+        let slug = curseforge_mods
+            .iter()
+            .find(|m| m.project_id == file.mod_id)
+            .map(|m| m.slug.clone());
+        let version = file.display_name;
+        owners.insert(path, FileOwner { slug, version });
+    }
+    Ok(owners)
+}
+
+/// Builds the actions of one kind
+struct Planner<'a> {
+    kind: ContentKind,
+    dir: &'a Path,
+    actions: Vec<PlannedAction>,
+    /// The files some entry accounts for
+    expected: HashSet<PathBuf>,
+    /// The versions of the entries, to name the version an old file is
+    /// replaced with
+    versions: HashMap<String, String>,
+}
+
+impl Planner<'_> {
+    fn push(&mut self, slug: Option<&str>, path: &Path, op: Operation, reason: Reason) {
+        self.expected.insert(path.to_path_buf());
+        self.actions.push(PlannedAction {
+            kind: self.kind,
+            slug: slug.map(|slug| slug.to_string()),
+            file: path.file_name().unwrap().to_string_lossy().to_string(),
+            op,
+            reason,
+        });
+    }
+
+    /// Disable the file of the entry `slug` if it is not in `enabled`,
+    /// returns whether it is not
+    fn plan_disabled(&mut self, slug: &str, filename: &str, enabled: &LocalModsConfig) -> bool {
+        if enabled.get_entry(self.kind, slug).is_some() {
+            return false;
+        }
+        let path = self.dir.join(filename);
+        if path.symlink_metadata().is_ok() {
+            let op = Operation::Rename {
+                to: format!("{filename}.{DISABLED_EXTENSION}"),
+            };
+            self.push(Some(slug), &path, op, Reason::Disabled);
+        }
+        true
+    }
+
+    /// Enable the disabled file of the entry `slug`, returns whether there
+    /// is one
+    fn plan_enabled(&mut self, slug: &str, filename: &str) -> bool {
+        let disabled_path = self.dir.join(format!("{filename}.{DISABLED_EXTENSION}"));
+        if disabled_path.symlink_metadata().is_err() {
+            return false;
+        }
+        let op = Operation::Rename {
+            to: filename.to_string(),
+        };
+        self.push(Some(slug), &disabled_path, op, Reason::Enabled);
+        true
+    }
+
+    fn plan_entry(&mut self, entry: &LockedEntry, side: Option<Side>, enabled: &LocalModsConfig) {
+        let slug = Some(entry.slug.as_str());
+        if let Some(version) = entry.version_number.as_ref().or(entry.version_id.as_ref()) {
+            self.versions.insert(entry.slug.clone(), version.clone());
+        }
+        if self.plan_disabled(&entry.slug, &entry.filename, enabled) {
+            return;
+        }
+        let installed = lock::installed_path(self.kind, entry, self.dir);
+        if let Some(side) = side.filter(|side| !entry.side.includes(*side)) {
+            if let Some(path) = installed {
+                self.push(slug, &path, Operation::Remove, Reason::NotForSide { side });
+            }
+            return;
+        }
+
+        let path = self.dir.join(&entry.filename);
+        match installed {
+            Some(path) => self.push(slug, &path, Operation::Keep, Reason::UpToDate),
+            None if path.is_file() => {
+                let detail = match entry.checksum().mismatch(&path) {
+                    Ok(Some(mismatch)) => mismatch,
+                    Ok(None) => "mismatch".to_string(),
+                    Err(err) => format!("failed to check it ({err})"),
+                };
+                let op = Operation::Replace {
+                    with: entry.filename.clone(),
+                    source: FileSource::of(entry),
+                };
+                self.push(slug, &path, op, Reason::Mismatch { detail });
+            }
+            None if self.plan_enabled(&entry.slug, &entry.filename) => (),
+            None => {
+                let op = Operation::Download {
+                    source: FileSource::of(entry),
+                };
+                self.push(slug, &path, op, Reason::Missing);
+            }
+        }
+    }
+
+    /// `source` is the file at the path of `m`
+    fn plan_local(
+        &mut self,
+        m: &LocalMod,
+        source: &Path,
+        side: Option<Side>,
+        enabled: &LocalModsConfig,
+        config: &LocalModsConfig,
+    ) {
+        let slug = Some(m.slug.as_str());
+        if self.plan_disabled(&m.slug, m.filename(), enabled) {
+            return;
+        }
+        let path = self.dir.join(m.filename());
+        let exists = path.symlink_metadata().is_ok();
+        if let Some(side) = side.filter(|side| !config.is_entry_on_side(self.kind, &m.slug, *side))
+        {
+            if exists {
+                self.push(slug, &path, Operation::Remove, Reason::NotForSide { side });
+            }
+            return;
+        }
+
+        let installed = exists && local::is_installed(m, source, &path);
+        let source = FileSource::Path {
+            path: m.path.clone(),
+            symlink: m.symlink,
+        };
+        match exists {
+            true if installed => self.push(slug, &path, Operation::Keep, Reason::UpToDate),
+            true => {
+                let op = Operation::Replace {
+                    with: m.filename().to_string(),
+                    source,
+                };
+                self.push(slug, &path, op, Reason::SourceChanged);
+            }
+            false if self.plan_enabled(&m.slug, m.filename()) => (),
+            false => self.push(slug, &path, Operation::Download { source }, Reason::Missing),
+        }
+    }
+
+    /// Plan a file of no entry, `owner` is the project it is of if known
+    fn plan_extra(
+        &mut self,
+        path: &Path,
+        owner: Option<&FileOwner>,
+        side: Option<Side>,
+        prune: bool,
+        locked: bool,
+        config: &LocalModsConfig,
+    ) {
+        let Some(owner) = owner else {
+            let (op, reason) = match (locked, prune) {
+                (true, _) => (Operation::Remove, Reason::NotLocked),
+                (false, true) => (Operation::Remove, Reason::Unknown),
+                (false, false) => (Operation::Keep, Reason::Unknown),
+            };
+            self.push(None, path, op, reason);
+            return;
+        };
+        let Some(slug) = &owner.slug else {
+            self.push(None, path, Operation::Remove, Reason::NotInConfig);
+            return;
+        };
+        if let Some(side) = side.filter(|side| !config.is_entry_on_side(self.kind, slug, *side)) {
+            self.push(
+                Some(slug),
+                path,
+                Operation::Remove,
+                Reason::NotForSide { side },
+            );
+            return;
+        }
+
+        // The file of another version of an entry is replaced by the file of
+        // the entry, instead of downloading it next to it
+        let reason = Reason::VersionChanged {
+            from: owner.version.clone(),
+            to: self.versions.get(slug).cloned().unwrap_or("-".to_string()),
+        };
+        let download = self.actions.iter().position(|action| {
+            action.slug.as_ref() == Some(slug) && matches!(action.op, Operation::Download { .. })
+        });
+        match download.map(|i| self.actions.remove(i)) {
+            Some(PlannedAction {
+                file,
+                op: Operation::Download { source },
+                ..
+            }) => {
+                let op = Operation::Replace { with: file, source };
+                self.push(Some(slug), path, op, reason);
+            }
+            _ => self.push(Some(slug), path, Operation::Remove, reason),
+        }
+    }
+}
+
+// MARK: Update
+
+/// Plan replacing the files of the entries `updates` moves, `old` and `new`
+/// are the resolutions of the entries before and after
+///
+/// The file of an entry that is not installed is only downloaded if the
+/// entry is enabled without a profile and was not disabled by one.
+pub(crate) fn plan_updates(
+    current_dir: &Path,
+    updates: &[PlannedUpdate],
+    old: &ModsLock,
+    new: &ModsLock,
+    config: &LocalModsConfig,
+) -> Vec<PlannedAction> {
+    let mut actions = vec![];
+    for update in updates {
+        let (kind, slug) = (update.kind, &update.slug);
+        let Some(entry) = new.get(kind, slug) else {
+            continue;
+        };
+        let dir = kind.dir(current_dir);
+        let installed = old
+            .get(kind, slug)
+            .and_then(|old| lock::installed_path(kind, old, &dir));
+        let (file, op, reason) = match installed {
+            Some(path) => (
+                path.file_name().unwrap().to_string_lossy().to_string(),
+                Operation::Replace {
+                    with: entry.filename.clone(),
+                    source: FileSource::of(entry),
+                },
+                Reason::VersionChanged {
+                    from: update.from.clone(),
+                    to: update.to.clone(),
+                },
+            ),
+            None if !config.is_entry_enabled(kind, slug, None)
+                || old.get(kind, slug).is_some_and(|old| {
+                    dir.join(format!("{}.{DISABLED_EXTENSION}", old.filename))
+                        .exists()
+                }) =>
+            {
+                continue;
+            }
+            None => (
+                entry.filename.clone(),
+                Operation::Download {
+                    source: FileSource::of(entry),
+                },
+                Reason::Missing,
+            ),
+        };
+        actions.push(PlannedAction {
+            kind,
+            slug: Some(slug.clone()),
+            file,
+            op,
+            reason,
+        });
+    }
+    actions
+}
+
+#[cfg(test)]
+mod test {
+    use ice::core::ModSide;
+    use ice_util::fs::get_sha1_hash;
+    use serde_json::json;
+
+    use super::*;
+    use crate::cli::{profile, temp_dir};
+
+    /// Write `content` as `dir/name` and return its sha1
+    fn write(dir: &Path, name: &str, content: &str) -> String {
+        fs::write(dir.join(name), content).unwrap();
+        get_sha1_hash(dir.join(name)).unwrap()
+    }
+
+    fn config(dir: &Path, mods: &str) -> LocalModsConfig {
+        let path = dir.join("mods.toml");
+        fs::write(
+            &path,
+            format!("version = \"1.21.6\"\nloader = \"fabric\"\n\n[mods]\n{mods}"),
+        )
+        .unwrap();
+        LocalModsConfig::load(path).unwrap()
+    }
+
+    fn locked(slug: &str, version: &str, sha1: &str) -> LockedEntry {
+        LockedEntry::test_mod(slug, version, Some(sha1.to_string()))
+    }
+
+    /// A line for each action, sorted by file
+    fn summary(actions: &[PlannedAction]) -> Vec<String> {
+        let mut lines = actions
+            .iter()
+            .map(|action| {
+                let op = match &action.op {
+                    Operation::Download { .. } => "download".to_string(),
+                    Operation::Replace { with, .. } => format!("replace with {with}"),
+                    Operation::Remove => "remove".to_string(),
+                    Operation::Keep => "keep".to_string(),
+                    Operation::Rename { to } => format!("rename to {to}"),
+                };
+                let reason = match &action.reason {
+                    Reason::Mismatch { .. } => "mismatch".to_string(),
+                    reason => reason.to_string(),
+                };
+                format!("{} {op} ({reason})", action.file)
+            })
+            .collect::<Vec<_>>();
+        lines.sort();
+        lines
+    }
+
+    #[test]
+    fn test_plan_sync_kind() {
+        let dir = temp_dir("plan-sync");
+        let config = config(
+            &dir,
+            r#"a = "a1.0#1.0"
+b = "b1.0#1.0"
+c = "c1.0#1.0"
+d = { version-id-number = "d1.0#1.0", enabled = false }
+e = "e1.0#1.0"
+f = "f1.0#1.0"
+"#,
+        );
+        let a = write(&dir, "a-1.0.jar", "a");
+        write(&dir, "b-1.0.jar", "b, but another build");
+        let d = write(&dir, "d-1.0.jar", "d");
+        write(&dir, "e-1.0.jar.disabled", "e");
+        let f = write(&dir, "f-1.0.jar", "f");
+        write(&dir, "stray.jar", "stray");
+        write(&dir, "_by-hand.jar", "by hand");
+        let mut f = locked("f", "1.0", &f);
+        f.side = ModSide::Client;
+        let entries = [
+            locked("a", "1.0", &a),
+            locked("b", "1.0", "0000"),
+            locked("c", "1.0", "0000"),
+            locked("d", "1.0", &d),
+            locked("e", "1.0", "0000"),
+            f,
+        ];
+        let entries = entries.iter().collect::<Vec<_>>();
+        let enabled = profile::enabled_entries(&config, None);
+        let plan = |options: SyncOptions| {
+            let actions = smol::block_on(plan_sync_kind(
+                ContentKind::Mod,
+                &dir,
+                &entries,
+                options,
+                &enabled,
+                &config,
+                None,
+            ))
+            .unwrap();
+            summary(&actions)
+        };
+
+        let options = SyncOptions {
+            side: Some(Side::Server),
+            ..Default::default()
+        };
+        assert_eq!(
+            plan(options),
+            [
+                "a-1.0.jar keep (up to date)",
+                "b-1.0.jar replace with b-1.0.jar (mismatch)",
+                "c-1.0.jar download (missing)",
+                "d-1.0.jar rename to d-1.0.jar.disabled (disabled)",
+                "e-1.0.jar.disabled rename to e-1.0.jar (enabled)",
+                "f-1.0.jar remove (not for server)",
+                "stray.jar keep (unknown)",
+            ]
+        );
+
+        let pruned = plan(SyncOptions {
+            prune: true,
+            ..Default::default()
+        });
+        assert!(pruned.contains(&"stray.jar remove (unknown)".to_string()));
+        assert!(pruned.contains(&"f-1.0.jar keep (up to date)".to_string()));
+        let locked = plan(SyncOptions {
+            locked: true,
+            ..Default::default()
+        });
+        assert!(locked.contains(&"stray.jar remove (not in mods.lock)".to_string()));
+        assert!(!locked.iter().any(|line| line.starts_with("_by-hand.jar")));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_plan_extra() {
+        let dir = temp_dir("plan-extra");
+        let config = config(&dir, "a = \"a2.0#2.0\"\nb = \"b1.0#1.0\"\n");
+        let b = write(&dir, "b-1.0.jar", "b");
+        let mut planner = Planner {
+            kind: ContentKind::Mod,
+            dir: &dir,
+            actions: vec![],
+            expected: HashSet::new(),
+            versions: HashMap::new(),
+        };
+        planner.plan_entry(&locked("a", "2.0", "0000"), None, &config);
+        planner.plan_entry(&locked("b", "1.0", &b), None, &config);
+        let owner = |slug: Option<&str>, version: &str| FileOwner {
+            slug: slug.map(|slug| slug.to_string()),
+            version: version.to_string(),
+        };
+        let extra = [
+            ("a-1.0.jar", owner(Some("a"), "1.0")),
+            ("b-0.9.jar", owner(Some("b"), "0.9")),
+            ("x-1.0.jar", owner(None, "1.0")),
+        ];
+        for (file, owner) in &extra {
+            planner.plan_extra(&dir.join(file), Some(owner), None, false, false, &config);
+        }
+
+        // The old file of a missing entry is replaced instead of downloading
+        // the new one next to it
+        assert_eq!(
+            summary(&planner.actions),
+            [
+                "a-1.0.jar replace with a-2.0.jar (1.0 -> 2.0)",
+                "b-0.9.jar remove (0.9 -> 1.0)",
+                "b-1.0.jar keep (up to date)",
+                "x-1.0.jar remove (not in mods.toml)",
+            ]
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_plan_updates() {
+        let dir = temp_dir("plan-updates");
+        let config = config(
+            &dir,
+            r#"a = "a1.0#1.0"
+b = "b1.0#1.0"
+c = { version-id-number = "c1.0#1.0", enabled = false }
+d = "d1.0#1.0"
+"#,
+        );
+        let a = write(&dir, "a-1.0.jar", "a");
+        write(&dir, "d-1.0.jar.disabled", "d");
+        let slugs = ["a", "b", "c", "d"];
+        let updates = slugs
+            .iter()
+            .map(|slug| PlannedUpdate {
+                kind: ContentKind::Mod,
+                slug: slug.to_string(),
+                from: "1.0".to_string(),
+                to: "2.0".to_string(),
+                entry: Mod::Unknown,
+            })
+            .collect::<Vec<_>>();
+        let lock = |version: &str| {
+            ModsLock::new(
+                slugs
+                    .iter()
+                    .map(|slug| match *slug {
+                        "a" if version == "1.0" => locked(slug, version, &a),
+                        _ => locked(slug, version, "0000"),
+                    })
+                    .collect(),
+            )
+        };
+
+        let actions = plan_updates(&dir, &updates, &lock("1.0"), &lock("2.0"), &config);
+        // c is disabled and d is disabled by a profile, they are left alone
+        assert_eq!(
+            summary(&actions),
+            [
+                "a-1.0.jar replace with a-2.0.jar (1.0 -> 2.0)",
+                "b-2.0.jar download (missing)",
+            ]
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_run_applies_plan() {
+        let dir = temp_dir("plan-run");
+        let config = config(
+            &dir,
+            r#"p = { path = "vendor/p.jar" }
+d = { version-id-number = "d1.0#1.0", enabled = false }
+"#,
+        );
+        fs::create_dir_all(dir.join("vendor")).unwrap();
+        write(&dir.join("vendor"), "p.jar", "p 2");
+        write(&dir, "p.jar", "p 1");
+        let d = write(&dir, "d-1.0.jar", "d");
+        write(&dir, "stray.jar", "stray");
+        let entries = [locked("d", "1.0", &d)];
+        let entries = entries.iter().collect::<Vec<_>>();
+        let enabled = profile::enabled_entries(&config, None);
+        let options = SyncOptions {
+            prune: true,
+            plan: PlanOptions {
+                yes: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let actions = smol::block_on(plan_sync_kind(
+            ContentKind::Mod,
+            &dir,
+            &entries,
+            options,
+            &enabled,
+            &config,
+            None,
+        ))
+        .unwrap();
+        assert_eq!(
+            summary(&actions),
+            [
+                "d-1.0.jar rename to d-1.0.jar.disabled (disabled)",
+                "p.jar replace with p.jar (the source changed)",
+                "stray.jar remove (unknown)",
+            ]
+        );
+
+        let plan = SyncPlan {
+            actions,
+            updates: vec![],
+        };
+        let failed = smol::block_on(run(&plan, &dir, options.plan, &config)).unwrap();
+        assert!(failed.is_empty());
+        assert_eq!(fs::read_to_string(dir.join("p.jar")).unwrap(), "p 2");
+        assert!(dir.join("d-1.0.jar.disabled").is_file());
+        assert!(!dir.join("d-1.0.jar").exists());
+        assert!(!dir.join("stray.jar").exists());
+
+        // A dry run touches nothing
+        write(&dir, "stray.jar", "stray");
+        let dry_run = PlanOptions {
+            dry_run: true,
+            ..Default::default()
+        };
+        assert!(smol::block_on(run(&plan, &dir, dry_run, &config)).is_none());
+        assert!(dir.join("stray.jar").is_file());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_plan_json() {
+        let plan = SyncPlan {
+            actions: vec![
+                PlannedAction {
+                    kind: ContentKind::Mod,
+                    slug: Some("sodium".to_string()),
+                    file: "sodium-0.6.0.jar".to_string(),
+                    op: Operation::Replace {
+                        with: "sodium-0.6.1.jar".to_string(),
+                        source: FileSource::Url {
+                            url: "https://cdn.modrinth.com/sodium-0.6.1.jar".to_string(),
+                            size: Some(3),
+                            sha1: Some("abc".to_string()),
+                            sha512: None,
+                        },
+                    },
+                    reason: Reason::VersionChanged {
+                        from: "0.6.0".to_string(),
+                        to: "0.6.1".to_string(),
+                    },
+                },
+                PlannedAction {
+                    kind: ContentKind::ResourcePack,
+                    slug: None,
+                    file: "stray.zip".to_string(),
+                    op: Operation::Remove,
+                    reason: Reason::Unknown,
+                },
+            ],
+            updates: vec![PlannedUpdate {
+                kind: ContentKind::Mod,
+                slug: "sodium".to_string(),
+                from: "0.6.0".to_string(),
+                to: "0.6.1".to_string(),
+                entry: Mod::Unknown,
+            }],
+        };
+
+        let value = serde_json::to_value(&plan).unwrap();
+        assert_eq!(
+            value,
+            json!({
+                "actions": [
+                    {
+                        "kind": "mod",
+                        "slug": "sodium",
+                        "file": "sodium-0.6.0.jar",
+                        "action": "replace",
+                        "with": "sodium-0.6.1.jar",
+                        "source": {
+                            "type": "url",
+                            "url": "https://cdn.modrinth.com/sodium-0.6.1.jar",
+                            "size": 3,
+                            "sha1": "abc",
+                            "sha512": null
+                        },
+                        "reason": { "code": "version-changed", "from": "0.6.0", "to": "0.6.1" }
+                    },
+                    {
+                        "kind": "resourcepack",
+                        "slug": null,
+                        "file": "stray.zip",
+                        "action": "remove",
+                        "reason": { "code": "unknown" }
+                    }
+                ],
+                "updates": [
+                    { "kind": "mod", "slug": "sodium", "from": "0.6.0", "to": "0.6.1" }
+                ]
+            })
+        );
+        let json = serde_json::to_string_pretty(&plan).unwrap();
+        assert_eq!(serde_json::from_str::<SyncPlan>(&json).unwrap(), plan);
+    }
+}
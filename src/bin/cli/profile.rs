@@ -0,0 +1,15 @@
+use ice::config::{LocalModsConfig, TomlProfile};
+
+/// The extension appended to the files of the disabled entries
+pub(crate) const DISABLED_EXTENSION: &str = "disabled";
+
+/// The entries of `config` installed with `profile`, see
+/// [`ice::config::ModsConfig::is_entry_enabled`]
+pub(crate) fn enabled_entries(
+    config: &LocalModsConfig,
+    profile: Option<&TomlProfile>,
+) -> LocalModsConfig {
+    let mut enabled = config.clone();
+    enabled.retain_entries(|kind, key| config.is_entry_enabled(kind, key, profile));
+    enabled
+}
@@ -0,0 +1,188 @@
+use std::path::{Path, PathBuf};
+
+use color_print::cformat;
+use ice::{
+    config::{LocalModsConfig, lock::ModsLock},
+    core::{ContentKind, Mod},
+};
+use ice_api_tool::{curseforge::CurseForgeClient, github::GitHubClient, modrinth::ModrinthClient};
+use tracing::info;
+
+use super::{
+    lock,
+    modrinth::{init_logger, remove_file},
+};
+
+/// The `remove` command
+///
+/// The entries are removed from mods.toml and their files are deleted, found
+/// by their hashes, or by their names once renamed to `.disabled`. The
+/// entries still requiring a removed one are reported, but kept.
+pub async fn remove(
+    client: &ModrinthClient,
+    curseforge: &CurseForgeClient,
+    github: &GitHubClient,
+    slugs: Vec<String>,
+    kind: Option<ContentKind>,
+    current_dir: impl AsRef<Path>,
+    config: &mut LocalModsConfig,
+) {
+    init_logger();
+
+    // Resolved before removing anything, to know the files and dependents
+    let lock = match lock::current(client, curseforge, github, config).await {
+        Ok(lock) => Some(lock),
+        Err(err) => {
+            info!(
+                "{}",
+                cformat!("<r>Failed</> {:?}, the files are not removed", err)
+            );
+            None
+        }
+    };
+
+    let current_dir = current_dir.as_ref();
+    for slug in &slugs {
+        let kinds = kind
+            .map(|kind| vec![kind])
+            .unwrap_or(config.kinds())
+            .into_iter()
+            .filter(|kind| config.get_entry(*kind, slug).is_some())
+            .collect::<Vec<_>>();
+        if kinds.is_empty() {
+            info!("{}", cformat!("<r>Failed</> {} is not in mods.toml", slug));
+            continue;
+        }
+
+        for kind in kinds {
+            if let Some(lock) = &lock {
+                warn_dependents(lock, kind, slug, &slugs);
+            }
+            let Some(m) = config.remove_entry(kind, slug) else {
+                continue;
+            };
+            config.save().unwrap();
+            info!(
+                "{}",
+                cformat!("<r>Removed</> {} from [{}]", slug, kind.table())
+            );
+
+            let dir = kind.dir(current_dir);
+            let (path, filename) = match (&m, &lock) {
+                (Mod::Local(m), _) => (Some(dir.join(m.filename())), m.filename().to_string()),
+                (_, Some(lock)) => match lock.get(kind, slug) {
+                    Some(entry) => (
+                        lock::installed_path(kind, entry, &dir),
+                        entry.filename.clone(),
+                    ),
+                    None => continue,
+                },
+                (_, None) => continue,
+            };
+            let mut paths = path
+                .into_iter()
+                .flat_map(|path| [path.clone(), disabled_path(&path)])
+                .collect::<Vec<_>>();
+            // A disabled file is not found by its hash, only by its name
+            paths.push(dir.join(format!("{filename}.disabled")));
+            paths.dedup();
+            for path in paths {
+                if path.symlink_metadata().is_err() {
+                    continue;
+                }
+                let filename = path.file_name().unwrap().to_string_lossy().to_string();
+                match remove_file(&path) {
+                    Ok(()) => info!("{}", cformat!("<r>Removed</> {}", filename)),
+                    Err(err) => info!("{}", cformat!("<r>Failed</> {:?}", err)),
+                }
+            }
+        }
+    }
+}
+
+/// `path` renamed to `.disabled`
+fn disabled_path(path: &Path) -> PathBuf {
+    path.with_file_name(format!(
+        "{}.disabled",
+        path.file_name().unwrap().to_string_lossy()
+    ))
+}
+
+/// Report the entries of `kind` that require `slug` and are not removed too
+fn warn_dependents(lock: &ModsLock, kind: ContentKind, slug: &str, removed: &[String]) {
+    let dependents = lock
+        .dependents(kind, slug)
+        .filter(|entry| !removed.contains(&entry.slug))
+        .map(|entry| entry.slug.as_str())
+        .collect::<Vec<_>>();
+    if !dependents.is_empty() {
+        info!(
+            "{}",
+            cformat!(
+                "<y>Warning</> {} is required by {}",
+                slug,
+                dependents.join(", ")
+            )
+        );
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use ice::config::lock::LockedEntry;
+    use ice_util::fs::get_sha1_hash;
+
+    use super::*;
+    use crate::cli::temp_dir;
+
+    #[test]
+    fn test_remove_files() {
+        let dir = temp_dir("remove");
+        fs::write(
+            dir.join("mods.toml"),
+            r#"version = "1.21.6"
+loader = "fabric"
+
+[mods]
+sodium = "sodium1.0#1.0"
+iris = { version-id-number = "iris1.0#1.0", enabled = false }
+lithium = "lithium1.0#1.0"
+"#,
+        )
+        .unwrap();
+        // Renamed since it was installed, found by its hash
+        fs::write(dir.join("renamed.jar"), "sodium").unwrap();
+        fs::write(dir.join("renamed.jar.disabled"), "sodium").unwrap();
+        fs::write(dir.join("iris-1.0.jar.disabled"), "iris").unwrap();
+        fs::write(dir.join("lithium-1.0.jar"), "lithium").unwrap();
+        let hash = |name: &str| Some(get_sha1_hash(dir.join(name)).unwrap());
+        let lock = ModsLock::new(vec![
+            LockedEntry::test_mod("sodium", "1.0", hash("renamed.jar")),
+            LockedEntry::test_mod("iris", "1.0", hash("iris-1.0.jar.disabled")),
+            LockedEntry::test_mod("lithium", "1.0", hash("lithium-1.0.jar")),
+        ]);
+        let mut config = LocalModsConfig::load(dir.join("mods.toml")).unwrap();
+        lock.save(config.lock_path()).unwrap();
+
+        smol::block_on(remove(
+            &ModrinthClient::new(),
+            &CurseForgeClient::new(),
+            &GitHubClient::new(),
+            vec!["sodium".to_string(), "iris".to_string()],
+            None,
+            &dir,
+            &mut config,
+        ));
+
+        assert!(!dir.join("renamed.jar").exists());
+        assert!(!dir.join("renamed.jar.disabled").exists());
+        assert!(!dir.join("iris-1.0.jar.disabled").exists());
+        assert!(dir.join("lithium-1.0.jar").is_file());
+        let config = fs::read_to_string(dir.join("mods.toml")).unwrap();
+        assert!(!config.contains("sodium") && !config.contains("iris"));
+        assert!(config.contains("lithium = \"lithium1.0#1.0\""));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,284 @@
+use std::collections::HashSet;
+
+use color_print::cformat;
+use ice::{
+    config::{LocalModsConfig, lock::ModsLock},
+    core::{ContentKind, Mod},
+};
+use ice_api_tool::{curseforge::CurseForgeClient, github::GitHubClient, modrinth::ModrinthClient};
+use tracing::info;
+
+use super::{lock, modrinth::init_logger};
+
+/// The `tree` command
+///
+/// The dependencies are the required ones of the Modrinth versions and
+/// CurseForge files, resolved like for mods.lock. The entries added as
+/// dependencies are shown under the entries requiring them, the ones nothing
+/// requires any more are listed as orphans.
+pub async fn tree(
+    client: &ModrinthClient,
+    curseforge: &CurseForgeClient,
+    github: &GitHubClient,
+    config: &LocalModsConfig,
+) {
+    init_logger();
+
+    let lock = match lock::current(client, curseforge, github, config).await {
+        Ok(lock) => lock,
+        Err(err) => {
+            info!("{}", cformat!("<r>Failed</> {:?}", err));
+            return;
+        }
+    };
+
+    let orphans = orphans(&lock, config);
+    let kinds = config.kinds();
+    for kind in &kinds {
+        let kind = *kind;
+        if kinds.len() > 1 {
+            println!("{}", cformat!("<bold>[{}]</>", kind.table()));
+        }
+        let roots = top_level(kind, &entry_slugs(kind, config), config);
+        // The entries whose dependencies are shown already
+        let mut expanded = HashSet::new();
+        for root in &roots {
+            let repeated = !expanded.insert(root.clone());
+            println!("{}", label(&lock, kind, root, repeated, config));
+            if !repeated {
+                print_dependencies(&lock, kind, root, "", &mut expanded, config);
+            }
+        }
+
+        let orphans = orphans
+            .iter()
+            .filter_map(|(k, slug)| (*k == kind).then_some(slug))
+            .collect::<Vec<_>>();
+        if !orphans.is_empty() {
+            println!();
+            println!(
+                "{}",
+                cformat!(
+                    "<y>orphans</> (nothing requires them any more, remove them with `ice mod remove`)"
+                )
+            );
+            for slug in orphans {
+                println!("  {}", label(&lock, kind, slug, false, config));
+            }
+        }
+        if kinds.len() > 1 {
+            println!();
+        }
+    }
+}
+
+/// The `why` command
+///
+/// Every chain of requirements from an entry not added as a dependency to
+/// `slug` is shown, the shortest one of each entry.
+pub async fn why(
+    client: &ModrinthClient,
+    curseforge: &CurseForgeClient,
+    github: &GitHubClient,
+    slug: String,
+    kind: Option<ContentKind>,
+    config: &LocalModsConfig,
+) {
+    init_logger();
+
+    let kinds = kind
+        .map(|kind| vec![kind])
+        .unwrap_or(config.kinds())
+        .into_iter()
+        .filter(|kind| config.get_entry(*kind, &slug).is_some())
+        .collect::<Vec<_>>();
+    if kinds.is_empty() {
+        info!("{}", cformat!("<r>Failed</> {} is not in mods.toml", slug));
+        return;
+    }
+    let lock = match lock::current(client, curseforge, github, config).await {
+        Ok(lock) => lock,
+        Err(err) => {
+            info!("{}", cformat!("<r>Failed</> {:?}", err));
+            return;
+        }
+    };
+
+    for kind in &kinds {
+        let kind = *kind;
+        if kinds.len() > 1 {
+            println!("{}", cformat!("<bold>[{}]</>", kind.table()));
+        }
+        let roots = top_level(kind, &entry_slugs(kind, config), config);
+        let paths = roots
+            .iter()
+            .filter(|root| **root != slug)
+            .filter_map(|root| lock.requirement_path(kind, root, &slug))
+            .collect::<Vec<_>>();
+        let label = label(&lock, kind, &slug, false, config);
+
+        if roots.contains(&slug) {
+            println!("{label} is added by itself");
+        }
+        if !paths.is_empty() {
+            println!("{label} is required by");
+            for path in paths {
+                println!("  {}", path.join(" -> "));
+            }
+        } else if roots.contains(&slug) {
+            println!("nothing else requires it");
+        } else {
+            println!(
+                "{}",
+                cformat!(
+                    "{} is an <y>orphan</>, nothing requires it any more, remove it with `ice mod remove {}`",
+                    label,
+                    slug
+                )
+            );
+        }
+        if kinds.len() > 1 {
+            println!();
+        }
+    }
+}
+
+/// The keys of the entries of `kind`
+fn entry_slugs(kind: ContentKind, config: &LocalModsConfig) -> Vec<String> {
+    config
+        .get_entries(kind)
+        .iter()
+        .filter_map(|m| m.slug().map(|slug| slug.to_string()))
+        .collect()
+}
+
+/// The entries of `slugs` not added as dependencies of other entries
+fn top_level(kind: ContentKind, slugs: &[String], config: &LocalModsConfig) -> Vec<String> {
+    slugs
+        .iter()
+        .filter(|slug| kind != ContentKind::Mod || !config.is_dependency(slug))
+        .cloned()
+        .collect()
+}
+
+/// The entries added as dependencies that no entry added by itself requires,
+/// directly or not
+fn orphans(lock: &ModsLock, config: &LocalModsConfig) -> Vec<(ContentKind, String)> {
+    let mut orphans = vec![];
+    for kind in config.kinds() {
+        let slugs = entry_slugs(kind, config);
+        let roots = top_level(kind, &slugs, config);
+        let required = lock.required(kind, roots.iter().map(String::as_str));
+        orphans.extend(
+            slugs
+                .into_iter()
+                .filter(|slug| !roots.contains(slug) && !required.contains(slug.as_str()))
+                .map(|slug| (kind, slug)),
+        );
+    }
+    orphans
+}
+
+/// The slug and version of the entry `slug`, marked if its dependencies are
+/// shown elsewhere
+fn label(
+    lock: &ModsLock,
+    kind: ContentKind,
+    slug: &str,
+    repeated: bool,
+    config: &LocalModsConfig,
+) -> String {
+    let version = match (lock.get(kind, slug), config.get_entry(kind, slug)) {
+        (Some(entry), _) => entry
+            .version_number
+            .clone()
+            .or(entry.version_id.clone())
+            .unwrap_or_default(),
+        (None, Some(Mod::Local(m))) => m.path.display().to_string(),
+        _ => String::new(),
+    };
+    let repeated = repeated
+        && lock
+            .get(kind, slug)
+            .is_some_and(|e| !e.dependencies.is_empty());
+    match repeated {
+        true => cformat!("{} <dim>{} (*)</>", slug, version),
+        false => cformat!("{} <dim>{}</>", slug, version),
+    }
+}
+
+/// Print the dependencies of the entry `slug` under it, each entry's own
+/// dependencies only the first time it is shown
+fn print_dependencies(
+    lock: &ModsLock,
+    kind: ContentKind,
+    slug: &str,
+    prefix: &str,
+    expanded: &mut HashSet<String>,
+    config: &LocalModsConfig,
+) {
+    let Some(entry) = lock.get(kind, slug) else {
+        return;
+    };
+    for (i, dep) in entry.dependencies.iter().enumerate() {
+        let last = i + 1 == entry.dependencies.len();
+        let repeated = !expanded.insert(dep.clone());
+        let branch = if last { "└── " } else { "├── " };
+        println!(
+            "{prefix}{branch}{}",
+            label(lock, kind, dep, repeated, config)
+        );
+        if !repeated {
+            let prefix = format!("{prefix}{}", if last { "    " } else { "│   " });
+            print_dependencies(lock, kind, dep, &prefix, expanded, config);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use ice::config::lock::LockedEntry;
+
+    use super::*;
+    use crate::cli::temp_dir;
+
+    #[test]
+    fn test_orphans() {
+        let dir = temp_dir("tree");
+        fs::write(
+            dir.join("mods.toml"),
+            r#"version = "1.21.6"
+loader = "fabric"
+
+[mods]
+a = "a1#1.0"
+b = "b1#1.0"
+c = { version-id-number = "c1#1.0", dependency = true }
+d = { version-id-number = "d1#1.0", dependency = true }
+e = "e1#1.0"
+"#,
+        )
+        .unwrap();
+        let config = LocalModsConfig::load(dir.join("mods.toml")).unwrap();
+        let mut a = LockedEntry::test_mod("a", "1.0", None);
+        a.dependencies = vec!["b".to_string(), "c".to_string()];
+        let mut entries = vec![a];
+        entries.extend(["b", "c", "d", "e"].map(|slug| LockedEntry::test_mod(slug, "1.0", None)));
+        let lock = ModsLock::new(entries);
+
+        // b is required by a but was added by itself, so it stays a root
+        let roots = top_level(
+            ContentKind::Mod,
+            &entry_slugs(ContentKind::Mod, &config),
+            &config,
+        );
+        assert_eq!(roots, ["a", "b", "e"]);
+        assert_eq!(
+            orphans(&lock, &config),
+            [(ContentKind::Mod, "d".to_string())]
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,128 @@
+use std::path::Path;
+
+use anyhow::Context;
+use color_print::cformat;
+use ice::{
+    config::LocalModsConfig,
+    core::{ContentKind, FileHash, Mod, UrlMod},
+};
+use ice_api_tool::github::GitHubClient;
+use ice_util::{Checksum, download_verified};
+use indicatif::ProgressStyle;
+use tracing::{Instrument, info, info_span};
+use tracing_indicatif::span_ext::IndicatifSpanExt;
+
+use super::{github, maven};
+
+/// The url entries in the table of `kind`
+pub(crate) fn entries(kind: ContentKind, config: &LocalModsConfig) -> Vec<UrlMod> {
+    config
+        .get_entries(kind)
+        .into_iter()
+        .filter_map(|m| match m {
+            Mod::Url(m) => Some(m),
+            _ => None,
+        })
+        .collect()
+}
+
+/// A file of an entry downloaded straight from a url
+///
+/// The url entries are ones, and so are the GitHub and Maven entries once
+/// their release or artifact is resolved.
+pub(crate) struct RemoteFile {
+    pub slug: String,
+    pub url: String,
+    pub filename: String,
+    pub size: Option<u64>,
+    /// The file is only checked by its name if `None`
+    pub hash: Option<FileHash>,
+}
+
+impl RemoteFile {
+    /// What the file is checked against
+    pub fn checksum(&self) -> Checksum {
+        let (sha1, sha512) = match &self.hash {
+            Some(FileHash::Sha1(hash)) => (Some(hash.clone()), None),
+            Some(FileHash::Sha512(hash)) => (None, Some(hash.clone())),
+            None => (None, None),
+        };
+        Checksum {
+            size: self.size,
+            sha1,
+            sha512,
+        }
+    }
+}
+
+impl From<UrlMod> for RemoteFile {
+    fn from(m: UrlMod) -> Self {
+        Self {
+            filename: m.filename().to_string(),
+            slug: m.slug,
+            url: m.url,
+            size: None,
+            hash: Some(m.hash),
+        }
+    }
+}
+
+/// The remote files of the url, GitHub and Maven entries of `kind`
+pub(crate) async fn remote_files(
+    github: &GitHubClient,
+    kind: ContentKind,
+    config: &LocalModsConfig,
+) -> Result<Vec<RemoteFile>, anyhow::Error> {
+    let mut files = entries(kind, config)
+        .into_iter()
+        .map(RemoteFile::from)
+        .collect::<Vec<_>>();
+    let (github_files, maven_files) = futures::try_join!(
+        github::resolve_files(github, kind, config),
+        maven::resolve_files(kind, config)
+    )?;
+    files.extend(github_files);
+    files.extend(maven_files);
+    Ok(files)
+}
+
+/// Report the existing file at `path` if it does not match `checksum`, it is
+/// about to be downloaded again
+pub(crate) fn report_mismatch(path: &Path, checksum: &Checksum) {
+    if let Ok(Some(mismatch)) = checksum.mismatch(path) {
+        info!(
+            "{}",
+            cformat!(
+                "<y>Redownloading</> {} ({})",
+                path.file_name().unwrap().to_string_lossy(),
+                mismatch
+            )
+        );
+    }
+}
+
+/// Download a remote file into `dir` and verify it
+///
+/// The file is downloaded next to its final path and only moved there once
+/// it matches, see [`download_verified`].
+///
+/// This function creates a span with spinner
+pub(crate) async fn download(m: &RemoteFile, dir: &Path) -> Result<(), anyhow::Error> {
+    let span = info_span!("downloading", file = m.filename);
+    span.pb_set_style(
+        &ProgressStyle::with_template(
+            "{span_child_prefix}{spinner} {span_name:.bold}{{{span_fields}}}:\n  {msg:.dim}",
+        )
+        .unwrap(),
+    );
+    let path = dir.join(&m.filename);
+    let checksum = m.checksum();
+    report_mismatch(&path, &checksum);
+    async {
+        download_verified(&m.url, path, &checksum, |_| {})
+            .await
+            .context("failed to download from url")
+    }
+    .instrument(span)
+    .await
+}
@@ -0,0 +1,241 @@
+//! The `version` constraint of an entry of mods.toml
+//!
+//! The entry still pins one version, the constraint only limits which
+//! versions `update` may move it to.
+
+use std::{
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+};
+
+use anyhow::bail;
+
+/// The versions an entry may be updated to
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum VersionConstraint {
+    /// `"*"` or `"latest"`, any version
+    #[default]
+    Any,
+    /// `"^0.5"`, the versions not changing the first non-zero number, and
+    /// not older than the given one
+    Caret(ModVersion),
+    /// `"~3.0"`, the versions not changing the first two numbers (the first
+    /// one if only one is given), and not older than the given one
+    Tilde(ModVersion),
+    /// `"pinned:<id>"`, only the version with the id: the Modrinth version
+    /// id, the CurseForge file id, the GitHub tag or the Maven version
+    Pinned(String),
+}
+
+impl VersionConstraint {
+    /// Whether the version with `id` and `number` is allowed
+    ///
+    /// `number` is parsed leniently, see [`ModVersion::parse`]. A number that
+    /// cannot be parsed is only allowed by [`VersionConstraint::Any`].
+    pub fn allows(&self, id: &str, number: &str, game_version: &str) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Pinned(pinned) => pinned == id,
+            Self::Caret(req) | Self::Tilde(req) => {
+                let Some(version) = ModVersion::parse(number, game_version) else {
+                    return false;
+                };
+                // The numbers that must stay the same
+                let fixed = match self {
+                    Self::Caret(_) => req
+                        .0
+                        .iter()
+                        .position(|n| *n != 0)
+                        .unwrap_or(req.0.len() - 1),
+                    _ => req.0.len().min(2) - 1,
+                };
+                version >= *req && (0..=fixed).all(|i| version.get(i) == req.get(i))
+            }
+        }
+    }
+}
+
+impl FromStr for VersionConstraint {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let numbers = |req: &str| {
+            ModVersion::parse_exact(req.trim()).ok_or(anyhow::anyhow!(
+                "`{s}` should be followed by numbers like `0.5`"
+            ))
+        };
+        Ok(match s {
+            "*" | "latest" => Self::Any,
+            _ if s.starts_with('^') => Self::Caret(numbers(&s[1..])?),
+            _ if s.starts_with('~') => Self::Tilde(numbers(&s[1..])?),
+            _ => match s.strip_prefix("pinned:") {
+                Some(id) if !id.trim().is_empty() => Self::Pinned(id.trim().to_string()),
+                _ => bail!(
+                    "invalid version constraint `{s}`, expected `*`, `latest`, `^0.5`, `~3.0` or `pinned:<id>`"
+                ),
+            },
+        })
+    }
+}
+
+impl Display for VersionConstraint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Any => f.write_str("*"),
+            Self::Caret(version) => write!(f, "^{version}"),
+            Self::Tilde(version) => write!(f, "~{version}"),
+            Self::Pinned(id) => write!(f, "pinned:{id}"),
+        }
+    }
+}
+
+/// The numbers of a mod version, compared as if padded with zeros
+#[derive(Debug, Clone, Eq)]
+pub struct ModVersion(pub Vec<u64>);
+
+impl ModVersion {
+    /// Find the mod version in a version number like `mc1.21-0.5.11`,
+    /// `0.100.7+1.21` or `fabric-1.21-rc1-6.0.0`
+    ///
+    /// The build metadata after `+` is dropped, and the rest is split on `-`,
+    /// `_` and spaces. Every part starting with numbers (after an optional
+    /// `v`) is a candidate, except the ones that are `game_version` if there
+    /// are others. The candidate with the most numbers wins, the last one on
+    /// a tie.
+    pub fn parse(s: &str, game_version: &str) -> Option<Self> {
+        let s = s.split('+').next().unwrap_or_default();
+        let candidates = s
+            .split(['-', '_', ' '])
+            .filter_map(|part| {
+                let part = part.strip_prefix(['v', 'V']).unwrap_or(part);
+                Self::parse_prefix(part)
+            })
+            .collect::<Vec<_>>();
+        let game_version = Self::parse_exact(game_version);
+        let is_game_version = |v: &ModVersion| game_version.as_ref().is_some_and(|g| g == v);
+        let candidates = match candidates.iter().all(is_game_version) {
+            true => candidates,
+            false => candidates
+                .into_iter()
+                .filter(|v| !is_game_version(v))
+                .collect(),
+        };
+        candidates.into_iter().max_by_key(|v| v.0.len())
+    }
+
+    /// Parse numbers separated by `.`, and nothing else
+    fn parse_exact(s: &str) -> Option<Self> {
+        s.split('.')
+            .map(|n| n.parse::<u64>().ok())
+            .collect::<Option<Vec<_>>>()
+            .map(Self)
+    }
+
+    /// Parse the numbers separated by `.` the string starts with, like `1.2`
+    /// in `1.2.jar` or `1.2beta`
+    fn parse_prefix(s: &str) -> Option<Self> {
+        let mut numbers = vec![];
+        for part in s.split('.') {
+            let digits = part.len() - part.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+            if digits == 0 {
+                break;
+            }
+            numbers.push(part[..digits].parse().ok()?);
+            if digits != part.len() {
+                break;
+            }
+        }
+        (!numbers.is_empty()).then_some(Self(numbers))
+    }
+
+    /// The number at `i`, 0 if there is none
+    fn get(&self, i: usize) -> u64 {
+        self.0.get(i).copied().unwrap_or(0)
+    }
+}
+
+impl PartialEq for ModVersion {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl PartialOrd for ModVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ModVersion {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        let len = self.0.len().max(other.0.len());
+        (0..len)
+            .map(|i| self.get(i).cmp(&other.get(i)))
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(std::cmp::Ordering::Equal)
+    }
+}
+
+impl Display for ModVersion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let numbers = self.0.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+        f.write_str(&numbers.join("."))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn version(s: &str) -> Option<String> {
+        ModVersion::parse(s, "1.21").map(|v| v.to_string())
+    }
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(version("mc1.21-0.5.11").as_deref(), Some("0.5.11"));
+        assert_eq!(version("0.100.7+1.21").as_deref(), Some("0.100.7"));
+        assert_eq!(version("3.0.0-beta.5+1.21").as_deref(), Some("3.0.0"));
+        assert_eq!(version("fabric-1.21-rc1-6.0.0").as_deref(), Some("6.0.0"));
+        assert_eq!(version("1.21-1.13.1").as_deref(), Some("1.13.1"));
+        assert_eq!(version("v1.2.0").as_deref(), Some("1.2.0"));
+        assert_eq!(version("Sodium 0.5.11").as_deref(), Some("0.5.11"));
+        assert_eq!(
+            version("jei-1.21-fabric-19.21.0.jar").as_deref(),
+            Some("19.21.0")
+        );
+        assert_eq!(version("1.21").as_deref(), Some("1.21"));
+        assert_eq!(version("release"), None);
+        assert_eq!(ModVersion(vec![1, 2]), ModVersion(vec![1, 2, 0]));
+        assert!(ModVersion(vec![0, 10]) > ModVersion(vec![0, 9, 3]));
+    }
+
+    #[test]
+    fn test_constraint() {
+        let parse = |s: &str| s.parse::<VersionConstraint>().unwrap();
+        assert_eq!(parse("latest"), VersionConstraint::Any);
+        assert_eq!(parse("^0.5").to_string(), "^0.5");
+        assert_eq!(
+            parse("pinned:AANobbMI"),
+            VersionConstraint::Pinned("AANobbMI".to_string())
+        );
+        assert!("0.5".parse::<VersionConstraint>().is_err());
+        assert!("^x".parse::<VersionConstraint>().is_err());
+        assert!("pinned:".parse::<VersionConstraint>().is_err());
+
+        let allows = |c: &str, number: &str| parse(c).allows("id", number, "1.21");
+        assert!(allows("*", "anything"));
+        assert!(allows("^0.5", "mc1.21-0.5.11"));
+        assert!(!allows("^0.5", "mc1.21-0.6.0"));
+        assert!(!allows("^0.5", "mc1.21-0.4.9"));
+        assert!(allows("^1.2", "1.9.0+1.21"));
+        assert!(!allows("^1.2", "2.0.0+1.21"));
+        assert!(allows("~3.0", "3.0.4-beta.1"));
+        assert!(!allows("~3.0", "3.1.0"));
+        assert!(allows("~3", "3.1.0"));
+        assert!(!allows("^0.5", "release"));
+        assert!(parse("pinned:id").allows("id", "1.0", "1.21"));
+        assert!(!parse("pinned:other").allows("id", "1.0", "1.21"));
+    }
+}
@@ -0,0 +1,417 @@
+//! `mods.lock`, the resolved files of the entries of a mods.toml
+//!
+//! It sits next to mods.toml and is rewritten as a whole whenever the
+//! entries are resolved. The entries are sorted and every key has a fixed
+//! place, so resolving the same mods.toml twice gives the same file.
+
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fs, io,
+    path::Path,
+};
+
+use ice_util::Checksum;
+use serde::{Deserialize, Serialize};
+
+use crate::core::{ContentKind, FileHash, Mod, ModSide};
+
+use super::ModsConfig;
+
+/// The version of the format of `mods.lock`
+pub const LOCK_VERSION: u32 = 1;
+
+const HEADER: &str = "# This file is generated by ice, do not edit it by hand.\n\n";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModsLock {
+    pub version: u32,
+    #[serde(default, rename = "entry")]
+    pub entries: Vec<LockedEntry>,
+}
+
+/// Where a locked file comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LockSource {
+    Modrinth,
+    CurseForge,
+    Url,
+    GitHub,
+    Maven,
+}
+
+/// The resolved file of an entry of mods.toml
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct LockedEntry {
+    pub kind: ContentKind,
+    pub slug: String,
+    pub source: LockSource,
+    /// What the entry pins in its source: the Modrinth version id, the
+    /// CurseForge file id, the GitHub tag or the Maven coordinates
+    pub version_id: Option<String>,
+    pub version_number: Option<String>,
+    pub filename: String,
+    /// `None` if the file cannot be downloaded by other apps
+    pub url: Option<String>,
+    pub size: Option<u64>,
+    pub sha1: Option<String>,
+    pub sha512: Option<String>,
+    pub side: ModSide,
+    /// The entries of the same table the required dependencies resolve to
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dependencies: Vec<String>,
+}
+
+impl LockedEntry {
+    /// The strongest hash of the file, if any is known
+    pub fn hash(&self) -> Option<FileHash> {
+        self.sha512
+            .clone()
+            .map(FileHash::Sha512)
+            .or(self.sha1.clone().map(FileHash::Sha1))
+    }
+
+    /// What the file is checked against
+    pub fn checksum(&self) -> Checksum {
+        Checksum {
+            size: self.size,
+            sha1: self.sha1.clone(),
+            sha512: self.sha512.clone(),
+        }
+    }
+
+    /// Whether this is still the resolution of the entry `m`
+    pub fn matches(&self, m: &Mod) -> bool {
+        let version_id = self.version_id.as_deref();
+        match m {
+            Mod::Modrinth(m) => {
+                self.source == LockSource::Modrinth && version_id == Some(m.version_id())
+            }
+            Mod::CurseForge(m) => {
+                self.source == LockSource::CurseForge
+                    && version_id == Some(m.file_id.to_string().as_str())
+            }
+            Mod::GitHub(m) => self.source == LockSource::GitHub && version_id == Some(&m.tag),
+            Mod::Maven(m) => {
+                self.source == LockSource::Maven
+                    && version_id == Some(m.coordinates.to_string().as_str())
+            }
+            Mod::Url(m) => {
+                let locked_hash = match m.hash {
+                    FileHash::Sha1(_) => self.sha1.clone().map(FileHash::Sha1),
+                    FileHash::Sha512(_) => self.sha512.clone().map(FileHash::Sha512),
+                };
+                self.source == LockSource::Url
+                    && self.url.as_ref() == Some(&m.url)
+                    && self.filename == m.filename()
+                    && locked_hash.is_some_and(|hash| hash.same_as(&m.hash))
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(any(test, feature = "test-util"))]
+impl LockedEntry {
+    /// A Modrinth mod locked to the version `{slug}{version}`, whose file
+    /// `{slug}-{version}.jar` no one serves, for the tests
+    pub fn test_mod(slug: &str, version: &str, sha1: Option<String>) -> Self {
+        LockedEntry {
+            kind: ContentKind::Mod,
+            slug: slug.to_string(),
+            source: LockSource::Modrinth,
+            version_id: Some(format!("{slug}{version}")),
+            version_number: Some(version.to_string()),
+            filename: format!("{slug}-{version}.jar"),
+            url: Some(format!("http://127.0.0.1:9/{slug}-{version}.jar")),
+            size: None,
+            sha1,
+            sha512: None,
+            side: ModSide::Both,
+            dependencies: vec![],
+        }
+    }
+}
+
+impl ModsLock {
+    /// A lock of `entries`, sorted by their kinds and slugs
+    pub fn new(mut entries: Vec<LockedEntry>) -> Self {
+        entries.sort_by(|a, b| (a.kind, &a.slug).cmp(&(b.kind, &b.slug)));
+        Self {
+            version: LOCK_VERSION,
+            entries,
+        }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, anyhow::Error> {
+        let lock = toml::from_str::<Self>(&fs::read_to_string(path)?)?;
+        if lock.version != LOCK_VERSION {
+            anyhow::bail!("unsupported mods.lock version {}", lock.version);
+        }
+        Ok(lock)
+    }
+
+    /// Write the lock to `path`, unless it already has the same content
+    ///
+    /// Returns whether the file is written.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<bool> {
+        let content = self.to_string();
+        let path = path.as_ref();
+        if fs::read_to_string(path).is_ok_and(|old| old == content) {
+            return Ok(false);
+        }
+        fs::write(path, content)?;
+        Ok(true)
+    }
+
+    /// The locked entries of `kind`
+    pub fn entries(&self, kind: ContentKind) -> impl Iterator<Item = &LockedEntry> {
+        self.entries.iter().filter(move |e| e.kind == kind)
+    }
+
+    pub fn get(&self, kind: ContentKind, slug: &str) -> Option<&LockedEntry> {
+        self.entries(kind).find(|e| e.slug == slug)
+    }
+
+    /// The locked entries of `kind` that require the entry `slug`
+    pub fn dependents<'a>(
+        &'a self,
+        kind: ContentKind,
+        slug: &'a str,
+    ) -> impl Iterator<Item = &'a LockedEntry> {
+        self.entries(kind)
+            .filter(move |e| e.dependencies.iter().any(|dep| dep == slug))
+    }
+
+    /// The entries of `kind` the entries `roots` require, transitively
+    ///
+    /// The roots are only in it if another root requires them.
+    pub fn required<'a>(
+        &'a self,
+        kind: ContentKind,
+        roots: impl IntoIterator<Item = &'a str>,
+    ) -> HashSet<&'a str> {
+        let mut required = HashSet::new();
+        let mut queue = roots
+            .into_iter()
+            .filter_map(|slug| self.get(kind, slug))
+            .collect::<VecDeque<_>>();
+        while let Some(entry) = queue.pop_front() {
+            for dep in &entry.dependencies {
+                if required.insert(dep.as_str())
+                    && let Some(dep) = self.get(kind, dep)
+                {
+                    queue.push_back(dep);
+                }
+            }
+        }
+        required
+    }
+
+    /// The shortest chain of requirements from the entry `from` of `kind`
+    /// to the entry `to`, both included
+    pub fn requirement_path<'a>(
+        &'a self,
+        kind: ContentKind,
+        from: &'a str,
+        to: &str,
+    ) -> Option<Vec<&'a str>> {
+        // The entry each entry is first reached from
+        let mut parents = HashMap::from([(from, from)]);
+        let mut queue = VecDeque::from([from]);
+        while let Some(slug) = queue.pop_front() {
+            if slug == to {
+                let mut path = vec![slug];
+                let mut cur = slug;
+                while cur != from {
+                    cur = parents[cur];
+                    path.push(cur);
+                }
+                path.reverse();
+                return Some(path);
+            }
+            let Some(entry) = self.get(kind, slug) else {
+                continue;
+            };
+            for dep in &entry.dependencies {
+                if !parents.contains_key(dep.as_str()) {
+                    parents.insert(dep, slug);
+                    queue.push_back(dep);
+                }
+            }
+        }
+        None
+    }
+
+    /// The entries the lock is out of date for, as `table.slug`
+    ///
+    /// That is the entries of `config` without a matching locked entry, and
+    /// the locked entries no longer in `config`. The path entries are never
+    /// locked.
+    pub fn outdated_entries(&self, config: &ModsConfig) -> Vec<String> {
+        let mut outdated = vec![];
+        for kind in config.kinds() {
+            for m in config.get_entries(kind) {
+                let Some(slug) = m.slug() else {
+                    continue;
+                };
+                if matches!(m, Mod::Local(_)) {
+                    continue;
+                }
+                if !self.get(kind, slug).is_some_and(|e| e.matches(&m)) {
+                    outdated.push(format!("{}.{slug}", kind.table()));
+                }
+            }
+        }
+        for e in &self.entries {
+            if config.get_entry(e.kind, &e.slug).is_none() {
+                outdated.push(format!("{}.{}", e.kind.table(), e.slug));
+            }
+        }
+        outdated
+    }
+}
+
+impl std::fmt::Display for ModsLock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(HEADER)?;
+        f.write_str(&toml::to_string(self).map_err(|_| std::fmt::Error)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn entry(slug: &str, version_id: &str) -> LockedEntry {
+        LockedEntry {
+            kind: ContentKind::Mod,
+            slug: slug.to_string(),
+            source: LockSource::Modrinth,
+            version_id: Some(version_id.to_string()),
+            version_number: Some("1.0.0".to_string()),
+            filename: format!("{slug}-1.0.0.jar"),
+            url: Some(format!("https://cdn.modrinth.com/{slug}-1.0.0.jar")),
+            size: Some(1024),
+            sha1: Some("abc".to_string()),
+            sha512: Some("def".to_string()),
+            side: ModSide::Both,
+            dependencies: vec![],
+        }
+    }
+
+    #[test]
+    fn test_lock_format() {
+        let mut sodium = entry("sodium", "AANobbMI");
+        sodium.dependencies = vec!["fabric-api".to_string()];
+        let lock = ModsLock::new(vec![sodium, entry("fabric-api", "P7uGFii0")]);
+        assert_eq!(lock.entries[0].slug, "fabric-api");
+
+        let content = lock.to_string();
+        assert_eq!(
+            content,
+            r#"# This file is generated by ice, do not edit it by hand.
+
+version = 1
+
+[[entry]]
+kind = "mod"
+slug = "fabric-api"
+source = "modrinth"
+version-id = "P7uGFii0"
+version-number = "1.0.0"
+filename = "fabric-api-1.0.0.jar"
+url = "https://cdn.modrinth.com/fabric-api-1.0.0.jar"
+size = 1024
+sha1 = "abc"
+sha512 = "def"
+side = "both"
+
+[[entry]]
+kind = "mod"
+slug = "sodium"
+source = "modrinth"
+version-id = "AANobbMI"
+version-number = "1.0.0"
+filename = "sodium-1.0.0.jar"
+url = "https://cdn.modrinth.com/sodium-1.0.0.jar"
+size = 1024
+sha1 = "abc"
+sha512 = "def"
+side = "both"
+dependencies = ["fabric-api"]
+"#
+        );
+        assert_eq!(toml::from_str::<ModsLock>(&content).unwrap(), lock);
+    }
+
+    #[test]
+    fn test_dependencies() {
+        let mut modmenu = entry("modmenu", "a");
+        modmenu.dependencies = vec!["cloth-config".to_string(), "fabric-api".to_string()];
+        let mut cloth_config = entry("cloth-config", "b");
+        cloth_config.dependencies = vec!["fabric-api".to_string()];
+        let mut old = entry("old", "c");
+        old.dependencies = vec!["older".to_string()];
+        let lock = ModsLock::new(vec![
+            modmenu,
+            cloth_config,
+            entry("fabric-api", "d"),
+            old,
+            entry("older", "e"),
+        ]);
+
+        let dependents = lock
+            .dependents(ContentKind::Mod, "fabric-api")
+            .map(|e| e.slug.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(dependents, vec!["cloth-config", "modmenu"]);
+        assert_eq!(
+            lock.required(ContentKind::Mod, ["modmenu"]),
+            HashSet::from(["cloth-config", "fabric-api"])
+        );
+        assert_eq!(
+            lock.requirement_path(ContentKind::Mod, "modmenu", "fabric-api"),
+            Some(vec!["modmenu", "fabric-api"])
+        );
+        assert_eq!(
+            lock.requirement_path(ContentKind::Mod, "cloth-config", "fabric-api"),
+            Some(vec!["cloth-config", "fabric-api"])
+        );
+        assert_eq!(
+            lock.requirement_path(ContentKind::Mod, "modmenu", "older"),
+            None
+        );
+        assert_eq!(
+            lock.requirement_path(ContentKind::Mod, "old", "old"),
+            Some(vec!["old"])
+        );
+    }
+
+    #[test]
+    fn test_outdated_entries() {
+        let config = ModsConfig::from_str(
+            r#"version = "1.21.1"
+loader = "fabric"
+
+[mods]
+sodium = "AANobbMI#1.0.0"
+lithium = "gvQqBUqZ#0.15.0"
+mine = { path = "vendor/mine.jar" }
+"#,
+        )
+        .unwrap();
+        let lock = ModsLock::new(vec![
+            entry("sodium", "AANobbMI"),
+            entry("lithium", "old"),
+            entry("iris", "YL57xq9U"),
+        ]);
+        assert_eq!(
+            lock.outdated_entries(&config),
+            vec!["mods.lithium".to_string(), "mods.iris".to_string()]
+        );
+    }
+}
@@ -3,28 +3,153 @@ use std::{
     fmt::Display,
     fs,
     ops::{Deref, DerefMut},
-    path::{Path, PathBuf},
+    path::{Component, Path, PathBuf},
     str::FromStr,
 };
 
+pub mod constraint;
+pub mod lock;
+
+use anyhow::bail;
+use constraint::VersionConstraint;
+use ice_api_tool::{
+    maven::Coordinates,
+    modrinth::types::{Project, VersionType},
+};
 use ice_core::ServerLoader;
 use serde::{Deserialize, Serialize};
 
-use crate::core::{Mod, ModrinthMod};
+use crate::core::{
+    ContentKind, CurseForgeMod, FileHash, GitHubMod, LocalMod, MavenMod, Mod, ModSide, ModrinthMod,
+    Side, UrlMod, url_filename,
+};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TomlModsManifest {
     pub version: String,
     pub loader: ServerLoader,
+    /// The default release channel of the mods, any channel if `None`
+    pub channel: Option<VersionType>,
+    /// The default side to sync for, every side if `None`
+    pub side: Option<Side>,
     pub mods: BTreeMap<String, TomlMod>,
+    /// Only managed if the table exists, same for `shaderpacks` and `datapacks`
+    pub resourcepacks: Option<BTreeMap<String, TomlMod>>,
+    pub shaderpacks: Option<BTreeMap<String, TomlMod>>,
+    pub datapacks: Option<BTreeMap<String, TomlMod>>,
+    /// The named selections of groups to sync, see [`TomlProfile`]
+    pub profiles: Option<BTreeMap<String, TomlProfile>>,
 
     #[serde(skip_serializing)]
     #[serde(flatten)]
     pub _unused_keys: BTreeMap<String, toml::Value>,
 }
 
+impl TomlModsManifest {
+    /// The table of `kind`, `None` if it does not exist
+    pub fn table(&self, kind: ContentKind) -> Option<&BTreeMap<String, TomlMod>> {
+        match kind {
+            ContentKind::Mod => Some(&self.mods),
+            ContentKind::ResourcePack => self.resourcepacks.as_ref(),
+            ContentKind::ShaderPack => self.shaderpacks.as_ref(),
+            ContentKind::Datapack => self.datapacks.as_ref(),
+        }
+    }
+
+    /// Check what the types cannot tell
+    ///
+    /// - A `url` entry needs a `sha1` or `sha512` pin
+    /// - An entry cannot have both a `url` and a `path`
+    /// - The file name of a `url` or `path` entry must be a plain file name
+    /// - A `github` entry needs a `tag`, a `maven` entry valid `coordinates`
+    /// - A `version` must be a valid [`VersionConstraint`]
+    pub fn validate(&self) -> Result<(), anyhow::Error> {
+        for kind in self.kinds() {
+            for (key, value) in self.table(kind).into_iter().flatten() {
+                let TomlMod::Detailed(detailed) = value else {
+                    continue;
+                };
+                if let Some(version) = &detailed.version
+                    && let Err(err) = version.parse::<VersionConstraint>()
+                {
+                    bail!("`{}.{key}` has an invalid `version`: {err}", kind.table());
+                }
+                if detailed.github.is_some() && detailed.tag.is_none() {
+                    bail!("`{}.{key}` has a `github` but no `tag`", kind.table());
+                }
+                if detailed.maven.is_some() {
+                    let Some(coordinates) = &detailed.coordinates else {
+                        bail!(
+                            "`{}.{key}` has a `maven` but no `coordinates`",
+                            kind.table()
+                        );
+                    };
+                    if let Err(err) = coordinates.parse::<Coordinates>() {
+                        bail!("`{}.{key}` has invalid `coordinates`: {err}", kind.table());
+                    }
+                }
+                if !detailed.is_file() {
+                    continue;
+                }
+                let filename = match (detailed.url.as_ref(), detailed.path.as_ref()) {
+                    (Some(_), Some(_)) => {
+                        bail!("`{}.{key}` has both a `url` and a `path`", kind.table())
+                    }
+                    (Some(url), None) => {
+                        if detailed.sha1.is_none() && detailed.sha512.is_none() {
+                            bail!(
+                                "`{}.{key}` has a `url` but no `sha1` or `sha512` to verify it",
+                                kind.table()
+                            );
+                        }
+                        detailed.filename.as_deref().unwrap_or(url_filename(url))
+                    }
+                    (None, Some(path)) => detailed
+                        .filename
+                        .as_deref()
+                        .or(path.file_name().and_then(|name| name.to_str()))
+                        .unwrap_or_default(),
+                    (None, None) => continue,
+                };
+                let mut components = Path::new(filename).components();
+                if !matches!(
+                    (components.next(), components.next()),
+                    (Some(Component::Normal(_)), None)
+                ) {
+                    bail!(
+                        "`{}.{key}` would be saved as `{filename}`, set `filename` to a plain file name",
+                        kind.table()
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The kinds whose table exists, `Mod` is always included
+    pub fn kinds(&self) -> Vec<ContentKind> {
+        ContentKind::ALL
+            .into_iter()
+            .filter(|kind| self.table(*kind).is_some())
+            .collect()
+    }
+}
+
+/// A profile of mods.toml, `ice mod sync --profile <name>` installs the
+/// entries it selects
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TomlProfile {
+    /// The groups whose entries are installed, the entries without a group
+    /// are installed anyway unless they are `optional`
+    #[serde(default)]
+    pub groups: Vec<String>,
+    /// The side to sync for, overriding the manifest one
+    pub side: Option<Side>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(untagged)]
+#[allow(clippy::large_enum_variant)]
 pub enum TomlMod {
     Simple(String),
     Detailed(TomlDetailedMod),
@@ -33,10 +158,78 @@ pub enum TomlMod {
 impl From<(String, TomlMod)> for Mod {
     fn from((slug, value): (String, TomlMod)) -> Self {
         match value {
+            // Not pinned yet, see `ModsConfig::unpinned_entries`
+            TomlMod::Simple(simple) if simple.parse::<VersionConstraint>().is_ok() => Mod::Unknown,
             TomlMod::Simple(version_id_number) => Mod::Modrinth(ModrinthMod {
                 slug,
                 version_id_number,
             }),
+            TomlMod::Detailed(TomlDetailedMod {
+                curseforge: Some(project_id),
+                file_id: Some(file_id),
+                ..
+            }) => Mod::CurseForge(CurseForgeMod {
+                slug,
+                project_id,
+                file_id,
+            }),
+            TomlMod::Detailed(TomlDetailedMod {
+                github: Some(repo),
+                tag: Some(tag),
+                asset,
+                ..
+            }) => Mod::GitHub(GitHubMod {
+                slug,
+                repo,
+                tag,
+                asset_pattern: asset,
+            }),
+            TomlMod::Detailed(TomlDetailedMod {
+                maven: Some(repository),
+                coordinates: Some(coordinates),
+                ..
+            }) => match coordinates.parse() {
+                Ok(coordinates) => Mod::Maven(MavenMod {
+                    slug,
+                    repository,
+                    coordinates,
+                }),
+                // Rejected by `TomlModsManifest::validate`
+                Err(_) => Mod::Unknown,
+            },
+            TomlMod::Detailed(TomlDetailedMod {
+                version_id_number: None,
+                url: None,
+                path: Some(path),
+                sha1,
+                sha512,
+                filename,
+                symlink,
+                ..
+            }) => Mod::Local(LocalMod {
+                slug,
+                path,
+                hash: sha512.map(FileHash::Sha512).or(sha1.map(FileHash::Sha1)),
+                filename,
+                symlink: symlink.unwrap_or_default(),
+            }),
+            TomlMod::Detailed(TomlDetailedMod {
+                version_id_number: None,
+                url: Some(url),
+                sha1,
+                sha512,
+                filename,
+                ..
+            }) => match sha512.map(FileHash::Sha512).or(sha1.map(FileHash::Sha1)) {
+                Some(hash) => Mod::Url(UrlMod {
+                    slug,
+                    url,
+                    hash,
+                    filename,
+                }),
+                // Rejected by `TomlModsManifest::validate`
+                None => Mod::Unknown,
+            },
             TomlMod::Detailed(detailed) => {
                 if let Some(version_id_number) = detailed.version_id_number {
                     Mod::Modrinth(ModrinthMod {
@@ -56,13 +249,72 @@ impl From<(String, TomlMod)> for Mod {
 pub struct TomlDetailedMod {
     pub version_id_number: Option<String>,
     // pub strict_match_game_version: Option<bool>,
+    /// Where to download the file from, if there is no `version-id-number`
     pub url: Option<String>,
+    /// Whether the mod was pulled in as a dependency of another mod
+    /// rather than explicitly requested
+    pub dependency: Option<bool>,
+    /// The release channel of this mod, overriding the manifest one
+    pub channel: Option<VersionType>,
+    /// The versions `update` may move this entry to, see [`VersionConstraint`]
+    pub version: Option<String>,
+    /// The sides this mod is installed on, overriding Modrinth's
+    /// `client_side` and `server_side`
+    pub side: Option<ModSide>,
+    /// The groups this mod is in, for the profiles to select
+    pub groups: Option<Vec<String>>,
+    /// Only install this mod with a profile selecting one of its groups
+    pub optional: Option<bool>,
+    /// Never install this mod if `false`, its file is kept as `.disabled`
+    pub enabled: Option<bool>,
+    /// The CurseForge project id, the mod is from CurseForge if set
+    pub curseforge: Option<u32>,
+    /// The CurseForge file id
+    pub file_id: Option<u32>,
+    /// The GitHub repository (`owner/name`) whose release asset is the file
+    pub github: Option<String>,
+    /// The tag of the GitHub release
+    pub tag: Option<String>,
+    /// The glob the name of the GitHub release asset matches
+    pub asset: Option<String>,
+    /// The base url of the Maven repository the file is in
+    pub maven: Option<String>,
+    /// The Maven coordinates of the file, `group:artifact:version[:classifier]`
+    pub coordinates: Option<String>,
+    /// A file relative to mods.toml to install, if there is no `version-id-number`
+    pub path: Option<PathBuf>,
+    /// Symlink the file of a `path` entry instead of copying it
+    pub symlink: Option<bool>,
+    /// The expected hashes of a `url` or `path` entry, one of them is
+    /// required for a `url` entry
+    pub sha1: Option<String>,
+    pub sha512: Option<String>,
+    /// The name to save the file of a `url` or `path` entry as
+    pub filename: Option<String>,
 
     #[serde(skip_serializing)]
     #[serde(flatten)]
     pub _unused_keys: BTreeMap<String, toml::Value>,
 }
 
+impl TomlDetailedMod {
+    /// Whether this is a `url` or `path` entry, a Modrinth entry may also have a `url`
+    fn is_file(&self) -> bool {
+        (self.url.is_some() || self.path.is_some())
+            && self.version_id_number.is_none()
+            && self.curseforge.is_none()
+    }
+}
+
+/// The `sha1` and `sha512` keys of an entry pinned by `hash`
+fn hash_keys(hash: Option<FileHash>) -> (Option<toml_edit::Value>, Option<toml_edit::Value>) {
+    match hash {
+        Some(FileHash::Sha1(hash)) => (Some(hash.into()), None),
+        Some(FileHash::Sha512(hash)) => (None, Some(hash.into())),
+        None => (None, None),
+    }
+}
+
 /// A wrapper around the toml config data in order to
 /// support format preserving edits while still
 /// providing easy access to the data.
@@ -82,7 +334,13 @@ impl ModsConfig {
         let config = TomlModsManifest {
             version,
             loader,
+            channel: None,
+            side: None,
             mods: BTreeMap::new(),
+            resourcepacks: None,
+            shaderpacks: None,
+            datapacks: None,
+            profiles: None,
             _unused_keys: BTreeMap::new(),
         };
 
@@ -90,39 +348,465 @@ impl ModsConfig {
     }
 
     pub fn get_mod(&self, key: &str) -> Option<Mod> {
-        self.mods
+        self.get_entry(ContentKind::Mod, key)
+    }
+
+    pub fn get_mods(&self) -> Vec<Mod> {
+        self.get_entries(ContentKind::Mod)
+    }
+
+    /// Get the entry `key` in the table of `kind`
+    pub fn get_entry(&self, kind: ContentKind, key: &str) -> Option<Mod> {
+        self.table(kind)?
             .get(key)
             .map(|v| (key.to_string(), v.clone()).into())
     }
 
-    pub fn get_mods(&self) -> Vec<Mod> {
-        self.mods
-            .iter()
+    /// Get the entries in the table of `kind`, empty if it does not exist
+    pub fn get_entries(&self, kind: ContentKind) -> Vec<Mod> {
+        self.table(kind)
+            .into_iter()
+            .flatten()
             .map(|(k, v)| (k.clone(), v.clone()).into())
             .collect()
     }
 
+    /// Whether the mod `key` was pulled in as a dependency
+    pub fn is_dependency(&self, key: &str) -> bool {
+        match self.mods.get(key) {
+            Some(TomlMod::Detailed(detailed)) => detailed.dependency.unwrap_or(false),
+            _ => false,
+        }
+    }
+
+    /// The release channel of the mod `key`
+    ///
+    /// The mod's own `channel` if set, else the manifest one, else any channel
+    pub fn channel_of(&self, key: &str) -> VersionType {
+        self.entry_channel(ContentKind::Mod, key)
+    }
+
+    /// The release channel of the entry `key` in the table of `kind`, see
+    /// [`ModsConfig::channel_of`]
+    pub fn entry_channel(&self, kind: ContentKind, key: &str) -> VersionType {
+        let channel = match self.table(kind).and_then(|table| table.get(key)) {
+            Some(TomlMod::Detailed(detailed)) => detailed.channel,
+            _ => None,
+        };
+        channel.unwrap_or(self.default_channel())
+    }
+
+    /// The versions `update` may move the entry `key` in the table of `kind`
+    /// to, any version if it has no `version`
+    pub fn entry_constraint(&self, kind: ContentKind, key: &str) -> VersionConstraint {
+        let version = match self.table(kind).and_then(|table| table.get(key)) {
+            Some(TomlMod::Detailed(detailed)) => detailed.version.as_ref(),
+            Some(TomlMod::Simple(simple)) => Some(simple),
+            None => None,
+        };
+        version
+            .and_then(|version| version.parse().ok())
+            .unwrap_or_default()
+    }
+
+    /// The Modrinth entries of `kind` written as a bare constraint, like
+    /// `sodium = "^0.5"`, which have no version pinned yet
+    ///
+    /// They are [`Mod::Unknown`] until a version is pinned with
+    /// [`ModsConfig::insert_entry`], which keeps the constraint as `version`.
+    pub fn unpinned_entries(&self, kind: ContentKind) -> Vec<(String, VersionConstraint)> {
+        self.table(kind)
+            .into_iter()
+            .flatten()
+            .filter_map(|(key, value)| match value {
+                TomlMod::Simple(simple) => Some((key.clone(), simple.parse().ok()?)),
+                TomlMod::Detailed(_) => None,
+            })
+            .collect()
+    }
+
+    /// Whether the entry `key` in the table of `kind` is installed on `side`
+    ///
+    /// The entry's own `side` if set, else whether `project` supports `side`
+    pub fn is_on_side(&self, kind: ContentKind, key: &str, project: &Project, side: Side) -> bool {
+        match self.entry_side(kind, key) {
+            Some(mod_side) => mod_side.includes(side),
+            None => side.is_supported_by(project),
+        }
+    }
+
+    /// Whether the entry `key` in the table of `kind` is installed on `side`,
+    /// for the sources that do not tell the sides of a project
+    ///
+    /// Only the entry's own `side` is used, every side if not set.
+    pub fn is_entry_on_side(&self, kind: ContentKind, key: &str, side: Side) -> bool {
+        self.entry_side(kind, key)
+            .is_none_or(|mod_side| mod_side.includes(side))
+    }
+
+    /// The `side` set on the entry `key` in the table of `kind`
+    pub fn entry_side(&self, kind: ContentKind, key: &str) -> Option<ModSide> {
+        match self.table(kind)?.get(key)? {
+            TomlMod::Detailed(detailed) => detailed.side,
+            TomlMod::Simple(_) => None,
+        }
+    }
+
+    /// The profile `name`, see [`TomlProfile`]
+    pub fn profile(&self, name: &str) -> Result<&TomlProfile, anyhow::Error> {
+        let profiles = self.profiles.as_ref();
+        match profiles.and_then(|profiles| profiles.get(name)) {
+            Some(profile) => Ok(profile),
+            None => {
+                let names = profiles
+                    .into_iter()
+                    .flat_map(|profiles| profiles.keys())
+                    .map(|name| format!("`{name}`"))
+                    .collect::<Vec<_>>();
+                match names.is_empty() {
+                    true => bail!("no profile `{name}`, mods.toml has no `[profiles]`"),
+                    false => bail!("no profile `{name}`, expected one of {}", names.join(", ")),
+                }
+            }
+        }
+    }
+
+    /// Whether the entry `key` in the table of `kind` is installed, with
+    /// `profile` selected if any
+    ///
+    /// An entry with `enabled = false` never is. With a profile, an entry with
+    /// `groups` is installed if the profile selects one of them. An `optional`
+    /// entry is only installed that way, the other ones without a group always
+    /// are.
+    pub fn is_entry_enabled(
+        &self,
+        kind: ContentKind,
+        key: &str,
+        profile: Option<&TomlProfile>,
+    ) -> bool {
+        let Some(TomlMod::Detailed(detailed)) = self.table(kind).and_then(|table| table.get(key))
+        else {
+            return true;
+        };
+        if detailed.enabled == Some(false) {
+            return false;
+        }
+        let groups = detailed.groups.as_deref().unwrap_or_default();
+        match profile {
+            Some(profile) if !groups.is_empty() => {
+                groups.iter().any(|group| profile.groups.contains(group))
+            }
+            _ => detailed.optional != Some(true),
+        }
+    }
+
+    /// Remove the entries `f` returns `false` for
+    pub fn retain_entries(&mut self, f: impl Fn(ContentKind, &str) -> bool) {
+        for kind in self.kinds() {
+            let Some(table) = self.document[kind.table()].as_table_like_mut() else {
+                continue;
+            };
+            let keys = table
+                .iter()
+                .map(|(key, _)| key.to_string())
+                .filter(|key| !f(kind, key))
+                .collect::<Vec<_>>();
+            for key in keys {
+                table.remove(&key);
+            }
+        }
+        self.reload();
+    }
+
+    /// The release channel of the manifest, any channel if not set
+    pub fn default_channel(&self) -> VersionType {
+        self.channel.unwrap_or(VersionType::Alpha)
+    }
+
+    /// Insert or update a mod
+    ///
+    /// If the mod already has a detailed entry, only its `version-id-number`
+    /// is changed and the other keys are kept.
     pub fn insert_mod(&mut self, value: Mod) {
+        self.insert_entry(ContentKind::Mod, value);
+    }
+
+    /// Insert or update an entry in the table of `kind`, see
+    /// [`ModsConfig::insert_mod`]
+    ///
+    /// The table is created if it does not exist. An unknown entry has no
+    /// key, so nothing is written for it.
+    pub fn insert_entry(&mut self, kind: ContentKind, value: Mod) {
         match value {
-            Mod::Modrinth(modrinth) => {
-                let toml_mod = TomlMod::Simple(modrinth.version_id_number.clone());
-                self.config.mods.insert(modrinth.slug.clone(), toml_mod);
-
-                let document = &mut self.document;
-                if let Some(item) = document["mods"][&modrinth.slug].as_value_mut() {
-                    let decor = item.decor();
-                    let prefix = decor.prefix().map(|s| s.as_str().unwrap()).unwrap_or("");
-                    let suffix = decor.suffix().map(|s| s.as_str().unwrap()).unwrap_or("");
-                    *item = toml_edit::value(modrinth.version_id_number)
-                        .into_value()
-                        .unwrap()
-                        .decorated(prefix, suffix);
-                } else {
-                    document["mods"][&modrinth.slug] = toml_edit::value(modrinth.version_id_number);
+            Mod::Modrinth(modrinth) => self.set_version_id_number(kind, modrinth),
+            Mod::CurseForge(curseforge) => self.set_entry_keys(
+                kind,
+                &curseforge.slug,
+                [
+                    ("curseforge", Some((curseforge.project_id as i64).into())),
+                    ("file-id", Some((curseforge.file_id as i64).into())),
+                ],
+            ),
+            Mod::GitHub(github) => self.set_entry_keys(
+                kind,
+                &github.slug,
+                [
+                    ("github", Some(github.repo.into())),
+                    ("tag", Some(github.tag.into())),
+                    ("asset", github.asset_pattern.map(Into::into)),
+                ],
+            ),
+            Mod::Maven(maven) => self.set_entry_keys(
+                kind,
+                &maven.slug,
+                [
+                    ("maven", Some(maven.repository.into())),
+                    ("coordinates", Some(maven.coordinates.to_string().into())),
+                ],
+            ),
+            Mod::Url(url) => {
+                let (sha1, sha512) = hash_keys(Some(url.hash));
+                self.set_entry_keys(
+                    kind,
+                    &url.slug,
+                    [
+                        ("url", Some(url.url.into())),
+                        ("sha1", sha1),
+                        ("sha512", sha512),
+                        ("filename", url.filename.map(Into::into)),
+                    ],
+                )
+            }
+            Mod::Local(local) => {
+                let (sha1, sha512) = hash_keys(local.hash);
+                self.set_entry_keys(
+                    kind,
+                    &local.slug,
+                    [
+                        (
+                            "path",
+                            Some(local.path.to_string_lossy().to_string().into()),
+                        ),
+                        ("symlink", local.symlink.then(|| true.into())),
+                        ("sha1", sha1),
+                        ("sha512", sha512),
+                        ("filename", local.filename.map(Into::into)),
+                    ],
+                )
+            }
+            Mod::Unknown => (),
+        }
+    }
+
+    /// Set the `version-id-number` of the Modrinth entry `modrinth`, keeping
+    /// the other keys of a detailed entry
+    fn set_version_id_number(&mut self, kind: ContentKind, modrinth: ModrinthMod) {
+        let document = &mut self.document;
+        if !document.contains_key(kind.table()) {
+            document[kind.table()] = toml_edit::Item::Table(toml_edit::Table::new());
+        }
+        let item = &mut document[kind.table()][&modrinth.slug];
+        if let Some(table) = item.as_table_like_mut() {
+            table.insert(
+                "version-id-number",
+                toml_edit::value(modrinth.version_id_number),
+            );
+        } else if let Some(item) = item.as_value_mut() {
+            let decor = item.decor();
+            let prefix = decor.prefix().map(|s| s.as_str().unwrap()).unwrap_or("");
+            let suffix = decor.suffix().map(|s| s.as_str().unwrap()).unwrap_or("");
+            // A bare constraint is kept as the `version` of the pinned entry
+            let value = match item
+                .as_str()
+                .filter(|simple| simple.parse::<VersionConstraint>().is_ok())
+            {
+                Some(constraint) => {
+                    let mut table = toml_edit::InlineTable::new();
+                    table.insert("version-id-number", modrinth.version_id_number.into());
+                    table.insert("version", constraint.into());
+                    toml_edit::Value::InlineTable(table)
+                }
+                None => modrinth.version_id_number.into(),
+            };
+            *item = value.decorated(prefix, suffix);
+        } else {
+            *item = toml_edit::value(modrinth.version_id_number);
+        }
+        self.reload();
+    }
+
+    /// Set the keys of the entry `slug` of a source other than Modrinth, the
+    /// keys set to `None` are removed
+    ///
+    /// The entry is turned into an inline table if it is not table-like, and
+    /// its `version-id-number` is removed.
+    fn set_entry_keys<'a>(
+        &mut self,
+        kind: ContentKind,
+        slug: &str,
+        keys: impl IntoIterator<Item = (&'a str, Option<toml_edit::Value>)>,
+    ) {
+        let document = &mut self.document;
+        if !document.contains_key(kind.table()) {
+            document[kind.table()] = toml_edit::Item::Table(toml_edit::Table::new());
+        }
+        let item = &mut document[kind.table()][slug];
+        if !item.is_table_like() {
+            let decor = item.as_value().map(|v| v.decor().clone());
+            let mut table = toml_edit::Value::InlineTable(toml_edit::InlineTable::new());
+            if let Some(decor) = decor {
+                *table.decor_mut() = decor;
+            }
+            *item = toml_edit::Item::Value(table);
+        }
+        let table = item.as_table_like_mut().unwrap();
+        table.remove("version-id-number");
+        for (key, value) in keys {
+            match value {
+                Some(value) => table.insert(key, toml_edit::Item::Value(value)),
+                None => table.remove(key),
+            };
+        }
+        if let Some(toml_edit::Value::InlineTable(table)) = item.as_value_mut() {
+            table.fmt();
+        }
+        self.reload();
+    }
+
+    /// Remove a mod, see [`ModsConfig::remove_entry`]
+    pub fn remove_mod(&mut self, key: &str) -> Option<Mod> {
+        self.remove_entry(ContentKind::Mod, key)
+    }
+
+    /// Remove the entry `key` from the table of `kind`, returning it
+    ///
+    /// The comment lines above the entry are kept above the next one, the
+    /// rest of the document is left as is.
+    pub fn remove_entry(&mut self, kind: ContentKind, key: &str) -> Option<Mod> {
+        let removed = self.get_entry(kind, key)?;
+        let table = self.document[kind.table()].as_table_like_mut()?;
+        let keys = table.iter().map(|(k, _)| k.to_string()).collect::<Vec<_>>();
+        let prefix = table
+            .key(key)
+            .and_then(|k| k.leaf_decor().prefix())
+            .and_then(|prefix| prefix.as_str())
+            .filter(|prefix| prefix.contains('#'))
+            .map(|prefix| prefix.to_string());
+        let next = keys
+            .iter()
+            .skip_while(|k| k.as_str() != key)
+            .nth(1)
+            .cloned();
+        table.remove(key);
+        if let (Some(prefix), Some(next)) = (prefix, next)
+            && let Some(mut next) = table.key_mut(&next)
+        {
+            let next_prefix = next
+                .leaf_decor()
+                .prefix()
+                .and_then(|prefix| prefix.as_str())
+                .unwrap_or_default()
+                .to_string();
+            next.leaf_decor_mut().set_prefix(prefix + &next_prefix);
+        }
+        self.reload();
+        Some(removed)
+    }
+
+    /// Mark the mod `key` as pulled in as a dependency, or as explicitly requested
+    pub fn set_dependency(&mut self, key: &str, dependency: bool) {
+        self.set_mod_key(key, "dependency", dependency.then(|| true.into()));
+    }
+
+    /// Disable the entry `key` in the table of `kind` with `enabled = false`,
+    /// or enable it again
+    pub fn set_entry_enabled(&mut self, kind: ContentKind, key: &str, enabled: bool) {
+        self.set_entry_key(kind, key, "enabled", (!enabled).then(|| false.into()));
+    }
+
+    /// Set the game version, keeping the comments around it
+    pub fn set_version(&mut self, version: &str) {
+        match self.document["version"].as_value_mut() {
+            Some(value) => {
+                let decor = value.decor().clone();
+                *value = version.into();
+                *value.decor_mut() = decor;
+            }
+            None => self.document["version"] = toml_edit::value(version),
+        }
+        self.reload();
+    }
+
+    /// Set a key of the mod entry `slug`, see [`ModsConfig::set_entry_key`]
+    fn set_mod_key(&mut self, slug: &str, key: &str, value: Option<toml_edit::Value>) {
+        self.set_entry_key(ContentKind::Mod, slug, key, value);
+    }
+
+    /// Set a key of the entry `slug` in the table of `kind`, or remove it if
+    /// `value` is `None`
+    ///
+    /// A simple entry is turned into an inline table when a key is set, and an
+    /// inline table that only has `version-id-number` left is turned back.
+    fn set_entry_key(
+        &mut self,
+        kind: ContentKind,
+        slug: &str,
+        key: &str,
+        value: Option<toml_edit::Value>,
+    ) {
+        let Some(item) = self.document[kind.table()]
+            .as_table_like_mut()
+            .and_then(|table| table.get_mut(slug))
+        else {
+            return;
+        };
+
+        if let Some(simple) = item.as_value_mut().filter(|v| v.is_str()) {
+            if value.is_none() {
+                return;
+            }
+            let decor = simple.decor().clone();
+            let mut table = toml_edit::InlineTable::new();
+            table.insert("version-id-number", simple.clone().decorated("", ""));
+            let mut table = toml_edit::Value::InlineTable(table);
+            *table.decor_mut() = decor;
+            *simple = table;
+        }
+
+        if let Some(table) = item.as_table_like_mut() {
+            match value {
+                Some(value) => {
+                    table.insert(key, toml_edit::Item::Value(value));
+                }
+                None => {
+                    table.remove(key);
                 }
             }
-            _ => {}
         }
+
+        if let Some(toml_edit::Value::InlineTable(table)) = item.as_value_mut() {
+            table.fmt();
+        }
+        if let Some(toml_edit::Value::InlineTable(table)) = item.as_value_mut()
+            && table.len() == 1
+            && let Some(version_id_number) = table
+                .get("version-id-number")
+                .and_then(|v| v.as_str())
+                .map(|s| s.to_string())
+        {
+            let decor = table.decor().clone();
+            let mut simple = toml_edit::Value::from(version_id_number);
+            *simple.decor_mut() = decor;
+            *item = toml_edit::Item::Value(simple);
+        }
+
+        self.reload();
+    }
+
+    /// Re-read the data from the document after an edit
+    fn reload(&mut self) {
+        self.config = toml::from_str(&self.document.to_string())
+            .expect("the edited document should still be a valid mods.toml");
     }
 }
 
@@ -140,6 +824,7 @@ impl FromStr for ModsConfig {
     type Err = anyhow::Error;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let config = toml::from_str::<TomlModsManifest>(s)?;
+        config.validate()?;
         let document = s.parse()?;
         Ok(ModsConfig { config, document })
     }
@@ -186,6 +871,16 @@ impl LocalModsConfig {
         Ok(LocalModsConfig { inner, path })
     }
 
+    /// The path to mods.toml, `path` entries are relative to its directory
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The path to the `mods.lock` next to mods.toml
+    pub fn lock_path(&self) -> PathBuf {
+        self.path.with_file_name("mods.lock")
+    }
+
     pub fn save(&self) -> Result<(), anyhow::Error> {
         let config = self.inner.to_string();
         fs::write(self.path.clone(), config)?;
@@ -201,8 +896,11 @@ mod test {
 
     #[test]
     fn foo() {
-        let mut config =
-            LocalModsConfig::new("1.21.6".to_string(), ServerLoader::Fabric, "H:/_mc/ice-test");
+        let mut config = LocalModsConfig::new(
+            "1.21.6".to_string(),
+            ServerLoader::Fabric,
+            "H:/_mc/ice-test",
+        );
         // let config = config.inner;
         // println!("{:?}", config);
         // println!("{:?}", config.to_string());
@@ -213,4 +911,592 @@ mod test {
         }));
         println!("{:?}", config.to_string())
     }
+
+    #[test]
+    fn test_set_version_and_enabled() {
+        let mut config = ModsConfig::from_str(
+            r#"version = "1.21.6" # target
+loader = "fabric"
+
+[mods]
+sodium = "abc#0.6.0"
+
+[resourcepacks]
+faithful = { version-id-number = "def#1.0", side = "client" }
+"#,
+        )
+        .unwrap();
+
+        config.set_version("1.21.8");
+        config.set_entry_enabled(ContentKind::Mod, "sodium", false);
+        config.set_entry_enabled(ContentKind::ResourcePack, "faithful", false);
+        assert_eq!(config.version, "1.21.8");
+        assert!(!config.is_entry_enabled(ContentKind::Mod, "sodium", None));
+        assert!(!config.is_entry_enabled(ContentKind::ResourcePack, "faithful", None));
+        assert_eq!(
+            config.to_string(),
+            r#"version = "1.21.8" # target
+loader = "fabric"
+
+[mods]
+sodium = { version-id-number = "abc#0.6.0", enabled = false }
+
+[resourcepacks]
+faithful = { version-id-number = "def#1.0", side = "client", enabled = false }
+"#
+        );
+
+        config.set_entry_enabled(ContentKind::Mod, "sodium", true);
+        assert!(config.is_entry_enabled(ContentKind::Mod, "sodium", None));
+        assert!(config.to_string().contains("sodium = \"abc#0.6.0\"\n"));
+    }
+
+    #[test]
+    fn test_set_dependency() {
+        let mut config = ModsConfig::from_str(
+            r#"version = "1.21.6"
+loader = "fabric"
+
+[mods]
+# rendering
+sodium = "abc#0.6.0" # pinned
+lithium = { version-id-number = "def#0.15.0", url = "https://example.com" }
+"#,
+        )
+        .unwrap();
+
+        config.set_dependency("sodium", true);
+        config.set_dependency("lithium", true);
+        assert!(config.is_dependency("sodium"));
+        assert!(config.is_dependency("lithium"));
+        assert_eq!(
+            config.to_string(),
+            r#"version = "1.21.6"
+loader = "fabric"
+
+[mods]
+# rendering
+sodium = { version-id-number = "abc#0.6.0", dependency = true } # pinned
+lithium = { version-id-number = "def#0.15.0", url = "https://example.com", dependency = true }
+"#
+        );
+
+        config.insert_mod(Mod::Modrinth(ModrinthMod {
+            slug: "sodium".to_string(),
+            version_id_number: "ghi#0.6.1".to_string(),
+        }));
+        config.set_dependency("sodium", false);
+        assert!(!config.is_dependency("sodium"));
+        assert!(
+            config
+                .to_string()
+                .contains("sodium = \"ghi#0.6.1\" # pinned\n")
+        );
+    }
+
+    #[test]
+    fn test_channel_of() {
+        let config = ModsConfig::from_str(
+            r#"version = "1.21.6"
+loader = "fabric"
+channel = "release"
+
+[mods]
+sodium = "abc#0.6.0"
+continuity = { version-id-number = "def#3.0.0-beta.5", channel = "beta" }
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.channel_of("sodium"), VersionType::Release);
+        assert_eq!(config.channel_of("continuity"), VersionType::Beta);
+        assert_eq!(config.channel_of("not-added"), VersionType::Release);
+    }
+
+    #[test]
+    fn test_content_tables() {
+        let mut config = ModsConfig::from_str(
+            r#"version = "1.21.6"
+loader = "fabric"
+
+[mods]
+sodium = "abc#0.6.0"
+
+[datapacks]
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.kinds(),
+            vec![ContentKind::Mod, ContentKind::Datapack]
+        );
+        assert!(config.get_entries(ContentKind::ResourcePack).is_empty());
+
+        config.insert_entry(
+            ContentKind::ResourcePack,
+            Mod::Modrinth(ModrinthMod {
+                slug: "faithful-32x".to_string(),
+                version_id_number: "def#1.21".to_string(),
+            }),
+        );
+        assert_eq!(
+            config.kinds(),
+            vec![
+                ContentKind::Mod,
+                ContentKind::ResourcePack,
+                ContentKind::Datapack
+            ]
+        );
+        assert!(config.get_entry(ContentKind::Mod, "faithful-32x").is_none());
+        assert!(
+            config
+                .get_entry(ContentKind::ResourcePack, "faithful-32x")
+                .is_some()
+        );
+        assert!(
+            config
+                .to_string()
+                .ends_with("\n[resourcepacks]\nfaithful-32x = \"def#1.21\"\n")
+        );
+    }
+
+    fn project(slug: &str, client_side: &str, server_side: &str) -> Project {
+        serde_json::from_value(serde_json::json!({
+            "id": slug,
+            "slug": slug,
+            "title": slug,
+            "description": "",
+            "project_type": "mod",
+            "client_side": client_side,
+            "server_side": server_side,
+            "license": { "id": "MIT", "name": "MIT License", "url": null },
+            "categories": [],
+            "game_versions": [],
+            "loaders": [],
+            "versions": [],
+            "downloads": 0,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_is_on_side() {
+        let config = ModsConfig::from_str(
+            r#"version = "1.21.6"
+loader = "fabric"
+side = "server"
+
+[mods]
+sodium = "abc#0.6.0"
+lithium = "def#0.15.0"
+iris = { version-id-number = "ghi#1.8.0", side = "both" }
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.side, Some(Side::Server));
+
+        let sodium = project("sodium", "required", "unsupported");
+        let lithium = project("lithium", "optional", "optional");
+        let iris = project("iris", "required", "unsupported");
+        assert!(!config.is_on_side(ContentKind::Mod, "sodium", &sodium, Side::Server));
+        assert!(config.is_on_side(ContentKind::Mod, "sodium", &sodium, Side::Client));
+        assert!(config.is_on_side(ContentKind::Mod, "lithium", &lithium, Side::Server));
+        // the entry's own side wins over the project's
+        assert!(config.is_on_side(ContentKind::Mod, "iris", &iris, Side::Server));
+    }
+
+    #[test]
+    fn test_curseforge_entries() {
+        let mut config = ModsConfig::from_str(
+            r#"version = "1.21.1"
+loader = "neoforge"
+
+[mods]
+jei = { curseforge = 238222, file-id = 5101366 } # from CurseForge
+sodium = "abc#0.6.0"
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.get_mod("jei"),
+            Some(Mod::CurseForge(CurseForgeMod {
+                slug: "jei".to_string(),
+                project_id: 238222,
+                file_id: 5101366,
+            }))
+        );
+
+        config.insert_mod(Mod::CurseForge(CurseForgeMod {
+            slug: "jei".to_string(),
+            project_id: 238222,
+            file_id: 5200000,
+        }));
+        config.insert_mod(Mod::CurseForge(CurseForgeMod {
+            slug: "sodium".to_string(),
+            project_id: 394468,
+            file_id: 5300000,
+        }));
+        config.insert_mod(Mod::CurseForge(CurseForgeMod {
+            slug: "appleskin".to_string(),
+            project_id: 248787,
+            file_id: 5400000,
+        }));
+        assert_eq!(
+            config.to_string(),
+            r#"version = "1.21.1"
+loader = "neoforge"
+
+[mods]
+jei = { curseforge = 238222, file-id = 5200000 } # from CurseForge
+sodium = { curseforge = 394468, file-id = 5300000 }
+appleskin = { curseforge = 248787, file-id = 5400000 }
+"#
+        );
+    }
+
+    #[test]
+    fn test_url_entries() {
+        let config = ModsConfig::from_str(
+            r#"version = "1.21.1"
+loader = "fabric"
+
+[mods]
+foo = { url = "https://example.com/files/foo-1.0.jar?raw=1", sha1 = "ABC" }
+bar = { url = "https://example.com/download", sha512 = "def", filename = "bar.jar" }
+"#,
+        )
+        .unwrap();
+        let Some(Mod::Url(foo)) = config.get_mod("foo") else {
+            panic!("foo should be a url entry");
+        };
+        assert_eq!(foo.hash, FileHash::Sha1("ABC".to_string()));
+        assert_eq!(foo.filename(), "foo-1.0.jar");
+        assert!(foo.hash.same_as(&FileHash::Sha1("abc".to_string())));
+        assert!(!foo.hash.same_as(&FileHash::Sha512("abc".to_string())));
+        let Some(Mod::Url(bar)) = config.get_mod("bar") else {
+            panic!("bar should be a url entry");
+        };
+        assert_eq!(bar.hash, FileHash::Sha512("def".to_string()));
+        assert_eq!(bar.filename(), "bar.jar");
+
+        let unpinned = ModsConfig::from_str(
+            r#"version = "1.21.1"
+loader = "fabric"
+
+[mods]
+foo = { url = "https://example.com/foo.jar" }
+"#,
+        );
+        assert!(unpinned.unwrap_err().to_string().contains("mods.foo"));
+
+        let escaping = ModsConfig::from_str(
+            r#"version = "1.21.1"
+loader = "fabric"
+
+[mods]
+foo = { url = "https://example.com/foo.jar", sha1 = "abc", filename = "../foo.jar" }
+"#,
+        );
+        assert!(escaping.is_err());
+    }
+
+    #[test]
+    fn test_path_entries() {
+        let config = ModsConfig::from_str(
+            r#"version = "1.21.1"
+loader = "fabric"
+
+[mods]
+foo = { path = "vendor/foo-1.0.jar" }
+bar = { path = "vendor/bar.jar", symlink = true, sha1 = "abc", filename = "bar-dev.jar" }
+"#,
+        )
+        .unwrap();
+        let Some(Mod::Local(foo)) = config.get_mod("foo") else {
+            panic!("foo should be a path entry");
+        };
+        assert_eq!(foo.path, PathBuf::from("vendor/foo-1.0.jar"));
+        assert_eq!(foo.filename(), "foo-1.0.jar");
+        assert_eq!(foo.hash, None);
+        assert!(!foo.symlink);
+        let Some(Mod::Local(bar)) = config.get_mod("bar") else {
+            panic!("bar should be a path entry");
+        };
+        assert_eq!(bar.filename(), "bar-dev.jar");
+        assert_eq!(bar.hash, Some(FileHash::Sha1("abc".to_string())));
+        assert!(bar.symlink);
+
+        let both = ModsConfig::from_str(
+            r#"version = "1.21.1"
+loader = "fabric"
+
+[mods]
+foo = { path = "vendor/foo.jar", url = "https://example.com/foo.jar", sha1 = "abc" }
+"#,
+        );
+        assert!(both.unwrap_err().to_string().contains("mods.foo"));
+    }
+
+    #[test]
+    fn test_github_and_maven_entries() {
+        let mut config = ModsConfig::from_str(
+            r#"version = "1.21.1"
+loader = "fabric"
+
+[mods]
+foo = { github = "owner/foo", tag = "v1.2.0", asset = "foo-*.jar", side = "server" } # our fork
+bar = { maven = "https://maven.example.com/releases", coordinates = "com.example:bar:1.0.0" }
+"#,
+        )
+        .unwrap();
+        let Some(Mod::GitHub(foo)) = config.get_mod("foo") else {
+            panic!("foo should be a GitHub entry");
+        };
+        assert_eq!(foo.repo, "owner/foo");
+        assert_eq!(foo.asset_pattern(), "foo-*.jar");
+        let Some(Mod::Maven(bar)) = config.get_mod("bar") else {
+            panic!("bar should be a Maven entry");
+        };
+        assert_eq!(bar.coordinates.filename(), "bar-1.0.0.jar");
+
+        config.insert_mod(Mod::GitHub(GitHubMod {
+            tag: "v1.3.0".to_string(),
+            ..foo
+        }));
+        config.insert_mod(Mod::Maven(MavenMod {
+            coordinates: bar.coordinates.with_version("1.1.0"),
+            ..bar
+        }));
+        assert_eq!(
+            config.to_string(),
+            r#"version = "1.21.1"
+loader = "fabric"
+
+[mods]
+foo = { github = "owner/foo", tag = "v1.3.0", asset = "foo-*.jar", side = "server" } # our fork
+bar = { maven = "https://maven.example.com/releases", coordinates = "com.example:bar:1.1.0" }
+"#
+        );
+
+        let untagged = ModsConfig::from_str(
+            r#"version = "1.21.1"
+loader = "fabric"
+
+[mods]
+foo = { github = "owner/foo" }
+"#,
+        );
+        assert!(untagged.unwrap_err().to_string().contains("mods.foo"));
+        let invalid = ModsConfig::from_str(
+            r#"version = "1.21.1"
+loader = "fabric"
+
+[mods]
+bar = { maven = "https://maven.example.com/releases", coordinates = "com.example:bar" }
+"#,
+        );
+        assert!(invalid.unwrap_err().to_string().contains("mods.bar"));
+    }
+
+    #[test]
+    fn test_insert_url_and_path_entries() {
+        let mut config = ModsConfig::from_str(
+            r#"version = "1.21.1"
+loader = "fabric"
+
+[mods]
+foo = "abc#1.0" # moved off Modrinth
+"#,
+        )
+        .unwrap();
+        let foo = Mod::Url(UrlMod {
+            slug: "foo".to_string(),
+            url: "https://example.com/foo.jar".to_string(),
+            hash: FileHash::Sha512("def".to_string()),
+            filename: Some("foo-1.0.jar".to_string()),
+        });
+        let bar = Mod::Local(LocalMod {
+            slug: "bar".to_string(),
+            path: PathBuf::from("vendor/bar.jar"),
+            hash: Some(FileHash::Sha1("ghi".to_string())),
+            filename: None,
+            symlink: true,
+        });
+        config.insert_mod(foo.clone());
+        config.insert_mod(bar.clone());
+        assert_eq!(
+            config.to_string(),
+            r#"version = "1.21.1"
+loader = "fabric"
+
+[mods]
+foo = { url = "https://example.com/foo.jar", sha512 = "def", filename = "foo-1.0.jar" } # moved off Modrinth
+bar = { path = "vendor/bar.jar", symlink = true, sha1 = "ghi" }
+"#
+        );
+        assert_eq!(config.get_mod("foo"), Some(foo));
+        assert_eq!(config.get_mod("bar"), Some(bar));
+
+        // The keys no longer set are removed
+        config.insert_mod(Mod::Url(UrlMod {
+            slug: "foo".to_string(),
+            url: "https://example.com/foo.jar".to_string(),
+            hash: FileHash::Sha1("jkl".to_string()),
+            filename: None,
+        }));
+        assert!(
+            config
+                .to_string()
+                .contains("foo = { url = \"https://example.com/foo.jar\", sha1 = \"jkl\" }")
+        );
+    }
+    #[test]
+    fn test_entry_constraint() {
+        let config = ModsConfig::from_str(
+            r#"version = "1.21.1"
+loader = "fabric"
+
+[mods]
+sodium = { version-id-number = "RncWhTxD#mc1.21-0.5.11", version = "^0.5" }
+jei = { curseforge = 238222, file-id = 5846880, version = "pinned:5846880" }
+lithium = "iEcXOkz4#mc1.21.1-0.13.0"
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config
+                .entry_constraint(ContentKind::Mod, "sodium")
+                .to_string(),
+            "^0.5"
+        );
+        assert_eq!(
+            config.entry_constraint(ContentKind::Mod, "jei"),
+            VersionConstraint::Pinned("5846880".to_string())
+        );
+        assert_eq!(
+            config.entry_constraint(ContentKind::Mod, "lithium"),
+            VersionConstraint::Any
+        );
+
+        let mut simple = ModsConfig::from_str(
+            r#"version = "1.21.1"
+loader = "fabric"
+
+[mods]
+sodium = "^0.5" # rendering
+"#,
+        )
+        .unwrap();
+        assert_eq!(simple.get_mod("sodium"), Some(Mod::Unknown));
+        assert_eq!(
+            simple.unpinned_entries(ContentKind::Mod),
+            [("sodium".to_string(), "^0.5".parse().unwrap())]
+        );
+        // Pinning a version keeps the constraint
+        simple.insert_mod(Mod::Modrinth(ModrinthMod {
+            slug: "sodium".to_string(),
+            version_id_number: "RncWhTxD#mc1.21-0.5.11".to_string(),
+        }));
+        assert!(simple.to_string().contains(
+            "sodium = { version-id-number = \"RncWhTxD#mc1.21-0.5.11\", version = \"^0.5\" } # rendering"
+        ));
+        assert!(simple.unpinned_entries(ContentKind::Mod).is_empty());
+        assert_eq!(
+            simple
+                .entry_constraint(ContentKind::Mod, "sodium")
+                .to_string(),
+            "^0.5"
+        );
+        let invalid = ModsConfig::from_str(
+            r#"version = "1.21.1"
+loader = "fabric"
+
+[mods]
+sodium = { version-id-number = "RncWhTxD#mc1.21-0.5.11", version = "0.5" }
+"#,
+        );
+        assert!(invalid.unwrap_err().to_string().contains("mods.sodium"));
+    }
+
+    #[test]
+    fn test_profiles() {
+        let mut config = ModsConfig::from_str(
+            r#"version = "1.21.1"
+loader = "fabric"
+
+[mods]
+fabric-api = "P7uGFii0#0.100.7+1.21"
+lithium = { version-id-number = "iEcXOkz4#mc1.21.1-0.13.0", groups = ["performance"] }
+modmenu = { version-id-number = "YIfqIJ8q#11.0.1", groups = ["client-ui"] }
+spark = { version-id-number = "u3b5d4oX#1.10.97", groups = ["admin"], optional = true }
+sodium = { version-id-number = "RncWhTxD#mc1.21-0.5.11", enabled = false }
+
+[profiles]
+server = { groups = ["performance", "admin"], side = "server" }
+"#,
+        )
+        .unwrap();
+        let enabled = |config: &ModsConfig, profile: Option<&TomlProfile>| {
+            ["fabric-api", "lithium", "modmenu", "spark", "sodium"]
+                .into_iter()
+                .filter(|key| config.is_entry_enabled(ContentKind::Mod, key, profile))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(enabled(&config, None), ["fabric-api", "lithium", "modmenu"]);
+        let server = config.profile("server").unwrap().clone();
+        assert_eq!(server.side, Some(Side::Server));
+        assert_eq!(
+            enabled(&config, Some(&server)),
+            ["fabric-api", "lithium", "spark"]
+        );
+        assert!(
+            config
+                .profile("client")
+                .unwrap_err()
+                .to_string()
+                .contains("`server`")
+        );
+
+        config.retain_entries(|_, key| key != "sodium");
+        assert!(config.get_mod("sodium").is_none());
+        assert!(config.get_mod("lithium").is_some());
+        assert!(!config.to_string().contains("sodium"));
+    }
+
+    #[test]
+    fn test_remove_mod() {
+        let mut config = ModsConfig::from_str(
+            r#"version = "1.21.1"
+loader = "fabric"
+
+[mods]
+# rendering
+sodium = "RncWhTxD#mc1.21-0.5.11" # fast
+iris = "kuOV4Ece#1.7.3+1.21"
+lithium = { version-id-number = "iEcXOkz4#mc1.21.1-0.13.0", side = "server" }
+
+# ui
+modmenu = "YIfqIJ8q#11.0.1"
+"#,
+        )
+        .unwrap();
+        assert!(config.remove_mod("jei").is_none());
+        assert!(
+            matches!(config.remove_mod("sodium"), Some(Mod::Modrinth(m)) if m.slug == "sodium")
+        );
+        assert!(config.remove_mod("lithium").is_some());
+        assert!(config.get_mod("sodium").is_none());
+        assert_eq!(
+            config.to_string(),
+            r#"version = "1.21.1"
+loader = "fabric"
+
+[mods]
+# rendering
+iris = "kuOV4Ece#1.7.3+1.21"
+
+# ui
+modmenu = "YIfqIJ8q#11.0.1"
+"#
+        );
+    }
 }
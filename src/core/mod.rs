@@ -1,10 +1,46 @@
-#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+use std::{
+    fmt::{self, Display, Formatter},
+    io,
+    path::{Path, PathBuf},
+};
+
+use ice_api_tool::{
+    curseforge::types::{ModLoaderType, class_id},
+    maven::Coordinates,
+    modrinth::types::{Loader, Project, ProjectType, SideSupport},
+};
+use ice_core::ServerLoader;
+use ice_util::fs::{get_sha1_hash, get_sha512_hash};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Default, Hash, Eq, PartialEq)]
 #[non_exhaustive]
 pub enum Mod {
     Modrinth(ModrinthMod),
+    CurseForge(CurseForgeMod),
+    Url(UrlMod),
+    Local(LocalMod),
+    GitHub(GitHubMod),
+    Maven(MavenMod),
+    #[default]
     Unknown,
 }
 
+impl Mod {
+    /// The key of the entry in mods.toml, `None` for an unknown one
+    pub fn slug(&self) -> Option<&str> {
+        match self {
+            Self::Modrinth(m) => Some(&m.slug),
+            Self::CurseForge(m) => Some(&m.slug),
+            Self::Url(m) => Some(&m.slug),
+            Self::Local(m) => Some(&m.slug),
+            Self::GitHub(m) => Some(&m.slug),
+            Self::Maven(m) => Some(&m.slug),
+            Self::Unknown => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Hash, Eq, PartialEq)]
 pub struct ModrinthMod {
     pub slug: String,
@@ -13,9 +49,362 @@ pub struct ModrinthMod {
 
 impl ModrinthMod {
     pub fn version_id(&self) -> &str {
-        &self.version_id_number.split("#").next().unwrap()
+        self.version_id_number.split("#").next().unwrap()
     }
     pub fn version_number(&self) -> &str {
-        &self.version_id_number.split("#").skip(1).next().unwrap()
+        self.version_id_number.split("#").nth(1).unwrap()
+    }
+}
+
+/// A file of a CurseForge project, `jei = { curseforge = 238222, file-id = 5101366 }`
+#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+pub struct CurseForgeMod {
+    pub slug: String,
+    pub project_id: u32,
+    pub file_id: u32,
+}
+
+/// A file downloaded from any url, pinned by its hash,
+/// `foo = { url = "https://example.com/foo.jar", sha512 = "..." }`
+#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+pub struct UrlMod {
+    pub slug: String,
+    pub url: String,
+    pub hash: FileHash,
+    /// The name to save the file as, defaults to the last segment of `url`
+    pub filename: Option<String>,
+}
+
+impl UrlMod {
+    pub fn filename(&self) -> &str {
+        match &self.filename {
+            Some(filename) => filename,
+            None => url_filename(&self.url),
+        }
+    }
+}
+
+/// The last segment of the path of `url`
+pub fn url_filename(url: &str) -> &str {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+/// A file on the local filesystem, copied or symlinked into the directory,
+/// `foo = { path = "vendor/foo.jar" }`
+#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+pub struct LocalMod {
+    pub slug: String,
+    /// Relative to the directory of mods.toml
+    pub path: PathBuf,
+    /// The expected hash of the file at `path`, it is not checked if `None`
+    pub hash: Option<FileHash>,
+    /// The name to install the file as, defaults to the file name of `path`
+    pub filename: Option<String>,
+    /// Symlink the file instead of copying it
+    pub symlink: bool,
+}
+
+impl LocalMod {
+    pub fn filename(&self) -> &str {
+        match &self.filename {
+            Some(filename) => filename,
+            None => self
+                .path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// An asset of a GitHub release,
+/// `foo = { github = "owner/foo", tag = "v1.2.0", asset = "foo-*.jar" }`
+#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+pub struct GitHubMod {
+    pub slug: String,
+    /// `owner/name`
+    pub repo: String,
+    pub tag: String,
+    /// The glob the name of the asset matches, the first `.jar` if `None`
+    pub asset_pattern: Option<String>,
+}
+
+impl GitHubMod {
+    pub fn asset_pattern(&self) -> &str {
+        self.asset_pattern.as_deref().unwrap_or("*.jar")
+    }
+}
+
+/// A jar in a Maven repository,
+/// `foo = { maven = "https://maven.example.com/releases", coordinates = "com.example:foo:1.2.0" }`
+#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+pub struct MavenMod {
+    pub slug: String,
+    /// The base url of the repository
+    pub repository: String,
+    pub coordinates: Coordinates,
+}
+
+/// The expected hash of a file, in hex
+#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+pub enum FileHash {
+    Sha1(String),
+    Sha512(String),
+}
+
+impl FileHash {
+    /// Hash the file at `path` with the same algorithm
+    pub fn of_file(&self, path: impl AsRef<Path>) -> io::Result<FileHash> {
+        Ok(match self {
+            Self::Sha1(_) => Self::Sha1(get_sha1_hash(path)?),
+            Self::Sha512(_) => Self::Sha512(get_sha512_hash(path)?),
+        })
+    }
+
+    /// Whether the file at `path` has this hash
+    pub fn matches(&self, path: impl AsRef<Path>) -> io::Result<bool> {
+        Ok(self.same_as(&self.of_file(path)?))
+    }
+
+    /// Whether `other` is the same hash, the hex is case-insensitive
+    pub fn same_as(&self, other: &FileHash) -> bool {
+        match (self, other) {
+            (Self::Sha1(a), Self::Sha1(b)) | (Self::Sha512(a), Self::Sha512(b)) => {
+                a.eq_ignore_ascii_case(b)
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Display for FileHash {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Sha1(hash) => write!(f, "sha1 {hash}"),
+            Self::Sha512(hash) => write!(f, "sha512 {hash}"),
+        }
+    }
+}
+
+/// The kinds of content in a mods.toml, each has its own table and directory
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Hash,
+    Eq,
+    PartialEq,
+    PartialOrd,
+    Ord,
+    Serialize,
+    Deserialize,
+    clap::ValueEnum,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum ContentKind {
+    Mod,
+    #[value(name = "resourcepack")]
+    ResourcePack,
+    #[value(name = "shaderpack")]
+    ShaderPack,
+    Datapack,
+}
+
+impl ContentKind {
+    pub const ALL: [ContentKind; 4] = [
+        ContentKind::Mod,
+        ContentKind::ResourcePack,
+        ContentKind::ShaderPack,
+        ContentKind::Datapack,
+    ];
+
+    /// The table of this kind in mods.toml
+    pub fn table(&self) -> &'static str {
+        match self {
+            Self::Mod => "mods",
+            Self::ResourcePack => "resourcepacks",
+            Self::ShaderPack => "shaderpacks",
+            Self::Datapack => "datapacks",
+        }
+    }
+
+    /// The directory of this kind
+    ///
+    /// `mods_dir` is the directory of mods.toml, the others are relative to
+    /// its parent, the instance
+    pub fn dir(&self, mods_dir: impl AsRef<Path>) -> PathBuf {
+        let mods_dir = mods_dir.as_ref();
+        let instance_dir = mods_dir.parent().unwrap_or(mods_dir);
+        match self {
+            Self::Mod => mods_dir.to_path_buf(),
+            Self::ResourcePack => instance_dir.join("resourcepacks"),
+            Self::ShaderPack => instance_dir.join("shaderpacks"),
+            Self::Datapack => instance_dir.join("server").join("world").join("datapacks"),
+        }
+    }
+
+    /// The extension of the files of this kind
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Mod => "jar",
+            _ => "zip",
+        }
+    }
+
+    /// The Modrinth loaders of the versions of this kind
+    pub fn loaders(&self, loader: ServerLoader) -> Vec<Loader> {
+        match self {
+            Self::Mod => loader.to_compatible_loaders(),
+            Self::ResourcePack => vec![Loader::Minecraft],
+            Self::ShaderPack => vec![
+                Loader::Iris,
+                Loader::Optifine,
+                Loader::Canvas,
+                Loader::Vanilla,
+            ],
+            Self::Datapack => vec![Loader::Datapack],
+        }
+    }
+
+    /// The CurseForge loaders of the files of this kind, empty for any
+    pub fn curseforge_loaders(&self, loader: ServerLoader) -> Vec<ModLoaderType> {
+        match (self, loader) {
+            (Self::Mod, ServerLoader::Fabric) => vec![ModLoaderType::Fabric],
+            (Self::Mod, ServerLoader::Quilt) => vec![ModLoaderType::Quilt, ModLoaderType::Fabric],
+            (Self::Mod, ServerLoader::NeoForge) => vec![ModLoaderType::NeoForge],
+            _ => vec![],
+        }
+    }
+
+    /// The CurseForge class of the projects of this kind
+    pub fn curseforge_class_id(&self) -> u32 {
+        match self {
+            Self::Mod => class_id::MODS,
+            Self::ResourcePack => class_id::RESOURCE_PACKS,
+            Self::ShaderPack => class_id::SHADERS,
+            Self::Datapack => class_id::DATAPACKS,
+        }
+    }
+
+    /// The kind of the projects of a CurseForge class
+    pub fn of_curseforge_class(class_id: u32) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|kind| kind.curseforge_class_id() == class_id)
+    }
+
+    /// Whether `project` can be added as this kind
+    ///
+    /// Modrinth lists datapacks as mods with the `datapack` loader
+    pub fn accepts(&self, project: &Project) -> bool {
+        match self {
+            Self::Mod => project.project_type == ProjectType::Mod,
+            Self::ResourcePack => project.project_type == ProjectType::ResourcePack,
+            Self::ShaderPack => project.project_type == ProjectType::Shader,
+            Self::Datapack => {
+                project.project_type == ProjectType::Datapack
+                    || project.loaders.contains(&Loader::Datapack)
+            }
+        }
+    }
+
+    /// The kind `project` is added as by default
+    pub fn of_project(project: &Project) -> Option<Self> {
+        match project.project_type {
+            ProjectType::Mod => Some(Self::Mod),
+            ProjectType::ResourcePack => Some(Self::ResourcePack),
+            ProjectType::Shader => Some(Self::ShaderPack),
+            ProjectType::Datapack => Some(Self::Datapack),
+            _ => None,
+        }
+    }
+}
+
+impl Display for ContentKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Mod => "mod",
+            Self::ResourcePack => "resourcepack",
+            Self::ShaderPack => "shaderpack",
+            Self::Datapack => "datapack",
+        })
+    }
+}
+
+/// The side an instance runs on
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    Server,
+    Client,
+}
+
+impl Side {
+    /// Whether `project` can run on this side, by its Modrinth side fields
+    pub fn is_supported_by(&self, project: &Project) -> bool {
+        let support = match self {
+            Self::Server => project.server_side,
+            Self::Client => project.client_side,
+        };
+        support != SideSupport::Unsupported
+    }
+}
+
+impl Display for Side {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Server => "server",
+            Self::Client => "client",
+        })
+    }
+}
+
+/// The sides a mod is installed on, overriding what Modrinth says
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ModSide {
+    Server,
+    Client,
+    Both,
+}
+
+impl ModSide {
+    pub fn includes(&self, side: Side) -> bool {
+        match self {
+            Self::Server => side == Side::Server,
+            Self::Client => side == Side::Client,
+            Self::Both => true,
+        }
+    }
+}
+
+impl Display for ModSide {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Server => "server",
+            Self::Client => "client",
+            Self::Both => "both",
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use clap::ValueEnum;
+
+    use super::*;
+
+    #[test]
+    fn test_content_kind_names() {
+        // `-t` takes the same names as mods.toml and the JSON output
+        for kind in ContentKind::ALL {
+            let name = kind.to_possible_value().unwrap();
+            assert_eq!(name.get_name(), kind.to_string());
+            assert_eq!(
+                serde_json::to_value(kind).unwrap(),
+                serde_json::json!(kind.to_string())
+            );
+        }
     }
 }
@@ -1,5 +1,5 @@
-pub mod modrinth;
 pub mod config;
 pub mod core;
 pub mod error;
+pub mod modrinth;
 // pub mod log;
@@ -1,7 +1,7 @@
 use std::path::Path;
 
 use color_print::{cprint, cprintln};
-use ice_api_tool::modrinth::{utils::download_version_file, *};
+use ice_api_tool::modrinth::{types::VersionType, utils::download_version_file, *};
 use ice_core::ServerLoader;
 use ice_util::fs::get_sha1_hash;
 
@@ -9,13 +9,16 @@ use ice_util::fs::get_sha1_hash;
 ///
 /// the downloaded version is satisfied to `loaders` and `game_version`
 pub async fn download_latest_mod<S: AsRef<str>, V: AsRef<str>, P: AsRef<Path>>(
+    client: &ModrinthClient,
     slug: S,
     loader: ServerLoader,
     game_version: V,
     dir: P,
 ) -> Result<(), anyhow::Error> {
     let loaders = loader.to_compatible_loaders();
-    let version = get_latest_version_from_slug(slug, loaders, game_version).await?;
+    let version = client
+        .get_latest_version_from_slug(slug, loaders, game_version, VersionType::Alpha)
+        .await?;
     download_version_file(version.get_primary_file(), dir).await?;
     Ok(())
 }
@@ -24,6 +27,7 @@ pub async fn download_latest_mod<S: AsRef<str>, V: AsRef<str>, P: AsRef<Path>>(
 ///
 /// the downloaded version is satisfied to `loader`
 pub async fn download_mod<S: AsRef<str>, P: AsRef<Path>>(
+    client: &ModrinthClient,
     slug: S,
     version_number: S,
     loader: ServerLoader,
@@ -37,7 +41,7 @@ pub async fn download_mod<S: AsRef<str>, P: AsRef<Path>>(
     cprint!("<g>Syncing</> [{loader:?}] {slug} = {version_number}...");
 
     let loaders = loader.to_compatible_loaders();
-    let versions = get_project_versions(slug, None, None).await?;
+    let versions = client.get_project_versions(slug, None, None).await?;
     match versions.iter().find(|v| {
         v.version_number == version_number
             && loaders.iter().any(|l| v.loaders.contains(l))
@@ -64,6 +68,7 @@ pub async fn download_mod<S: AsRef<str>, P: AsRef<Path>>(
 ///
 /// use the latest version satisfies to `loader` and `game_version`
 pub async fn add_mod<S: AsRef<str>, V: AsRef<str>, P: AsRef<Path>>(
+    client: &ModrinthClient,
     slug: S,
     loader: ServerLoader,
     game_version: V,
@@ -73,16 +78,18 @@ pub async fn add_mod<S: AsRef<str>, V: AsRef<str>, P: AsRef<Path>>(
     let dir = dir.as_ref();
 
     let loaders = loader.to_compatible_loaders();
-    let version = get_latest_version_from_slug(slug, loaders, game_version).await?;
+    let version = client
+        .get_latest_version_from_slug(slug, loaders, game_version, VersionType::Alpha)
+        .await?;
     download_version_file(version.get_primary_file(), dir).await?;
     Ok((slug.to_string(), version.version_number))
 }
 
-
 /// Update the mod from `path`
 ///
 /// if success return the new version_number
 pub async fn update_mod<P: AsRef<Path>, S: AsRef<str>>(
+    client: &ModrinthClient,
     path: P,
     loader: ServerLoader,
     game_version: S,
@@ -93,10 +100,13 @@ pub async fn update_mod<P: AsRef<Path>, S: AsRef<str>>(
     let hash = get_sha1_hash(path)?;
     let loaders = loader.to_compatible_loaders();
 
-    let cur_version = get_version_from_hash(&hash, HashMethod::Sha1).await?;
-    let new_version =
-        get_latest_version_from_hash(&hash, HashMethod::Sha1, &loaders, game_version).await?;
-    let project = get_project(&new_version.project_id).await?;
+    let cur_version = client
+        .get_version_from_hash(&hash, HashMethod::Sha1)
+        .await?;
+    let new_version = client
+        .get_latest_version_from_hash(&hash, HashMethod::Sha1, &loaders, game_version)
+        .await?;
+    let project = client.get_project(&new_version.project_id).await?;
     cprint!("<g!>Updating</> {}...", project.slug);
     if cur_version == new_version {
         cprintln!("is already latest, no need to update, skipped.")
@@ -114,4 +124,4 @@ pub async fn update_mod<P: AsRef<Path>, S: AsRef<str>>(
         std::fs::remove_file(path)?;
     }
     Ok((project.slug, new_version.version_number))
-}
\ No newline at end of file
+}